@@ -13,3 +13,7 @@ export const allocationDistributionChart : Record<number, AllocationPair>= {
   400: [7000, 3000],
   600: [8000, 2000],
 };
+
+// Virtual shares and USDC every vault is priced with, `VIRTUAL_SHARES` / `VIRTUAL_ASSETS` in the program
+export const VIRTUAL_SHARES = 1_000_000;
+export const VIRTUAL_ASSETS = 1_000_000;
@@ -30,6 +30,32 @@ export const airdropTo = async (
   return tx;
 };
 
+// vault_index seed, little endian u16 like on-chain `vault_index.to_le_bytes()`
+export const vaultIndexSeed = (vaultIndex: number): Buffer => {
+  const seed = Buffer.alloc(2);
+  seed.writeUInt16LE(vaultIndex);
+  return seed;
+};
+
+// ProgramData account of an upgradeable program, it records who can upgrade it and open vaults
+export const programDataAddress = (programId: web3.PublicKey): web3.PublicKey =>
+  web3.PublicKey.findProgramAddressSync([programId.toBuffer()], new web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111"))[0];
+
+// Moves the surfnet clock to `unixTimestamp` (seconds) with the surfnet_timeTravel cheatcode
+export async function timeTravelTo(unixTimestamp: number, rpcUrl = "http://localhost:8899") {
+  const payload = {
+    jsonrpc: "2.0",
+    id: 1,
+    method: "surfnet_timeTravel",
+    params: [{ absoluteTimestamp: unixTimestamp * 1000 }],
+  };
+  const resp = await axios.post(rpcUrl, payload, { headers: { "Content-Type": "application/json" } });
+  if (resp?.data?.error) {
+    throw new Error(JSON.stringify(resp.data.error));
+  }
+  return resp.data.result;
+}
+
 export async function convertUsdcToJupFTokenAmount(
   fTokenMint: web3.PublicKey,
   usdcAmount: anchor.BN, // in smallest units (e.g., 50 * 10^6 for 50 USDC)
@@ -69,7 +95,7 @@ export async function convertJupFTokenToUsdcAmount(
   return usdcAmount;
 }
 
-export async function convertKaminoTokenToUsdcAmount (convertAmount : anchor.BN, accounts : {usdcMint: anchor.web3.PublicKey, admin:anchor.web3.PublicKey, vaultPda: anchor.web3.PublicKey }) {
+export async function convertKaminoTokenToUsdcAmount (convertAmount : anchor.BN, accounts : {usdcMint: anchor.web3.PublicKey, vaultPda: anchor.web3.PublicKey }) {
   const kaminoMainMarket = new anchor.web3.PublicKey("7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF");
   const rpc = initRpc('http://localhost:8899');
   const market = await KaminoMarket.load(rpc as any, kaminoMainMarket.toBase58() as Address, DEFAULT_RECENT_SLOT_DURATION_MS);
@@ -94,8 +120,8 @@ export async function setUSDCViaCheatcode(
     const owner = new web3.PublicKey(receiverAddress);
     const mint = new web3.PublicKey(usdcMintInfo.address);
 
-    // compute Associated Token Account (ATA) for owner+mint
-    const ata = await getAssociatedTokenAddress(mint, owner, false, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
+    // compute Associated Token Account (ATA) for owner+mint, owners can be PDAs
+    const ata = await getAssociatedTokenAddress(mint, owner, true, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID);
     // console.log("ATA computed:", ata.toBase58());
 
     // convert human USDC amount to smallest units
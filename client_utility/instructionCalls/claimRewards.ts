@@ -0,0 +1,36 @@
+import * as anchor from "@coral-xyz/anchor";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
+
+// Accrues JupLend's incentives into the f-token exchange price of every JupLend strategy and
+// books them as yield, anyone can call it
+export async function claimJupRewards(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  accounts: {
+    caller: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+    usdcMint: anchor.web3.PublicKey;
+  }
+) {
+  const strategyAccounts = await getStrategyAccounts(program, provider, accounts.vaultPda, "cpi");
+
+  const tx = await program.methods
+    .claimJupRewards()
+    .accounts({
+      caller: accounts.caller.publicKey,
+      mainVault: accounts.vaultPda,
+      usdcMint: accounts.usdcMint,
+    } as any)
+    .remainingAccounts(strategyAccounts)
+    .preInstructions([
+      ...(await refreshReserveInstructions(program, accounts.vaultPda)),
+      anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+    ])
+    .signers([accounts.caller])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Claim Jup rewards transaction:", tx);
+
+  return tx;
+}
@@ -0,0 +1,46 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
+
+// Sells `amountIn` reward tokens held in the vault's ATA of `rewardMint` for USDC and deploys the
+// proceeds into the strategies. `swapAccounts` are the swap program and the accounts of its pool.
+// Fails with SlippageExceeded if the swap pays less than `minAmountOut` USDC.
+export async function compound(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  amountIn: anchor.BN,
+  minAmountOut: anchor.BN,
+  accounts: {
+    keeper: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+    usdcMint: anchor.web3.PublicKey;
+    rewardMint: anchor.web3.PublicKey;
+  },
+  swapAccounts: anchor.web3.AccountMeta[]
+) {
+  const strategyAccounts = await getStrategyAccounts(program, provider, accounts.vaultPda, "cpi");
+
+  const tx = await program.methods
+    .compound(amountIn, minAmountOut)
+    .accounts({
+      keeper: accounts.keeper.publicKey,
+      mainVault: accounts.vaultPda,
+      mainVaultUsdcAta: getAssociatedTokenAddressSync(accounts.usdcMint, accounts.vaultPda, true),
+      usdcMint: accounts.usdcMint,
+      rewardMint: accounts.rewardMint,
+      vaultRewardAta: getAssociatedTokenAddressSync(accounts.rewardMint, accounts.vaultPda, true),
+      tokenProgram: TOKEN_PROGRAM_ID,
+    } as any)
+    .remainingAccounts([...strategyAccounts, ...swapAccounts])
+    .preInstructions([
+      ...(await refreshReserveInstructions(program, accounts.vaultPda)),
+      anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+    ])
+    .signers([accounts.keeper])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Compound transaction:", tx);
+
+  return tx;
+}
@@ -0,0 +1,63 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  getAssociatedTokenAddressSync,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
+
+// Deposits user USDC and splits it between the strategies by their target weights.
+// Fails with SlippageExceeded if the deposit mints fewer than `minSharesOut` shares, see `minOut` to
+// derive it from `previewDeposit`.
+export async function depositAndAllocate(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  depositAmount: anchor.BN,
+  accounts: {
+    user: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+    usdcMint: anchor.web3.PublicKey;
+  },
+  minSharesOut: anchor.BN
+) {
+  const strategyAccounts = await getStrategyAccounts(program, provider, accounts.vaultPda, "cpi");
+
+  const [userPosition] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("user_position"), accounts.vaultPda.toBuffer(), accounts.user.publicKey.toBuffer()],
+    program.programId
+  );
+  const [shareMint] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("share_mint"), accounts.vaultPda.toBuffer()],
+    program.programId
+  );
+
+  const tx = await program.methods
+    .depositAndAllocate(depositAmount, minSharesOut)
+    .accounts({
+      deposit: {
+        user: accounts.user.publicKey,
+        vault: accounts.vaultPda,
+        userPosition,
+        usdcMint: accounts.usdcMint,
+        userUsdcAta: getAssociatedTokenAddressSync(accounts.usdcMint, accounts.user.publicKey),
+        vaultUsdcAta: getAssociatedTokenAddressSync(accounts.usdcMint, accounts.vaultPda, true),
+        shareMint,
+        userShareAta: getAssociatedTokenAddressSync(shareMint, accounts.user.publicKey),
+        systemProgram: anchor.web3.SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      },
+      tokenProgram: TOKEN_PROGRAM_ID,
+    } as any)
+    .remainingAccounts(strategyAccounts)
+    .preInstructions([
+      ...(await refreshReserveInstructions(program, accounts.vaultPda)),
+      anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+    ])
+    .signers([accounts.user])
+    .rpc({ skipPreflight: true });
+
+  console.log("Deposit and allocate transaction:", tx);
+
+  return tx;
+}
@@ -0,0 +1,38 @@
+import * as anchor from "@coral-xyz/anchor";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+
+export type DepositLimits = {
+  depositCap: anchor.BN;
+  userDepositCap: anchor.BN;
+  minDeposit: anchor.BN;
+};
+
+// No caps and no minimum, the limits a new vault starts with
+export const NO_DEPOSIT_LIMITS: DepositLimits = {
+  depositCap: new anchor.BN("18446744073709551615"),
+  userDepositCap: new anchor.BN("18446744073709551615"),
+  minDeposit: new anchor.BN(0),
+};
+
+// Sets the vault TVL cap, the per-user cap and the minimum deposit
+export async function setDepositLimits(
+  program: anchor.Program<YieldAggregator>,
+  limits: DepositLimits,
+  accounts: {
+    admin: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await program.methods
+    .setDepositLimits(limits.depositCap, limits.userDepositCap, limits.minDeposit)
+    .accounts({
+      admin: accounts.admin.publicKey,
+      mainVault: accounts.vaultPda,
+    })
+    .signers([accounts.admin])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Set deposit limits transaction:", tx);
+
+  return tx;
+}
@@ -0,0 +1,54 @@
+import * as anchor from "@coral-xyz/anchor";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+
+// Anchor's EVENT_IX_TAG, the self-CPIs of `#[event_cpi]` start with it
+const EVENT_IX_TAG = Buffer.from("e445a52e51cb9a1d", "hex");
+
+// Decodes the events emitted by a transaction. The program emits them as self-CPIs, so they are
+// read from the inner instructions rather than from the (truncatable) logs.
+export async function getCpiEvents(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  tx: string
+) {
+  const txDetails = await provider.connection.getTransaction(tx, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  if (!txDetails?.meta?.innerInstructions) {
+    return [];
+  }
+
+  const accountKeys = txDetails.transaction.message.getAccountKeys({
+    accountKeysFromLookups: txDetails.meta.loadedAddresses,
+  });
+
+  const events: anchor.Event[] = [];
+  for (const { instructions } of txDetails.meta.innerInstructions) {
+    for (const ix of instructions) {
+      if (!accountKeys.get(ix.programIdIndex)?.equals(program.programId)) {
+        continue;
+      }
+      const data = Buffer.from(anchor.utils.bytes.bs58.decode(ix.data));
+      if (!data.subarray(0, 8).equals(EVENT_IX_TAG)) {
+        continue;
+      }
+      const event = program.coder.events.decode(data.subarray(8).toString("base64"));
+      if (event) {
+        events.push(event);
+      }
+    }
+  }
+  return events;
+}
+
+// First event named `name` emitted by the transaction
+export async function getCpiEvent(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  tx: string,
+  name: string
+) {
+  const events = await getCpiEvents(program, provider, tx);
+  return events.find((event) => event.name === name)?.data as Record<string, any> | undefined;
+}
@@ -0,0 +1,51 @@
+import * as anchor from "@coral-xyz/anchor";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { runTimelocked } from "./timelock";
+
+export type FeeConfig = {
+  performanceFeeBps: number;
+  managementFeeBps: number;
+  depositFeeBps: number;
+  withdrawFeeBps: number;
+};
+
+// Sets all four vault fees, each one is capped on-chain. Queued, then executed once the clock is
+// moved past the vault's timelock delay.
+export async function setFees(
+  program: anchor.Program<YieldAggregator>,
+  fees: FeeConfig,
+  accounts: {
+    feeManager: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await runTimelocked(program, { setFees: { fees } }, { proposer: accounts.feeManager, vaultPda: accounts.vaultPda });
+
+  console.log("Set fees transaction:", tx);
+  return tx;
+}
+
+// Mints the fee shares booked so far to the vault's fee recipient, anyone can call it
+export async function claimFees(
+  program: anchor.Program<YieldAggregator>,
+  accounts: {
+    payer: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+    feeRecipient: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await program.methods
+    .claimFees()
+    .accounts({
+      payer: accounts.payer.publicKey,
+      mainVault: accounts.vaultPda,
+      feeRecipient: accounts.feeRecipient,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .signers([accounts.payer])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Claim fees transaction:", tx);
+  return tx;
+}
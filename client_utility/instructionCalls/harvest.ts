@@ -0,0 +1,32 @@
+import * as anchor from "@coral-xyz/anchor";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
+
+// Marks every strategy position to market, anyone can call it
+export async function harvest(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  accounts: {
+    caller: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+    usdcMint: anchor.web3.PublicKey;
+  }
+) {
+  const strategyAccounts = await getStrategyAccounts(program, provider, accounts.vaultPda, "valuation");
+
+  const tx = await program.methods
+    .harvest()
+    .accounts({
+      caller: accounts.caller.publicKey,
+      mainVault: accounts.vaultPda,
+      usdcMint: accounts.usdcMint,
+    })
+    .remainingAccounts(strategyAccounts)
+    .preInstructions(await refreshReserveInstructions(program, accounts.vaultPda))
+    .signers([accounts.caller])
+    .rpc();
+
+  console.log("Harvest transaction:", tx);
+
+  return tx;
+}
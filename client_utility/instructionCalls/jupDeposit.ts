@@ -1,5 +1,6 @@
 import * as anchor from "@coral-xyz/anchor";
 import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
 
 
 export async function jupDeposit(
@@ -7,22 +8,24 @@ export async function jupDeposit(
   provider : anchor.AnchorProvider,
   depositAmount : anchor.BN,
   accounts : {
-    admin : anchor.web3.Keypair,
+    keeper : anchor.web3.Keypair,
     usdcMint : anchor.web3.PublicKey,
+    vaultPda : anchor.web3.PublicKey,
 }){
   const { getDepositContext } = await import("@jup-ag/lend/earn");
   
   const jupDepositContext = await getDepositContext({
     asset: accounts.usdcMint,
     connection: provider.connection,
-    signer: accounts.admin.publicKey,
+    signer: accounts.keeper.publicKey,
   });
 
   const tx = await program.methods
     .jupDeposit(depositAmount.sub(new anchor.BN(50)))
     .accounts({
-      admin: accounts.admin.publicKey,
+      keeper: accounts.keeper.publicKey,
       usdcMint: accounts.usdcMint,
+      mainVault: accounts.vaultPda,
       fTokenMint: jupDepositContext.fTokenMint,
       lendingAdmin: jupDepositContext.lendingAdmin,
       lending: jupDepositContext.lending,
@@ -34,7 +37,9 @@ export async function jupDeposit(
       liquidityProgram: jupDepositContext.liquidityProgram,
       rewardsRateModel: jupDepositContext.rewardsRateModel
     })
-    .signers([accounts.admin])
+    .remainingAccounts(await getStrategyAccounts(program, provider, accounts.vaultPda, "valuation"))
+    .preInstructions(await refreshReserveInstructions(program, accounts.vaultPda))
+    .signers([accounts.keeper])
     .rpc({skipPreflight: true});
 
   console.log("Jup deposit transaction:", tx);
@@ -1,13 +1,15 @@
 import * as anchor from "@coral-xyz/anchor";
 import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
 
 export async function jupWithdraw(
   program: anchor.Program<YieldAggregator>,
   provider: anchor.AnchorProvider,
   withdrawAmount: anchor.BN,
   accounts: {
-    admin: anchor.web3.Keypair;
+    keeper: anchor.web3.Keypair;
     usdcMint: anchor.web3.PublicKey;
+    vaultPda: anchor.web3.PublicKey;
   }
 ) {
   const { getWithdrawContext } = await import("@jup-ag/lend/earn");
@@ -15,14 +17,15 @@ export async function jupWithdraw(
   const jupWithdrawContext = await getWithdrawContext({
     asset: accounts.usdcMint,
     connection: provider.connection,
-    signer: accounts.admin.publicKey,
+    signer: accounts.keeper.publicKey,
   });
 
   const tx = await program.methods
     .jupWithdraw(withdrawAmount)
     .accounts({
-      admin: accounts.admin.publicKey,
+      keeper: accounts.keeper.publicKey,
       usdcMint: accounts.usdcMint,
+      mainVault: accounts.vaultPda,
       fTokenMint: jupWithdrawContext.fTokenMint,
       lendingAdmin: jupWithdrawContext.lendingAdmin,
       lending: jupWithdrawContext.lending,
@@ -37,7 +40,9 @@ export async function jupWithdraw(
       liquidityProgram: jupWithdrawContext.liquidityProgram,
       rewardsRateModel: jupWithdrawContext.rewardsRateModel,
     })
-    .signers([accounts.admin])
+    .remainingAccounts(await getStrategyAccounts(program, provider, accounts.vaultPda, "valuation"))
+    .preInstructions(await refreshReserveInstructions(program, accounts.vaultPda))
+    .signers([accounts.keeper])
     .rpc({ skipPreflight: true });
 
   console.log("Jup withdraw transaction:", tx);
@@ -2,6 +2,7 @@ import * as anchor from "@coral-xyz/anchor";
 import { getDepositReserveLiquidityAccounts, initRpc } from "../generate-kamino-accounts";
 import { DEFAULT_RECENT_SLOT_DURATION_MS, KaminoMarket } from "@kamino-finance/klend-sdk";
 import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
 import { Address } from "@solana/kit";
 
 
@@ -11,21 +12,23 @@ export async function kaminoDeposit(
     program : anchor.Program<YieldAggregator>,
     depositAmount : anchor.BN,
     accounts : {
-        admin : anchor.web3.Keypair,
+        keeper : anchor.web3.Keypair,
         usdcMint : anchor.web3.PublicKey,
+        vaultPda : anchor.web3.PublicKey,
     }
 ){
     const kaminoMainMarket = new anchor.web3.PublicKey("7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF");
     const rpc = initRpc('http://localhost:8899');
     const market = await KaminoMarket.load(rpc as any, kaminoMainMarket.toBase58() as Address, DEFAULT_RECENT_SLOT_DURATION_MS);
     const reserve = market.getReserveByMint(accounts.usdcMint.toBase58() as Address);
-    const ixAccounts = await getDepositReserveLiquidityAccounts(accounts.admin.publicKey, reserve.address, kaminoMainMarket.toBase58() as Address, accounts.usdcMint.toBase58() as Address);
+    const ixAccounts = await getDepositReserveLiquidityAccounts(accounts.keeper.publicKey, reserve.address, kaminoMainMarket.toBase58() as Address, accounts.usdcMint.toBase58() as Address);
 
     const tx = await program.methods
         .kaminoDeposit(depositAmount)
         .accounts({
-        admin: accounts.admin.publicKey,
+        keeper: accounts.keeper.publicKey,
         usdcMint: accounts.usdcMint,
+        mainVault: accounts.vaultPda,
         reserve: ixAccounts.reserve,
         lendingMarket: ixAccounts.lendingMarket,
         lendingMarketAuthority: ixAccounts.lendingMarketAuthority,
@@ -36,7 +39,9 @@ export async function kaminoDeposit(
         instructionSysvarAccount: ixAccounts.instructionSysvarAccount,
         klendProgram: KLEND_PROGRAM_ID,
         })
-        .signers([accounts.admin])
+        .remainingAccounts(await getStrategyAccounts(program, program.provider as anchor.AnchorProvider, accounts.vaultPda, "valuation"))
+        .preInstructions(await refreshReserveInstructions(program, accounts.vaultPda))
+        .signers([accounts.keeper])
         .rpc({skipPreflight: true});
 
     console.log("Kamino deposit transaction:", tx);
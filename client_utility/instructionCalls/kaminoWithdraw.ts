@@ -8,6 +8,7 @@ import {
   KaminoMarket,
 } from "@kamino-finance/klend-sdk";
 import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
 import { Address } from "@solana/kit";
 
 const KLEND_PROGRAM_ID = new anchor.web3.PublicKey(
@@ -18,8 +19,9 @@ export async function kaminoWithdraw(
   program: anchor.Program<YieldAggregator>,
   withdrawAmount: anchor.BN,
   accounts: {
-    admin: anchor.web3.Keypair;
+    keeper: anchor.web3.Keypair;
     usdcMint: anchor.web3.PublicKey;
+    vaultPda: anchor.web3.PublicKey;
   }
 ) {
   const kaminoMainMarket = new anchor.web3.PublicKey(
@@ -35,7 +37,7 @@ export async function kaminoWithdraw(
     accounts.usdcMint.toBase58() as Address
   );
   const ixAccounts = await getDepositReserveLiquidityAccounts(
-    accounts.admin.publicKey,
+    accounts.keeper.publicKey,
     reserve.address,
     kaminoMainMarket.toBase58() as Address,
     accounts.usdcMint.toBase58() as Address
@@ -44,8 +46,9 @@ export async function kaminoWithdraw(
   const tx = await program.methods
     .kaminoWithdraw(withdrawAmount)
     .accounts({
-      admin: accounts.admin.publicKey,
+      keeper: accounts.keeper.publicKey,
       usdcMint: accounts.usdcMint,
+      mainVault: accounts.vaultPda,
       reserve: ixAccounts.reserve,
       lendingMarket: ixAccounts.lendingMarket,
       lendingMarketAuthority: ixAccounts.lendingMarketAuthority,
@@ -56,7 +59,9 @@ export async function kaminoWithdraw(
       instructionSysvarAccount: ixAccounts.instructionSysvarAccount,
       klendProgram: KLEND_PROGRAM_ID,
     })
-    .signers([accounts.admin])
+    .remainingAccounts(await getStrategyAccounts(program, provider, accounts.vaultPda, "valuation"))
+    .preInstructions(await refreshReserveInstructions(program, accounts.vaultPda))
+    .signers([accounts.keeper])
     .rpc({ skipPreflight: true });
 
   console.log("Kamino withdraw transaction:", tx);
@@ -0,0 +1,164 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import axios from "axios";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
+
+// Vault from before the share mint, PDA of `[b"vault", authority]`
+export const legacyVaultAddress = (programId: anchor.web3.PublicKey, authority: anchor.web3.PublicKey): anchor.web3.PublicKey =>
+  anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("vault"), authority.toBuffer()], programId)[0];
+
+// User position from before the share mint, PDA of `[b"user_position", user]`
+export const legacyPositionAddress = (programId: anchor.web3.PublicKey, user: anchor.web3.PublicKey): anchor.web3.PublicKey =>
+  anchor.web3.PublicKey.findProgramAddressSync([Buffer.from("user_position"), user.toBuffer()], programId)[0];
+
+const discriminator = (program: anchor.Program<YieldAggregator>, name: string): Buffer =>
+  Buffer.from(program.idl.accounts.find((account) => account.name === name).discriminator);
+
+const u64 = (value: anchor.BN | number): Buffer => new anchor.BN(value).toArrayLike(Buffer, "le", 8);
+
+// Legacy `Vault` account data, laid out like the vault before the share mint
+export function encodeLegacyVault(
+  program: anchor.Program<YieldAggregator>,
+  legacy: {
+    authority: anchor.web3.PublicKey;
+    usdcMint: anchor.web3.PublicKey;
+    totalShares: anchor.BN;
+    totalUnderlying: anchor.BN;
+  }
+): Buffer {
+  const [vault, bump] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vault"), legacy.authority.toBuffer()],
+    program.programId
+  );
+  const allocation = Buffer.alloc(4);
+  allocation.writeUInt16LE(5000, 0);
+  allocation.writeUInt16LE(5000, 2);
+  return Buffer.concat([
+    discriminator(program, "vault"),
+    legacy.authority.toBuffer(),
+    legacy.usdcMint.toBuffer(),
+    getAssociatedTokenAddressSync(legacy.usdcMint, vault, true).toBuffer(),
+    u64(legacy.totalShares),
+    u64(0), // acc_per_share
+    u64(legacy.totalUnderlying),
+    u64(0), // jup_lend_balance
+    u64(0), // kamino_balance
+    u64(0), // last_jup_value
+    u64(0), // last_kamino_value
+    allocation,
+    u64(0), // last_update_ts
+    Buffer.from([bump]),
+  ]);
+}
+
+// Legacy `UserPosition` account data, laid out like the positions before the share mint
+export function encodeLegacyPosition(
+  program: anchor.Program<YieldAggregator>,
+  legacy: { vault: anchor.web3.PublicKey; user: anchor.web3.PublicKey; shares: anchor.BN; pendingRewards: anchor.BN }
+): Buffer {
+  const [, bump] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("user_position"), legacy.user.toBuffer()],
+    program.programId
+  );
+  return Buffer.concat([
+    discriminator(program, "userPosition"),
+    legacy.vault.toBuffer(),
+    legacy.user.toBuffer(),
+    u64(legacy.shares),
+    Buffer.alloc(16), // reward_debt
+    u64(legacy.pendingRewards),
+    u64(0), // last_updated
+    Buffer.from([bump]),
+  ]);
+}
+
+// Writes a program account with the surfnet_setAccount cheatcode, to stand in for accounts
+// created by earlier program versions
+export async function setProgramAccountViaCheatcode(
+  connection: anchor.web3.Connection,
+  program: anchor.Program<YieldAggregator>,
+  address: anchor.web3.PublicKey,
+  data: Buffer,
+  rpcUrl = "http://localhost:8899"
+) {
+  const payload = {
+    jsonrpc: "2.0",
+    id: 1,
+    method: "surfnet_setAccount",
+    params: [
+      address.toBase58(),
+      {
+        lamports: await connection.getMinimumBalanceForRentExemption(data.length),
+        data: data.toString("hex"),
+        owner: program.programId.toBase58(),
+        executable: false,
+      },
+    ],
+  };
+  const resp = await axios.post(rpcUrl, payload, { headers: { "Content-Type": "application/json" } });
+  if (resp?.data?.error) {
+    throw new Error(JSON.stringify(resp.data.error));
+  }
+  return resp.data.result;
+}
+
+// Moves the funds of the legacy vault of `authority` into `vaultPda` and books vault shares for
+// its holders. `legacyPositions` are the legacy vault's position token accounts to move along.
+export async function migrateVault(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  accounts: {
+    authority: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+    usdcMint: anchor.web3.PublicKey;
+  },
+  legacyPositions: anchor.web3.PublicKey[] = []
+) {
+  const legacyVault = legacyVaultAddress(program.programId, accounts.authority.publicKey);
+  const strategyAccounts = await getStrategyAccounts(program, provider, accounts.vaultPda, "valuation");
+
+  const tx = await program.methods
+    .migrateVault()
+    .accounts({
+      authority: accounts.authority.publicKey,
+      vault: accounts.vaultPda,
+      legacyUsdcAta: getAssociatedTokenAddressSync(accounts.usdcMint, legacyVault, true),
+      usdcMint: accounts.usdcMint,
+    } as any)
+    .remainingAccounts([
+      ...strategyAccounts,
+      ...legacyPositions.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
+    ])
+    .preInstructions(await refreshReserveInstructions(program, accounts.vaultPda))
+    .signers([accounts.authority])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Migrate vault transaction:", tx);
+
+  return tx;
+}
+
+// Exchanges the legacy position of `user` for share tokens of `vaultPda` and closes it
+export async function migrateUserPosition(
+  program: anchor.Program<YieldAggregator>,
+  accounts: {
+    user: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+    legacyAuthority: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await program.methods
+    .migrateUserPosition()
+    .accounts({
+      user: accounts.user.publicKey,
+      vault: accounts.vaultPda,
+      migratedVault: legacyVaultAddress(program.programId, accounts.legacyAuthority),
+    } as any)
+    .signers([accounts.user])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Migrate user position transaction:", tx);
+
+  return tx;
+}
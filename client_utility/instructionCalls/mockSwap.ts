@@ -0,0 +1,76 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { MockSwap } from "../../target/types/mock_swap";
+
+// 1e9 `price` pays out one output base unit per input base unit
+export const MOCK_SWAP_PRICE_SCALE = new anchor.BN(1_000_000_000);
+
+export type MockSwapPool = {
+  pool: anchor.web3.PublicKey;
+  inputMint: anchor.web3.PublicKey;
+  outputMint: anchor.web3.PublicKey;
+  inputAta: anchor.web3.PublicKey;
+  outputAta: anchor.web3.PublicKey;
+};
+
+export function mockSwapPool(
+  swapProgram: anchor.Program<MockSwap>,
+  inputMint: anchor.web3.PublicKey,
+  outputMint: anchor.web3.PublicKey
+): MockSwapPool {
+  const [pool] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("pool"), inputMint.toBuffer(), outputMint.toBuffer()],
+    swapProgram.programId
+  );
+  return {
+    pool,
+    inputMint,
+    outputMint,
+    inputAta: getAssociatedTokenAddressSync(inputMint, pool, true),
+    outputAta: getAssociatedTokenAddressSync(outputMint, pool, true),
+  };
+}
+
+// Opens a fixed-price pool paying `price` / MOCK_SWAP_PRICE_SCALE output per input,
+// its output ATA has to be funded before it can pay anything
+export async function initializeMockSwapPool(
+  swapProgram: anchor.Program<MockSwap>,
+  price: anchor.BN,
+  accounts: {
+    payer: anchor.web3.Keypair;
+    inputMint: anchor.web3.PublicKey;
+    outputMint: anchor.web3.PublicKey;
+  }
+) {
+  const pool = mockSwapPool(swapProgram, accounts.inputMint, accounts.outputMint);
+
+  const tx = await swapProgram.methods
+    .initializePool(price)
+    .accounts({
+      payer: accounts.payer.publicKey,
+      inputMint: accounts.inputMint,
+      outputMint: accounts.outputMint,
+      pool: pool.pool,
+      poolInputAta: pool.inputAta,
+      poolOutputAta: pool.outputAta,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    } as any)
+    .signers([accounts.payer])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Initialize mock swap pool transaction:", tx);
+
+  return pool;
+}
+
+// Accounts `compound` passes on to the swap program after the strategy accounts
+export function mockSwapAccounts(swapProgram: anchor.Program<MockSwap>, pool: MockSwapPool): anchor.web3.AccountMeta[] {
+  return [
+    { pubkey: swapProgram.programId, isSigner: false, isWritable: false },
+    { pubkey: pool.pool, isSigner: false, isWritable: false },
+    { pubkey: pool.inputMint, isSigner: false, isWritable: false },
+    { pubkey: pool.outputMint, isSigner: false, isWritable: false },
+    { pubkey: pool.inputAta, isSigner: false, isWritable: true },
+    { pubkey: pool.outputAta, isSigner: false, isWritable: true },
+  ];
+}
@@ -0,0 +1,36 @@
+import * as anchor from "@coral-xyz/anchor";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
+
+// Moves vault funds between the strategies to match the given weights, in registry order, accounting is derived on-chain
+export async function rebalance(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  weights: number[],
+  accounts: {
+    keeper: anchor.web3.Keypair;
+    usdcMint: anchor.web3.PublicKey;
+    vaultPda: anchor.web3.PublicKey;
+  }
+) {
+  const strategyAccounts = await getStrategyAccounts(program, provider, accounts.vaultPda, "cpi");
+
+  const tx = await program.methods
+    .rebalance(weights)
+    .accounts({
+      keeper: accounts.keeper.publicKey,
+      usdcMint: accounts.usdcMint,
+      mainVault: accounts.vaultPda,
+    })
+    .remainingAccounts(strategyAccounts)
+    .preInstructions([
+      ...(await refreshReserveInstructions(program, accounts.vaultPda)),
+      anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+    ])
+    .signers([accounts.keeper])
+    .rpc({ skipPreflight: true });
+
+  console.log("Rebalance transaction:", tx);
+
+  return tx;
+}
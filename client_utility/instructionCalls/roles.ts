@@ -0,0 +1,52 @@
+import * as anchor from "@coral-xyz/anchor";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+
+export type Role = { keeper: {} } | { guardian: {} } | { feeManager: {} };
+
+// Assigns `role` to `member`, replacing its previous holder. The admin always holds every role.
+export async function setRole(
+  program: anchor.Program<YieldAggregator>,
+  role: Role,
+  accounts: {
+    admin: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+    member: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await program.methods
+    .setRole(role as any)
+    .accounts({
+      admin: accounts.admin.publicKey,
+      mainVault: accounts.vaultPda,
+      member: accounts.member,
+    } as any)
+    .signers([accounts.admin])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Set role transaction:", tx);
+
+  return tx;
+}
+
+// Unassigns `role`, only the admin holds it afterwards. Takes effect at once, the guardian included.
+export async function revokeRole(
+  program: anchor.Program<YieldAggregator>,
+  role: Role,
+  accounts: {
+    admin: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await program.methods
+    .revokeRole(role as any)
+    .accounts({
+      admin: accounts.admin.publicKey,
+      mainVault: accounts.vaultPda,
+    } as any)
+    .signers([accounts.admin])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Revoke role transaction:", tx);
+
+  return tx;
+}
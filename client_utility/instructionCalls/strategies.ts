@@ -0,0 +1,265 @@
+import * as anchor from "@coral-xyz/anchor";
+import {
+  createAssociatedTokenAccountIdempotentInstruction,
+  getAssociatedTokenAddressSync,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import { Address } from "@solana/kit";
+import { getDepositReserveLiquidityAccounts } from "../generate-kamino-accounts";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { runTimelocked } from "./timelock";
+
+const KLEND_PROGRAM_ID = new anchor.web3.PublicKey(
+  "KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD"
+);
+const JUP_LENDING_PROGRAM_ID = new anchor.web3.PublicKey(
+  "jup3YeL8QhtSx1e253b2FDvsMNC87fDrgQZivbrndc9"
+);
+const KAMINO_MAIN_MARKET = new anchor.web3.PublicKey(
+  "7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF"
+);
+
+// Offsets in a klend reserve, discriminator included, of its lending market and the oracles of
+// `config.token_info`
+const RESERVE_LENDING_MARKET_OFFSET = 32;
+const RESERVE_SCOPE_PRICE_FEED_OFFSET = 5112;
+const RESERVE_SWITCHBOARD_PRICE_OFFSET = 5160;
+const RESERVE_SWITCHBOARD_TWAP_OFFSET = 5192;
+const RESERVE_PYTH_PRICE_OFFSET = 5224;
+// sha256("global:refresh_reserve")[0..8]
+const REFRESH_RESERVE_DISCRIMINATOR = Buffer.from([2, 218, 138, 235, 79, 201, 25, 102]);
+
+const meta = (pubkey: anchor.web3.PublicKey, isWritable = false): anchor.web3.AccountMeta => ({
+  pubkey: new anchor.web3.PublicKey(pubkey),
+  isSigner: false,
+  isWritable,
+});
+
+export type StrategyKind = { jupLend: {} } | { kamino: {} };
+
+// Registers a lending market on the vault with no target weight. Queued, then executed once the
+// clock is moved past the vault's timelock delay.
+export async function addStrategy(
+  program: anchor.Program<YieldAggregator>,
+  kind: StrategyKind,
+  cap: anchor.BN,
+  accounts: {
+    admin: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+    positionMint: anchor.web3.PublicKey;
+    market: anchor.web3.PublicKey;
+  }
+) {
+  const positionTokenAccount = getAssociatedTokenAddressSync(accounts.positionMint, accounts.vaultPda, true, TOKEN_PROGRAM_ID);
+  const tx = await runTimelocked(
+    program,
+    {
+      addStrategy: {
+        kind,
+        cap,
+        positionTokenAccount,
+        market: accounts.market,
+        program: "jupLend" in kind ? JUP_LENDING_PROGRAM_ID : KLEND_PROGRAM_ID,
+      },
+    },
+    { proposer: accounts.admin, vaultPda: accounts.vaultPda },
+    {
+      preInstructions: [
+        createAssociatedTokenAccountIdempotentInstruction(
+          accounts.admin.publicKey,
+          positionTokenAccount,
+          accounts.vaultPda,
+          accounts.positionMint,
+          TOKEN_PROGRAM_ID
+        ),
+      ],
+      remainingAccounts: [meta(positionTokenAccount), meta(accounts.market)],
+    }
+  );
+
+  console.log("Add strategy transaction:", tx);
+
+  return tx;
+}
+
+// Sets the target weight of every strategy at once, in registry order, they must sum to 10000
+export async function setStrategyWeight(
+  program: anchor.Program<YieldAggregator>,
+  weights: number[],
+  accounts: {
+    keeper: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await program.methods
+    .setStrategyWeight(weights)
+    .accounts({
+      keeper: accounts.keeper.publicKey,
+      mainVault: accounts.vaultPda,
+    })
+    .signers([accounts.keeper])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Set strategy weight transaction:", tx);
+
+  return tx;
+}
+
+export type WeightBounds = { minBps: number; maxBps: number };
+
+// Sets the weight bounds of every strategy, in registry order, and how fast weights can move.
+// Queued, then executed once the clock is moved past the vault's timelock delay.
+export async function setAllocationBounds(
+  program: anchor.Program<YieldAggregator>,
+  bounds: WeightBounds[],
+  maxWeightChangeBps: number,
+  weightChangeCooldown: anchor.BN,
+  accounts: {
+    admin: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await runTimelocked(
+    program,
+    { setAllocationBounds: { bounds, maxWeightChangeBps, weightChangeCooldown } },
+    { proposer: accounts.admin, vaultPda: accounts.vaultPda }
+  );
+
+  console.log("Set allocation bounds transaction:", tx);
+
+  return tx;
+}
+
+// Removes an empty strategy, or disables one that still holds a position
+export async function removeStrategy(
+  program: anchor.Program<YieldAggregator>,
+  strategyIndex: number,
+  accounts: {
+    admin: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+  }
+) {
+  const vault = await program.account.vault.fetch(accounts.vaultPda, "confirmed");
+  const strategy = vault.strategies[strategyIndex];
+  const tx = await program.methods
+    .removeStrategy(strategyIndex)
+    .accounts({
+      admin: accounts.admin.publicKey,
+      mainVault: accounts.vaultPda,
+    } as any)
+    // The strategy's valuation accounts, its position is priced to tell a drained strategy from a live one
+    .remainingAccounts([meta(strategy.positionTokenAccount), meta(strategy.market)])
+    .preInstructions(await refreshReserveInstructions(program, accounts.vaultPda))
+    .signers([accounts.admin])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Remove strategy transaction:", tx);
+
+  return tx;
+}
+
+// Remaining accounts of every strategy of the vault, in registry order. "valuation" only prices the
+// positions, "cpi" also lets the program deposit into and withdraw from every market.
+export async function getStrategyAccounts(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  vaultPda: anchor.web3.PublicKey,
+  mode: "valuation" | "cpi"
+): Promise<anchor.web3.AccountMeta[]> {
+  const vault = await program.account.vault.fetch(vaultPda, "confirmed");
+  const remainingAccounts: anchor.web3.AccountMeta[] = [];
+
+  for (const strategy of vault.strategies) {
+    if (mode === "valuation") {
+      remainingAccounts.push(meta(strategy.positionTokenAccount), meta(strategy.market));
+      continue;
+    }
+
+    if ("jupLend" in strategy.kind) {
+      const { getWithdrawContext } = await import("@jup-ag/lend/earn");
+      const jupContext = await getWithdrawContext({
+        asset: vault.usdcMint,
+        connection: provider.connection,
+        signer: provider.wallet.publicKey,
+      });
+      remainingAccounts.push(
+        meta(strategy.positionTokenAccount, true),
+        meta(strategy.market, true),
+        meta(jupContext.fTokenMint, true),
+        meta(jupContext.lendingAdmin),
+        meta(jupContext.supplyTokenReservesLiquidity, true),
+        meta(jupContext.lendingSupplyPositionOnLiquidity, true),
+        meta(jupContext.rateModel),
+        meta(jupContext.vault, true),
+        meta(jupContext.liquidity, true),
+        meta(jupContext.liquidityProgram, true),
+        meta(jupContext.rewardsRateModel),
+        meta(JUP_LENDING_PROGRAM_ID),
+        meta(anchor.utils.token.ASSOCIATED_PROGRAM_ID),
+        meta(anchor.web3.SystemProgram.programId),
+        meta(jupContext.claimAccount, true)
+      );
+    } else {
+      const ixAccounts = await getDepositReserveLiquidityAccounts(
+        provider.wallet.publicKey,
+        strategy.market.toBase58() as Address,
+        KAMINO_MAIN_MARKET.toBase58() as Address,
+        vault.usdcMint.toBase58() as Address
+      );
+      remainingAccounts.push(
+        meta(strategy.positionTokenAccount, true),
+        meta(strategy.market, true),
+        meta(ixAccounts.lendingMarket),
+        meta(ixAccounts.lendingMarketAuthority),
+        meta(ixAccounts.reserveLiquiditySupply, true),
+        meta(ixAccounts.reserveCollateralMint, true),
+        meta(ixAccounts.collateralTokenProgram),
+        meta(ixAccounts.liquidityTokenProgram),
+        meta(ixAccounts.instructionSysvarAccount),
+        meta(KLEND_PROGRAM_ID)
+      );
+    }
+  }
+
+  return remainingAccounts;
+}
+
+// klend `refresh_reserve` of every Kamino strategy of the vault. Anything that moves the vault's
+// funds, shares or books prices its reserves only if they were refreshed earlier in the same
+// slot, so these go first in the transaction.
+export async function refreshReserveInstructions(
+  program: anchor.Program<YieldAggregator>,
+  vaultPda: anchor.web3.PublicKey
+): Promise<anchor.web3.TransactionInstruction[]> {
+  const vault = await program.account.vault.fetch(vaultPda, "confirmed");
+  const instructions: anchor.web3.TransactionInstruction[] = [];
+
+  for (const strategy of vault.strategies) {
+    if (!("kamino" in strategy.kind)) {
+      continue;
+    }
+    const reserve = await program.provider.connection.getAccountInfo(strategy.market, "confirmed");
+    const key = (offset: number) => new anchor.web3.PublicKey(reserve.data.subarray(offset, offset + 32));
+    // Oracles the reserve doesn't use are passed as the klend program, like any missing optional account
+    const oracle = (offset: number) => {
+      const oracleKey = key(offset);
+      return oracleKey.equals(anchor.web3.PublicKey.default) ? KLEND_PROGRAM_ID : oracleKey;
+    };
+    instructions.push(
+      new anchor.web3.TransactionInstruction({
+        programId: KLEND_PROGRAM_ID,
+        keys: [
+          meta(strategy.market, true),
+          meta(key(RESERVE_LENDING_MARKET_OFFSET)),
+          meta(oracle(RESERVE_PYTH_PRICE_OFFSET)),
+          meta(oracle(RESERVE_SWITCHBOARD_PRICE_OFFSET)),
+          meta(oracle(RESERVE_SWITCHBOARD_TWAP_OFFSET)),
+          meta(oracle(RESERVE_SCOPE_PRICE_FEED_OFFSET)),
+        ],
+        data: REFRESH_RESERVE_DISCRIMINATOR,
+      })
+    );
+  }
+
+  return instructions;
+}
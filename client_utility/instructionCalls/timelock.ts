@@ -0,0 +1,206 @@
+import * as anchor from "@coral-xyz/anchor";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { timeTravelTo } from "../helper-fns";
+import type { FeeConfig } from "./fees";
+import type { StrategyKind, WeightBounds } from "./strategies";
+
+export type TimelockAction =
+  | { setAllocationBounds: { bounds: WeightBounds[]; maxWeightChangeBps: number; weightChangeCooldown: anchor.BN } }
+  | { setFees: { fees: FeeConfig } }
+  | {
+      addStrategy: {
+        kind: StrategyKind;
+        cap: anchor.BN;
+        positionTokenAccount: anchor.web3.PublicKey;
+        market: anchor.web3.PublicKey;
+        program: anchor.web3.PublicKey;
+      };
+    }
+  | { setGuardian: { guardian: anchor.web3.PublicKey } }
+  | { setTimelockDelay: { delay: anchor.BN } }
+  | { setSwapProgram: { swapProgram: anchor.web3.PublicKey } };
+
+export function queuedActionPda(
+  program: anchor.Program<YieldAggregator>,
+  vaultPda: anchor.web3.PublicKey,
+  id: anchor.BN
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("queued_action"), vaultPda.toBuffer(), id.toArrayLike(Buffer, "le", 8)],
+    program.programId
+  )[0];
+}
+
+async function queueActionBuilder(
+  program: anchor.Program<YieldAggregator>,
+  action: TimelockAction,
+  accounts: { proposer: anchor.web3.Keypair; vaultPda: anchor.web3.PublicKey }
+) {
+  const vault = await program.account.vault.fetch(accounts.vaultPda, "confirmed");
+  const queuedAction = queuedActionPda(program, accounts.vaultPda, vault.nextActionId);
+  const builder = program.methods
+    .queueAction(action as any)
+    .accounts({
+      proposer: accounts.proposer.publicKey,
+      mainVault: accounts.vaultPda,
+      queuedAction,
+    } as any)
+    .signers([accounts.proposer]);
+  return { builder, queuedAction, id: vault.nextActionId };
+}
+
+function executeActionBuilder(
+  program: anchor.Program<YieldAggregator>,
+  queuedAction: anchor.web3.PublicKey,
+  accounts: {
+    executor: anchor.web3.PublicKey;
+    proposer: anchor.web3.PublicKey;
+    vaultPda: anchor.web3.PublicKey;
+  },
+  remainingAccounts: anchor.web3.AccountMeta[] = []
+) {
+  return program.methods
+    .executeAction()
+    .accounts({
+      executor: accounts.executor,
+      mainVault: accounts.vaultPda,
+      queuedAction,
+      proposer: accounts.proposer,
+    } as any)
+    .remainingAccounts(remainingAccounts);
+}
+
+// Queues a risk-sensitive change, returns the id and address of the queued action
+export async function queueAction(
+  program: anchor.Program<YieldAggregator>,
+  action: TimelockAction,
+  accounts: { proposer: anchor.web3.Keypair; vaultPda: anchor.web3.PublicKey }
+) {
+  const { builder, queuedAction, id } = await queueActionBuilder(program, action, accounts);
+  const tx = await builder.rpc({ commitment: "confirmed" });
+
+  console.log("Queue action transaction:", tx);
+
+  return { tx, queuedAction, id };
+}
+
+// Applies a queued action once its delay has passed, anyone can call it
+export async function executeAction(
+  program: anchor.Program<YieldAggregator>,
+  queuedAction: anchor.web3.PublicKey,
+  accounts: { executor: anchor.web3.Keypair; vaultPda: anchor.web3.PublicKey },
+  remainingAccounts: anchor.web3.AccountMeta[] = []
+) {
+  const queued = await program.account.queuedAction.fetch(queuedAction, "confirmed");
+  const tx = await executeActionBuilder(
+    program,
+    queuedAction,
+    { executor: accounts.executor.publicKey, proposer: queued.proposer, vaultPda: accounts.vaultPda },
+    remainingAccounts
+  )
+    .signers([accounts.executor])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Execute action transaction:", tx);
+
+  return tx;
+}
+
+// Drops a queued action before it is executed, signed by the guardian or the admin. Only the admin
+// can drop a guardian change.
+export async function cancelAction(
+  program: anchor.Program<YieldAggregator>,
+  queuedAction: anchor.web3.PublicKey,
+  accounts: { signer: anchor.web3.Keypair; vaultPda: anchor.web3.PublicKey }
+) {
+  const queued = await program.account.queuedAction.fetch(queuedAction, "confirmed");
+  const tx = await program.methods
+    .cancelAction()
+    .accounts({
+      signer: accounts.signer.publicKey,
+      mainVault: accounts.vaultPda,
+      queuedAction,
+      proposer: queued.proposer,
+    } as any)
+    .signers([accounts.signer])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Cancel action transaction:", tx);
+
+  return tx;
+}
+
+// Moves the clock past the eta of a queued action, so it can be executed
+export async function waitOutTimelock(program: anchor.Program<YieldAggregator>, queuedAction: anchor.web3.PublicKey) {
+  const queued = await program.account.queuedAction.fetch(queuedAction, "confirmed");
+  await timeTravelTo(queued.eta.toNumber() + 1);
+}
+
+// Queues `action`, moves the clock past the vault's timelock delay and executes it
+export async function runTimelocked(
+  program: anchor.Program<YieldAggregator>,
+  action: TimelockAction,
+  accounts: { proposer: anchor.web3.Keypair; vaultPda: anchor.web3.PublicKey },
+  options: {
+    preInstructions?: anchor.web3.TransactionInstruction[];
+    remainingAccounts?: anchor.web3.AccountMeta[];
+  } = {}
+) {
+  const { builder, queuedAction } = await queueActionBuilder(program, action, accounts);
+  await builder.preInstructions(options.preInstructions ?? []).rpc({ commitment: "confirmed" });
+  await waitOutTimelock(program, queuedAction);
+
+  return executeActionBuilder(
+    program,
+    queuedAction,
+    { executor: accounts.proposer.publicKey, proposer: accounts.proposer.publicKey, vaultPda: accounts.vaultPda },
+    options.remainingAccounts
+  )
+    .signers([accounts.proposer])
+    .rpc({ commitment: "confirmed" });
+}
+
+// Proposes `newAuthority` as the next admin, it takes over once it calls `acceptAuthority`
+export async function proposeAuthority(
+  program: anchor.Program<YieldAggregator>,
+  accounts: {
+    admin: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+    newAuthority: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await program.methods
+    .proposeAuthority()
+    .accounts({
+      admin: accounts.admin.publicKey,
+      mainVault: accounts.vaultPda,
+      newAuthority: accounts.newAuthority,
+    } as any)
+    .signers([accounts.admin])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Propose authority transaction:", tx);
+
+  return tx;
+}
+
+export async function acceptAuthority(
+  program: anchor.Program<YieldAggregator>,
+  accounts: {
+    newAuthority: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await program.methods
+    .acceptAuthority()
+    .accounts({
+      newAuthority: accounts.newAuthority.publicKey,
+      mainVault: accounts.vaultPda,
+    } as any)
+    .signers([accounts.newAuthority])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Accept authority transaction:", tx);
+
+  return tx;
+}
@@ -0,0 +1,64 @@
+import * as anchor from "@coral-xyz/anchor";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
+
+export type VaultStatus =
+  | { active: {} }
+  | { depositsPaused: {} }
+  | { withdrawOnly: {} }
+  | { shutdown: {} };
+
+// Moves the vault to `status`, the guardian can only tighten it, the admin can also relax it
+export async function setVaultStatus(
+  program: anchor.Program<YieldAggregator>,
+  status: VaultStatus,
+  accounts: {
+    signer: anchor.web3.Keypair;
+    vaultPda: anchor.web3.PublicKey;
+  }
+) {
+  const tx = await program.methods
+    .setVaultStatus(status as any)
+    .accounts({
+      signer: accounts.signer.publicKey,
+      mainVault: accounts.vaultPda,
+    } as any)
+    .signers([accounts.signer])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Set vault status transaction:", tx);
+
+  return tx;
+}
+
+// Redeems every strategy position back into the vault ATA, the vault must be WithdrawOnly or Shutdown
+export async function emergencyUnwind(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  accounts: {
+    signer: anchor.web3.Keypair;
+    usdcMint: anchor.web3.PublicKey;
+    vaultPda: anchor.web3.PublicKey;
+  }
+) {
+  const strategyAccounts = await getStrategyAccounts(program, provider, accounts.vaultPda, "cpi");
+
+  const tx = await program.methods
+    .emergencyUnwind()
+    .accounts({
+      signer: accounts.signer.publicKey,
+      mainVault: accounts.vaultPda,
+      usdcMint: accounts.usdcMint,
+    } as any)
+    .remainingAccounts(strategyAccounts)
+    .preInstructions([
+      ...(await refreshReserveInstructions(program, accounts.vaultPda)),
+      anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+    ])
+    .signers([accounts.signer])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Emergency unwind transaction:", tx);
+
+  return tx;
+}
@@ -0,0 +1,96 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
+
+// Accounts every vault view prices the vault with, the strategy positions go in as remaining accounts.
+// The views price from reserves refreshed in the same slot like deposits and withdrawals, so
+// `refreshInstructions` are simulated first.
+export type VaultViewAccounts = {
+  accounts: {
+    mainVault: anchor.web3.PublicKey;
+    mainVaultUsdcAta: anchor.web3.PublicKey;
+  };
+  strategyAccounts: anchor.web3.AccountMeta[];
+  refreshInstructions: anchor.web3.TransactionInstruction[];
+};
+
+export async function getVaultViewAccounts(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  vaultPda: anchor.web3.PublicKey
+): Promise<VaultViewAccounts> {
+  const vault = await program.account.vault.fetch(vaultPda, "confirmed");
+  return {
+    accounts: {
+      mainVault: vaultPda,
+      mainVaultUsdcAta: vault.vaultUsdcAta,
+    },
+    strategyAccounts: await getStrategyAccounts(program, provider, vaultPda, "valuation"),
+    refreshInstructions: await refreshReserveInstructions(program, vaultPda),
+  };
+}
+
+// Slippage bounds `toleranceBps` away from a preview, against the caller: the least a deposit or
+// `withdrawShares` should get out, and the most shares `withdrawAssets` should burn
+export const minOut = (preview: anchor.BN, toleranceBps: number): anchor.BN =>
+  preview.muln(10_000 - toleranceBps).divn(10_000);
+
+export const maxIn = (preview: anchor.BN, toleranceBps: number): anchor.BN =>
+  preview.muln(10_000 + toleranceBps).addn(9_999).divn(10_000);
+
+// The views are simulated, nothing is sent on-chain
+export async function convertToShares(program: anchor.Program<YieldAggregator>, view: VaultViewAccounts, assets: anchor.BN): Promise<anchor.BN> {
+  return program.methods.convertToShares(assets).accounts(view.accounts).remainingAccounts(view.strategyAccounts).preInstructions(view.refreshInstructions).view();
+}
+
+export async function convertToAssets(program: anchor.Program<YieldAggregator>, view: VaultViewAccounts, shares: anchor.BN): Promise<anchor.BN> {
+  return program.methods.convertToAssets(shares).accounts(view.accounts).remainingAccounts(view.strategyAccounts).preInstructions(view.refreshInstructions).view();
+}
+
+export async function previewDeposit(program: anchor.Program<YieldAggregator>, view: VaultViewAccounts, assets: anchor.BN): Promise<anchor.BN> {
+  return program.methods.previewDeposit(assets).accounts(view.accounts).remainingAccounts(view.strategyAccounts).preInstructions(view.refreshInstructions).view();
+}
+
+export async function previewWithdraw(program: anchor.Program<YieldAggregator>, view: VaultViewAccounts, assets: anchor.BN): Promise<anchor.BN> {
+  return program.methods.previewWithdraw(assets).accounts(view.accounts).remainingAccounts(view.strategyAccounts).preInstructions(view.refreshInstructions).view();
+}
+
+// The share ATA of users that never deposited is skipped if it doesn't exist yet
+export async function maxDeposit(
+  program: anchor.Program<YieldAggregator>,
+  view: VaultViewAccounts,
+  user: anchor.web3.PublicKey,
+  shareMint: anchor.web3.PublicKey
+): Promise<anchor.BN> {
+  const userShareAta = getAssociatedTokenAddressSync(shareMint, user);
+  const shareAtaExists = (await program.provider.connection.getAccountInfo(userShareAta)) !== null;
+  return program.methods
+    .maxDeposit()
+    .accountsPartial({
+      view: view.accounts,
+      user,
+      userShareAta: shareAtaExists ? userShareAta : null,
+    })
+    .remainingAccounts(view.strategyAccounts)
+    .preInstructions(view.refreshInstructions)
+    .view();
+}
+
+export async function maxWithdraw(
+  program: anchor.Program<YieldAggregator>,
+  view: VaultViewAccounts,
+  user: anchor.web3.PublicKey,
+  shareMint: anchor.web3.PublicKey
+): Promise<anchor.BN> {
+  return program.methods
+    .maxWithdraw()
+    .accountsPartial({
+      view: view.accounts,
+      user,
+      userShareAta: getAssociatedTokenAddressSync(shareMint, user),
+    })
+    .remainingAccounts(view.strategyAccounts)
+    .preInstructions(view.refreshInstructions)
+    .view();
+}
@@ -0,0 +1,69 @@
+import * as anchor from "@coral-xyz/anchor";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { YieldAggregator } from "../../target/types/yield_aggregator";
+import { getCpiEvent } from "./events";
+import { getStrategyAccounts, refreshReserveInstructions } from "./strategies";
+
+type WithdrawAccounts = {
+  user: anchor.web3.Keypair;
+  usdcMint: anchor.web3.PublicKey;
+  vaultPda: anchor.web3.PublicKey;
+};
+
+// Burns `shares` of the user for USDC, returns the transaction signature and the emitted WithdrawEvent.
+// Fails with SlippageExceeded if the shares are worth less than `minAssetsOut`, see `minOut` to derive
+// it from a preview.
+export async function withdrawShares(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  shares: anchor.BN,
+  accounts: WithdrawAccounts,
+  minAssetsOut: anchor.BN
+) {
+  return withdraw(program, provider, "withdrawShares", shares, minAssetsOut, accounts);
+}
+
+// Withdraws exactly `amount` USDC for the user, returns the transaction signature and the emitted WithdrawEvent.
+// Fails with SlippageExceeded if it takes more than `maxSharesIn` shares, see `maxIn` to derive it
+// from `previewWithdraw`.
+export async function withdrawAssets(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  amount: anchor.BN,
+  accounts: WithdrawAccounts,
+  maxSharesIn: anchor.BN
+) {
+  return withdraw(program, provider, "withdrawAssets", amount, maxSharesIn, accounts);
+}
+
+async function withdraw(
+  program: anchor.Program<YieldAggregator>,
+  provider: anchor.AnchorProvider,
+  method: "withdrawShares" | "withdrawAssets",
+  value: anchor.BN,
+  slippageBound: anchor.BN,
+  accounts: WithdrawAccounts
+) {
+  const strategyAccounts = await getStrategyAccounts(program, provider, accounts.vaultPda, "cpi");
+
+  const tx = await program.methods[method](value, slippageBound)
+    .accounts({
+      user: accounts.user.publicKey,
+      usdcMint: accounts.usdcMint,
+      mainVault: accounts.vaultPda,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    })
+    .remainingAccounts(strategyAccounts)
+    .preInstructions([
+      ...(await refreshReserveInstructions(program, accounts.vaultPda)),
+      anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 }),
+    ])
+    .signers([accounts.user])
+    .rpc({ commitment: "confirmed" });
+
+  console.log("Withdraw transaction:", tx);
+
+  const withdrawEvent = await getCpiEvent(program, provider, tx, "withdrawEvent");
+
+  return { tx, withdrawEvent };
+}
@@ -0,0 +1,207 @@
+// anchor 0.31 `#[program]` still expands to the deprecated `AccountInfo::realloc`
+#![allow(deprecated)]
+
+//! Fixed-price swap pools for local tests. Implements the swap interface the yield aggregator
+//! sells reward tokens through: `swap(amount_in, min_amount_out)` with the authority, source,
+//! destination and token program first and the pool accounts after them.
+
+use anchor_lang::prelude::*;
+use anchor_spl::{associated_token::AssociatedToken, token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked}};
+
+declare_id!("2JdS1W62hPtuKnTwHD8ySqtuGNj65ruG9MVqRPYoVpWu");
+
+/// Scale of `Pool::price`
+pub const PRICE_SCALE: u128 = 1_000_000_000;
+
+#[program]
+pub mod mock_swap {
+    use super::*;
+
+    pub fn initialize_pool(ctx: Context<InitializePool>, price: u64) -> Result<()> {
+        ctx.accounts.pool.set_inner(Pool {
+            input_mint: ctx.accounts.input_mint.key(),
+            output_mint: ctx.accounts.output_mint.key(),
+            price,
+            bump: ctx.bumps.pool,
+        });
+        Ok(())
+    }
+
+    pub fn set_price(ctx: Context<SetPrice>, price: u64) -> Result<()> {
+        ctx.accounts.pool.price = price;
+        Ok(())
+    }
+
+    pub fn swap(ctx: Context<Swap>, amount_in: u64, min_amount_out: u64) -> Result<()> {
+        ctx.accounts.swap(amount_in, min_amount_out)
+    }
+}
+
+/// Pays out `price` output base units per `PRICE_SCALE` input base units from its own reserves,
+/// PDA of `[b"pool", input_mint, output_mint]`
+#[account]
+#[derive(InitSpace)]
+pub struct Pool {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub price: u64,
+    pub bump: u8,
+}
+
+impl Pool {
+    pub fn amount_out(&self, amount_in: u64) -> Result<u64> {
+        let amount_out = amount_in as u128 * self.price as u128 / PRICE_SCALE;
+        u64::try_from(amount_out).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Swap pays out less than the minimum amount out.")]
+    SlippageExceeded,
+
+    #[msg("MATH_OVERFLOW")]
+    MathOverflow,
+}
+
+#[derive(Accounts)]
+pub struct InitializePool<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub input_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mint::token_program = token_program)]
+    pub output_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Pool::INIT_SPACE,
+        seeds = [b"pool", input_mint.key().as_ref(), output_mint.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = input_mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub pool_input_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Funded by the test with what the pool pays out
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = output_mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub pool_output_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+/// Anyone can move the price, the pools only exist in tests
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+}
+
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        token::mint = pool.input_mint,
+        token::authority = authority,
+        token::token_program = token_program
+    )]
+    pub source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = pool.output_mint,
+        token::token_program = token_program
+    )]
+    pub destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    #[account(
+        seeds = [b"pool", pool.input_mint.as_ref(), pool.output_mint.as_ref()],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(address = pool.input_mint)]
+    pub input_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(address = pool.output_mint)]
+    pub output_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = input_mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub pool_input_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = output_mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program
+    )]
+    pub pool_output_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+impl<'info> Swap<'info> {
+    pub fn swap(&mut self, amount_in: u64, min_amount_out: u64) -> Result<()> {
+        let amount_out = self.pool.amount_out(amount_in)?;
+        require!(amount_out >= min_amount_out, ErrorCode::SlippageExceeded);
+
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.source.to_account_info(),
+                    mint: self.input_mint.to_account_info(),
+                    to: self.pool_input_ata.to_account_info(),
+                    authority: self.authority.to_account_info(),
+                },
+            ),
+            amount_in,
+            self.input_mint.decimals,
+        )?;
+
+        let input_mint = self.pool.input_mint;
+        let output_mint = self.pool.output_mint;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"pool", input_mint.as_ref(), output_mint.as_ref(), &[self.pool.bump]]];
+        transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.pool_output_ata.to_account_info(),
+                    mint: self.output_mint.to_account_info(),
+                    to: self.destination.to_account_info(),
+                    authority: self.pool.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount_out,
+            self.output_mint.decimals,
+        )
+    }
+}
@@ -0,0 +1,172 @@
+use std::cell::Ref;
+
+use anchor_lang::prelude::*;
+
+use crate::adapters::{AdapterKind, LendingAdapter, MarketAccounts, Rounding, VaultCpiAccounts, read_vault_token_account};
+use crate::error::ErrorCode;
+use crate::{ExternalAccount, Lending as JupLending, Strategy, jup_accounts, jup_cpi, jup_lend};
+
+/// JupLend earn market. Accounts, in order:
+/// 0. vault f-token ATA
+/// 1. lending
+/// 2. f_token_mint
+/// 3. lending_admin
+/// 4. supply_token_reserves_liquidity
+/// 5. lending_supply_position_on_liquidity
+/// 6. rate_model
+/// 7. vault (Jup's, not ours)
+/// 8. liquidity
+/// 9. liquidity_program
+/// 10. rewards_rate_model
+/// 11. lending_program
+/// 12. associated_token_program
+/// 13. system_program
+/// 14. claim_account, only to withdraw
+pub struct JupLendAdapter<'a, 'info> {
+    vault: Pubkey,
+    accounts: &'a [AccountInfo<'info>],
+}
+
+impl<'a, 'info> JupLendAdapter<'a, 'info> {
+    pub const VALUATION_ACCOUNTS: usize = 2;
+    pub const DEPOSIT_ACCOUNTS: usize = 14;
+    pub const WITHDRAW_ACCOUNTS: usize = 15;
+
+    /// Checks the accounts are the ones recorded for `strategy`: the vault's f-token ATA and the
+    /// `lending` account, plus the f-token mint, the liquidity accounts `lending` points to and
+    /// the JupLend program when they are passed to deposit or withdraw
+    pub fn load(strategy: &Strategy, vault: Pubkey, accounts: &'a [AccountInfo<'info>]) -> Result<Self> {
+        require!(accounts.len() >= Self::VALUATION_ACCOUNTS, ErrorCode::MissingAdapterAccounts);
+        strategy.check_accounts(accounts)?;
+
+        let adapter = Self { vault, accounts };
+        let lending = adapter.lending()?;
+        let position = read_vault_token_account(&accounts[0], &vault)?;
+        require_keys_eq!(position.mint, strategy.position_mint, ErrorCode::InvalidLendingAccount);
+
+        if accounts.len() >= Self::DEPOSIT_ACCOUNTS {
+            require_keys_eq!(accounts[2].key(), strategy.position_mint, ErrorCode::StrategyAccountMismatch);
+            require_keys_eq!(accounts[4].key(), lending.token_reserves_liquidity(), ErrorCode::StrategyAccountMismatch);
+            require_keys_eq!(accounts[5].key(), lending.supply_position_on_liquidity(), ErrorCode::StrategyAccountMismatch);
+            require_keys_eq!(accounts[10].key(), lending.rewards_rate_model(), ErrorCode::StrategyAccountMismatch);
+            require_keys_eq!(accounts[11].key(), strategy.program, ErrorCode::StrategyAccountMismatch);
+        }
+        drop(lending);
+        Ok(adapter)
+    }
+
+    /// Reads what a strategy records from the `lending` account
+    pub fn market_accounts(lending: &AccountInfo) -> Result<MarketAccounts> {
+        let lending = JupLending::load(lending)?;
+        Ok(MarketAccounts {
+            program: jup_lend::ID,
+            asset_mint: lending.mint(),
+            position_mint: lending.f_token_mint(),
+            lending_market: Pubkey::default(),
+        })
+    }
+
+    /// The lending account, read in place and checked to be owned by JupLend
+    pub fn lending(&self) -> Result<Ref<'a, JupLending>> {
+        JupLending::load(&self.accounts[1])
+    }
+
+    /// Accrues the incentives of the market's `rewards_rate_model` into its f-token exchange
+    /// price, which JupLend otherwise only moves on deposits and withdrawals. Anyone can call it.
+    pub fn update_rate(&self, usdc_mint: &AccountInfo<'info>) -> Result<()> {
+        let accounts = jup_accounts::UpdateRate {
+            lending: self.account(1)?,
+            mint: usdc_mint.clone(),
+            f_token_mint: self.account(2)?,
+            supply_token_reserves_liquidity: self.account(4)?,
+            rewards_rate_model: self.account(10)?,
+        };
+
+        let cpi_context = CpiContext::new(self.account(11)?, accounts);
+        jup_cpi::update_rate(cpi_context).map_err(|_| ErrorCode::CpiToLendingProgramFailed)?;
+        Ok(())
+    }
+
+    fn account(&self, index: usize) -> Result<AccountInfo<'info>> {
+        self.accounts
+            .get(index)
+            .cloned()
+            .ok_or(ErrorCode::MissingAdapterAccounts.into())
+    }
+}
+
+impl<'a, 'info> LendingAdapter<'info> for JupLendAdapter<'a, 'info> {
+    fn kind(&self) -> AdapterKind {
+        AdapterKind::JupLend
+    }
+
+    fn deposit(&self, vault: &VaultCpiAccounts<'_, 'info>, amount: u64) -> Result<()> {
+        let accounts = jup_accounts::Deposit {
+            signer: vault.vault.clone(),
+            depositor_token_account: vault.vault_usdc_ata.clone(),
+            recipient_token_account: self.account(0)?,
+            mint: vault.usdc_mint.clone(),
+            lending: self.account(1)?,
+            f_token_mint: self.account(2)?,
+            lending_admin: self.account(3)?,
+            supply_token_reserves_liquidity: self.account(4)?,
+            lending_supply_position_on_liquidity: self.account(5)?,
+            rate_model: self.account(6)?,
+            vault: self.account(7)?,
+            liquidity: self.account(8)?,
+            liquidity_program: self.account(9)?,
+            rewards_rate_model: self.account(10)?,
+            token_program: vault.token_program.clone(),
+            associated_token_program: self.account(12)?,
+            system_program: self.account(13)?,
+        };
+
+        let cpi_context = CpiContext::new_with_signer(self.account(11)?, accounts, vault.signer_seeds);
+        jup_cpi::deposit(cpi_context, amount).map_err(|_| ErrorCode::CpiToLendingProgramFailed)?;
+        Ok(())
+    }
+
+    fn withdraw(&self, vault: &VaultCpiAccounts<'_, 'info>, position_amount: u64) -> Result<()> {
+        // redeem(shares) burns f-tokens, withdraw(amount) would ask for an amount of USDC instead
+        let accounts = jup_accounts::Redeem {
+            signer: vault.vault.clone(),
+            owner_token_account: self.account(0)?,
+            recipient_token_account: vault.vault_usdc_ata.clone(),
+            mint: vault.usdc_mint.clone(),
+            lending: self.account(1)?,
+            f_token_mint: self.account(2)?,
+            lending_admin: self.account(3)?,
+            supply_token_reserves_liquidity: self.account(4)?,
+            lending_supply_position_on_liquidity: self.account(5)?,
+            rate_model: self.account(6)?,
+            vault: self.account(7)?,
+            liquidity: self.account(8)?,
+            liquidity_program: self.account(9)?,
+            rewards_rate_model: self.account(10)?,
+            token_program: vault.token_program.clone(),
+            associated_token_program: self.account(12)?,
+            system_program: self.account(13)?,
+            claim_account: self.account(14)?,
+        };
+
+        let cpi_context = CpiContext::new_with_signer(self.account(11)?, accounts, vault.signer_seeds);
+        jup_cpi::redeem(cpi_context, position_amount).map_err(|_| ErrorCode::CpiToLendingProgramFailed)?;
+        Ok(())
+    }
+
+    fn position_amount(&self) -> Result<u64> {
+        Ok(read_vault_token_account(&self.accounts[0], &self.vault)?.amount)
+    }
+
+    fn value_of_position(&self, position_amount: u64) -> Result<u64> {
+        self.lending()?.f_tokens_to_assets(position_amount, Rounding::Down)
+    }
+
+    fn position_for_value(&self, assets: u64, rounding: Rounding) -> Result<u64> {
+        self.lending()?.assets_to_f_tokens(assets, rounding)
+    }
+
+    fn exchange_rate(&self) -> Result<u128> {
+        self.lending()?.exchange_rate()
+    }
+}
@@ -0,0 +1,191 @@
+use std::cell::Ref;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke_signed, sysvar};
+
+use crate::adapters::{AdapterKind, Freshness, LendingAdapter, MarketAccounts, Rounding, VaultCpiAccounts, read_vault_token_account};
+use crate::error::ErrorCode;
+use crate::{ExternalAccount, KAMINO_LENDING_PROGRAM_ID, Reserve, Strategy};
+
+/// discriminator = sha256("global:deposit_reserve_liquidity")[0..8]
+const DEPOSIT_RESERVE_LIQUIDITY_DISCRIMINATOR: [u8; 8] = [169, 201, 30, 126, 6, 205, 102, 68];
+
+/// discriminator = sha256("global:redeem_reserve_collateral")[0..8]
+const REDEEM_RESERVE_COLLATERAL_DISCRIMINATOR: [u8; 8] = [234, 117, 181, 125, 185, 142, 220, 29];
+
+/// Kamino Lend reserve. Accounts, in order:
+/// 0. vault collateral token ATA
+/// 1. reserve
+/// 2. lending_market
+/// 3. lending_market_authority
+/// 4. reserve_liquidity_supply
+/// 5. reserve_collateral_mint
+/// 6. collateral_token_program
+/// 7. liquidity_token_program
+/// 8. instruction_sysvar_account
+/// 9. klend_program
+pub struct KaminoAdapter<'a, 'info> {
+    vault: Pubkey,
+    accounts: &'a [AccountInfo<'info>],
+    freshness: Freshness,
+}
+
+impl<'a, 'info> KaminoAdapter<'a, 'info> {
+    pub const VALUATION_ACCOUNTS: usize = 2;
+    pub const CPI_ACCOUNTS: usize = 10;
+
+    /// Checks the accounts are the ones recorded for `strategy`: the vault's collateral ATA and
+    /// the reserve, plus the lending market, the supply vault and collateral mint of the reserve,
+    /// the instructions sysvar and the klend program when they are passed to deposit or withdraw.
+    /// With `Freshness::Current` the reserve has to have been refreshed earlier in this slot.
+    pub fn load(strategy: &Strategy, vault: Pubkey, accounts: &'a [AccountInfo<'info>], freshness: Freshness) -> Result<Self> {
+        require!(accounts.len() >= Self::VALUATION_ACCOUNTS, ErrorCode::MissingAdapterAccounts);
+        strategy.check_accounts(accounts)?;
+
+        let reserve = Reserve::load(&accounts[1])?;
+        if freshness == Freshness::Current {
+            reserve.check_refreshed(Clock::get()?.slot)?;
+        }
+        let position = read_vault_token_account(&accounts[0], &vault)?;
+        require_keys_eq!(position.mint, strategy.position_mint, ErrorCode::InvalidLendingAccount);
+
+        if accounts.len() >= Self::CPI_ACCOUNTS {
+            require_keys_eq!(accounts[2].key(), strategy.lending_market, ErrorCode::StrategyAccountMismatch);
+            require_keys_eq!(accounts[4].key(), reserve.liquidity_supply_vault(), ErrorCode::StrategyAccountMismatch);
+            require_keys_eq!(accounts[5].key(), strategy.position_mint, ErrorCode::StrategyAccountMismatch);
+            require_keys_eq!(accounts[8].key(), sysvar::instructions::ID, ErrorCode::StrategyAccountMismatch);
+            require_keys_eq!(accounts[9].key(), strategy.program, ErrorCode::StrategyAccountMismatch);
+        }
+        drop(reserve);
+        Ok(Self { vault, accounts, freshness })
+    }
+
+    /// Reads what a strategy records from the reserve
+    pub fn market_accounts(reserve: &AccountInfo) -> Result<MarketAccounts> {
+        let reserve = Reserve::load(reserve)?;
+        Ok(MarketAccounts {
+            program: KAMINO_LENDING_PROGRAM_ID,
+            asset_mint: reserve.liquidity_mint(),
+            position_mint: reserve.collateral_mint(),
+            lending_market: reserve.lending_market(),
+        })
+    }
+
+    /// The reserve, read in place, once it is known to be fresh enough to price from
+    pub fn reserve(&self) -> Result<Ref<'a, Reserve>> {
+        let reserve = Reserve::load(&self.accounts[1])?;
+        reserve.check_fresh(Clock::get()?.slot, self.freshness.max_staleness_slots())?;
+        Ok(reserve)
+    }
+
+    fn cpi_accounts(&self) -> Result<&'a [AccountInfo<'info>]> {
+        require!(self.accounts.len() >= Self::CPI_ACCOUNTS, ErrorCode::MissingAdapterAccounts);
+        Ok(self.accounts)
+    }
+
+    fn invoke(&self, vault: &VaultCpiAccounts<'_, 'info>, data: Vec<u8>, metas: Vec<AccountMeta>, infos: &[AccountInfo<'info>]) -> Result<()> {
+        let ix = Instruction {
+            program_id: KAMINO_LENDING_PROGRAM_ID,
+            accounts: metas,
+            data,
+        };
+        invoke_signed(&ix, infos, vault.signer_seeds)?;
+        Ok(())
+    }
+}
+
+impl<'a, 'info> LendingAdapter<'info> for KaminoAdapter<'a, 'info> {
+    fn kind(&self) -> AdapterKind {
+        AdapterKind::Kamino
+    }
+
+    fn deposit(&self, vault: &VaultCpiAccounts<'_, 'info>, amount: u64) -> Result<()> {
+        let a = self.cpi_accounts()?;
+        let mut data = DEPOSIT_RESERVE_LIQUIDITY_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+
+        let metas = vec![
+            AccountMeta::new_readonly(vault.vault.key(), true), // owner/signer
+            AccountMeta::new(a[1].key(), false),
+            AccountMeta::new_readonly(a[2].key(), false),
+            AccountMeta::new_readonly(a[3].key(), false),
+            AccountMeta::new_readonly(vault.usdc_mint.key(), false),
+            AccountMeta::new(a[4].key(), false),
+            AccountMeta::new(a[5].key(), false),
+            AccountMeta::new(vault.vault_usdc_ata.key(), false), // userSourceLiquidity
+            AccountMeta::new(a[0].key(), false),                 // userDestinationCollateral
+            AccountMeta::new_readonly(a[6].key(), false),
+            AccountMeta::new_readonly(a[7].key(), false),
+            AccountMeta::new_readonly(a[8].key(), false),
+        ];
+        let infos = [
+            vault.vault.clone(),
+            a[1].clone(),
+            a[2].clone(),
+            a[3].clone(),
+            vault.usdc_mint.clone(),
+            a[4].clone(),
+            a[5].clone(),
+            vault.vault_usdc_ata.clone(),
+            a[0].clone(),
+            a[6].clone(),
+            a[7].clone(),
+            a[8].clone(),
+            a[9].clone(),
+        ];
+        self.invoke(vault, data, metas, &infos)
+    }
+
+    fn withdraw(&self, vault: &VaultCpiAccounts<'_, 'info>, position_amount: u64) -> Result<()> {
+        let a = self.cpi_accounts()?;
+        let mut data = REDEEM_RESERVE_COLLATERAL_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&position_amount.to_le_bytes());
+
+        let metas = vec![
+            AccountMeta::new_readonly(vault.vault.key(), true), // owner/signer
+            AccountMeta::new_readonly(a[2].key(), false),
+            AccountMeta::new(a[1].key(), false),
+            AccountMeta::new_readonly(a[3].key(), false),
+            AccountMeta::new(vault.usdc_mint.key(), false), // reserveLiquidityMint (writable)
+            AccountMeta::new(a[5].key(), false),
+            AccountMeta::new(a[4].key(), false),
+            AccountMeta::new(a[0].key(), false),                 // userSourceCollateral
+            AccountMeta::new(vault.vault_usdc_ata.key(), false), // userDestinationLiquidity
+            AccountMeta::new_readonly(a[6].key(), false),
+            AccountMeta::new_readonly(a[7].key(), false),
+            AccountMeta::new_readonly(a[8].key(), false),
+        ];
+        let infos = [
+            vault.vault.clone(),
+            a[2].clone(),
+            a[1].clone(),
+            a[3].clone(),
+            vault.usdc_mint.clone(),
+            a[5].clone(),
+            a[4].clone(),
+            a[0].clone(),
+            vault.vault_usdc_ata.clone(),
+            a[6].clone(),
+            a[7].clone(),
+            a[8].clone(),
+            a[9].clone(),
+        ];
+        self.invoke(vault, data, metas, &infos)
+    }
+
+    fn position_amount(&self) -> Result<u64> {
+        Ok(read_vault_token_account(&self.accounts[0], &self.vault)?.amount)
+    }
+
+    fn value_of_position(&self, position_amount: u64) -> Result<u64> {
+        self.reserve()?.collateral_to_liquidity(position_amount, Rounding::Down)
+    }
+
+    fn position_for_value(&self, assets: u64, rounding: Rounding) -> Result<u64> {
+        self.reserve()?.liquidity_to_collateral(assets, rounding)
+    }
+
+    fn exchange_rate(&self) -> Result<u128> {
+        self.reserve()?.exchange_rate()
+    }
+}
@@ -0,0 +1,234 @@
+pub mod jup;
+pub mod kamino;
+pub mod swap;
+
+pub use jup::*;
+pub use kamino::*;
+pub use swap::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::error::ErrorCode;
+use crate::{MAX_RESERVE_STALENESS_SLOTS, Strategy, Vault};
+
+/// Lending markets the vault knows how to talk to
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AdapterKind {
+    JupLend,
+    Kamino,
+}
+
+impl AdapterKind {
+    /// Accounts a strategy of this kind takes in `remaining_accounts`
+    pub fn accounts_len(&self, accounts: AdapterAccounts) -> usize {
+        match (self, accounts) {
+            (AdapterKind::JupLend, AdapterAccounts::Valuation) => JupLendAdapter::VALUATION_ACCOUNTS,
+            (AdapterKind::JupLend, AdapterAccounts::Cpi) => JupLendAdapter::WITHDRAW_ACCOUNTS,
+            (AdapterKind::Kamino, AdapterAccounts::Valuation) => KaminoAdapter::VALUATION_ACCOUNTS,
+            (AdapterKind::Kamino, AdapterAccounts::Cpi) => KaminoAdapter::CPI_ACCOUNTS,
+        }
+    }
+}
+
+/// Which accounts of each strategy an instruction takes: only what pricing the position needs,
+/// or everything needed to deposit into and withdraw from the market
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AdapterAccounts {
+    Valuation,
+    Cpi,
+}
+
+/// How recently a market has to have been refreshed for the vault to price from it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Freshness {
+    /// Refreshed in the current slot and not deposited into or redeemed from since, as klend
+    /// itself requires to move funds. Everything that prices the vault uses it, the views too so
+    /// they quote what execution pays, and the client prepends `refresh_reserve`.
+    Current,
+    /// Refreshed within `MAX_RESERVE_STALENESS_SLOTS`, only to check a market when it is
+    /// registered as a strategy, which prices nothing
+    Recent,
+}
+
+impl Freshness {
+    /// Most slots since the last refresh a market is still priced from
+    pub fn max_staleness_slots(self) -> u64 {
+        match self {
+            Freshness::Current => 0,
+            Freshness::Recent => MAX_RESERVE_STALENESS_SLOTS,
+        }
+    }
+}
+
+/// Which way a conversion rounds, always picked so the vault never gives away value
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+/// Vault accounts a lending adapter moves USDC between. The vault PDA owns every position
+/// and signs every CPI with `signer_seeds`.
+pub struct VaultCpiAccounts<'a, 'info> {
+    pub vault: AccountInfo<'info>,
+    pub vault_usdc_ata: AccountInfo<'info>,
+    pub usdc_mint: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub signer_seeds: &'a [&'a [&'a [u8]]],
+}
+
+/// A lending market the vault supplies USDC to.
+///
+/// An adapter is built from a slice of accounts laid out by the adapter itself. The first two
+/// are always the vault's position token account and the market state account, which is all
+/// valuation needs, the rest are only required to deposit or withdraw.
+pub trait LendingAdapter<'info> {
+    fn kind(&self) -> AdapterKind;
+
+    /// Supplies `amount` USDC from the vault's idle ATA to the market
+    fn deposit(&self, vault: &VaultCpiAccounts<'_, 'info>, amount: u64) -> Result<()>;
+
+    /// Redeems `position_amount` of the vault's position tokens back into its idle ATA
+    fn withdraw(&self, vault: &VaultCpiAccounts<'_, 'info>, position_amount: u64) -> Result<()>;
+
+    /// Position tokens (f-tokens, collateral tokens...) the vault holds in this market
+    fn position_amount(&self) -> Result<u64>;
+
+    /// USDC redeemable for `position_amount` position tokens, rounded down
+    fn value_of_position(&self, position_amount: u64) -> Result<u64>;
+
+    /// Position tokens worth `assets` USDC
+    fn position_for_value(&self, assets: u64, rounding: Rounding) -> Result<u64>;
+
+    /// USDC per position token, scaled by `EXCHANGE_RATE_SCALE`
+    fn exchange_rate(&self) -> Result<u128>;
+
+    /// USDC value of everything the vault holds in this market
+    fn position_value(&self) -> Result<u64> {
+        self.value_of_position(self.position_amount()?)
+    }
+
+    /// Redeems position tokens worth `assets` USDC, capped by the position, and returns the
+    /// position tokens redeemed
+    fn withdraw_value(&self, vault: &VaultCpiAccounts<'_, 'info>, assets: u64, rounding: Rounding) -> Result<u64> {
+        let position_amount = self.position_for_value(assets, rounding)?.min(self.position_amount()?);
+        if position_amount > 0 {
+            self.withdraw(vault, position_amount)?;
+        }
+        Ok(position_amount)
+    }
+}
+
+/// Reads a token account of the vault, rejecting accounts that are not token accounts or
+/// that `vault` does not own
+pub fn read_vault_token_account(info: &AccountInfo, vault: &Pubkey) -> Result<TokenAccount> {
+    require!(
+        *info.owner == anchor_spl::token::ID || *info.owner == anchor_spl::token_2022::ID,
+        ErrorCode::InvalidLendingAccount
+    );
+    let token_account = TokenAccount::try_deserialize(&mut &info.data.borrow()[..])?;
+    require_keys_eq!(token_account.owner, *vault, ErrorCode::InvalidLendingAccount);
+    Ok(token_account)
+}
+
+/// What a market account says about itself, recorded on the strategy when it is registered
+pub struct MarketAccounts {
+    /// Program owning the market
+    pub program: Pubkey,
+    /// Asset the market lends out, has to be the vault's USDC
+    pub asset_mint: Pubkey,
+    pub position_mint: Pubkey,
+    pub lending_market: Pubkey,
+}
+
+/// Adapter of any supported market, so strategies of different kinds can be handled together
+pub enum Adapter<'a, 'info> {
+    JupLend(JupLendAdapter<'a, 'info>),
+    Kamino(KaminoAdapter<'a, 'info>),
+}
+
+impl<'a, 'info> Adapter<'a, 'info> {
+    /// Builds the adapter of `strategy`, checking `accounts` are the ones recorded for it and
+    /// the market was refreshed recently enough to price from
+    pub fn load(strategy: &Strategy, vault: Pubkey, accounts: &'a [AccountInfo<'info>], freshness: Freshness) -> Result<Self> {
+        Ok(match strategy.kind {
+            AdapterKind::JupLend => Adapter::JupLend(JupLendAdapter::load(strategy, vault, accounts)?),
+            AdapterKind::Kamino => Adapter::Kamino(KaminoAdapter::load(strategy, vault, accounts, freshness)?),
+        })
+    }
+
+    /// Reads the accounts a `kind` market account points to
+    pub fn market_accounts(kind: AdapterKind, market: &AccountInfo) -> Result<MarketAccounts> {
+        match kind {
+            AdapterKind::JupLend => JupLendAdapter::market_accounts(market),
+            AdapterKind::Kamino => KaminoAdapter::market_accounts(market),
+        }
+    }
+
+    fn inner(&self) -> &dyn LendingAdapter<'info> {
+        match self {
+            Adapter::JupLend(adapter) => adapter,
+            Adapter::Kamino(adapter) => adapter,
+        }
+    }
+}
+
+impl<'a, 'info> LendingAdapter<'info> for Adapter<'a, 'info> {
+    fn kind(&self) -> AdapterKind {
+        self.inner().kind()
+    }
+
+    fn deposit(&self, vault: &VaultCpiAccounts<'_, 'info>, amount: u64) -> Result<()> {
+        self.inner().deposit(vault, amount)
+    }
+
+    fn withdraw(&self, vault: &VaultCpiAccounts<'_, 'info>, position_amount: u64) -> Result<()> {
+        self.inner().withdraw(vault, position_amount)
+    }
+
+    fn position_amount(&self) -> Result<u64> {
+        self.inner().position_amount()
+    }
+
+    fn value_of_position(&self, position_amount: u64) -> Result<u64> {
+        self.inner().value_of_position(position_amount)
+    }
+
+    fn position_for_value(&self, assets: u64, rounding: Rounding) -> Result<u64> {
+        self.inner().position_for_value(assets, rounding)
+    }
+
+    fn exchange_rate(&self) -> Result<u128> {
+        self.inner().exchange_rate()
+    }
+}
+
+/// Number of `remaining_accounts` the strategies of `vault` take together
+pub fn strategy_accounts_len(vault: &Vault, accounts: AdapterAccounts) -> usize {
+    vault.strategies.iter().map(|strategy| strategy.kind.accounts_len(accounts)).sum()
+}
+
+/// Builds the adapter of every strategy of `vault`, in registry order, from `remaining_accounts`.
+/// Each strategy takes `AdapterKind::accounts_len` accounts, checked against the ones recorded
+/// when the strategy was registered.
+pub fn load_strategy_adapters<'a, 'info>(
+    vault: &Vault,
+    vault_key: Pubkey,
+    remaining_accounts: &'a [AccountInfo<'info>],
+    accounts: AdapterAccounts,
+    freshness: Freshness,
+) -> Result<Vec<Adapter<'a, 'info>>> {
+    let mut adapters = Vec::with_capacity(vault.strategies.len());
+    let mut offset = 0;
+    for strategy in &vault.strategies {
+        let len = strategy.kind.accounts_len(accounts);
+        let strategy_accounts = remaining_accounts
+            .get(offset..offset + len)
+            .ok_or(ErrorCode::MissingAdapterAccounts)?;
+        adapters.push(Adapter::load(strategy, vault_key, strategy_accounts, freshness)?);
+        offset += len;
+    }
+    require!(offset == remaining_accounts.len(), ErrorCode::StrategyAccountMismatch);
+    Ok(adapters)
+}
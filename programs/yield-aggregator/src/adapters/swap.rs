@@ -0,0 +1,140 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke_signed};
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::adapters::VaultCpiAccounts;
+use crate::error::ErrorCode;
+use crate::Vault;
+
+/// discriminator = sha256("global:swap")[0..8]
+const SWAP_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+
+/// Swap program the vault sells reward tokens for USDC through, `Vault::swap_program`.
+///
+/// Any program with a `swap(amount_in: u64, min_amount_out: u64)` instruction taking the
+/// authority, source token account, destination token account and token program first can be
+/// plugged in, `programs/mock-swap` implements it with fixed-price pools. Accounts, in order:
+/// 0. swap program
+/// 1.. accounts of the pool or route, passed on as they are
+pub struct SwapAdapter<'a, 'info> {
+    accounts: &'a [AccountInfo<'info>],
+}
+
+impl<'a, 'info> SwapAdapter<'a, 'info> {
+    /// Checks the program is the vault's swap program. The vault signs the swap, so the pool
+    /// accounts can't include anything else it has authority over: its share mint or positions.
+    pub fn load(vault: &Vault, vault_key: Pubkey, accounts: &'a [AccountInfo<'info>]) -> Result<Self> {
+        // `Pubkey::default()` is the system program, which must not pass for a configured one
+        require_keys_neq!(vault.swap_program, Pubkey::default(), ErrorCode::SwapNotConfigured);
+        let program = accounts.first().ok_or(ErrorCode::MissingAdapterAccounts)?;
+        require_keys_eq!(program.key(), vault.swap_program, ErrorCode::SwapNotConfigured);
+
+        let protected = |key: &Pubkey| {
+            *key == vault_key
+                || *key == vault.share_mint
+                || vault.strategies.iter().any(|strategy| strategy.position_token_account == *key)
+        };
+        require!(!accounts[1..].iter().any(|info| protected(info.key)), ErrorCode::SwapAccountNotAllowed);
+        Ok(Self { accounts })
+    }
+
+    /// Sells `amount_in` of the vault's `source` tokens for USDC paid into the vault's idle ATA.
+    /// The swap program enforces `min_amount_out`, callers still have to check what arrived.
+    pub fn swap(&self, vault: &VaultCpiAccounts<'_, 'info>, source: &AccountInfo<'info>, amount_in: u64, min_amount_out: u64) -> Result<()> {
+        let mut data = SWAP_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+        let pool = &self.accounts[1..];
+        let mut metas = vec![
+            AccountMeta::new_readonly(vault.vault.key(), true), // authority/signer
+            AccountMeta::new(source.key(), false),
+            AccountMeta::new(vault.vault_usdc_ata.key(), false), // destination
+            AccountMeta::new_readonly(vault.token_program.key(), false),
+        ];
+        metas.extend(pool.iter().map(|info| AccountMeta {
+            pubkey: info.key(),
+            is_signer: false,
+            is_writable: info.is_writable,
+        }));
+
+        let mut infos = vec![
+            vault.vault.clone(),
+            source.clone(),
+            vault.vault_usdc_ata.clone(),
+            vault.token_program.clone(),
+        ];
+        infos.extend_from_slice(self.accounts);
+
+        let ix = Instruction {
+            program_id: self.accounts[0].key(),
+            accounts: metas,
+            data,
+        };
+        invoke_signed(&ix, &infos, vault.signer_seeds)?;
+        Ok(())
+    }
+
+    /// The swap program gets the vault's signature over the source and destination accounts, so it
+    /// could hand them over, approve a delegate or set a close authority. `account`, reloaded after
+    /// the swap, must still be the vault's alone.
+    pub fn check_account_after_swap(account: &TokenAccount, vault: &Pubkey) -> Result<()> {
+        require!(
+            account.owner == *vault && account.delegate.is_none() && account.close_authority.is_none(),
+            ErrorCode::SwapAccountTampered
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::Discriminator;
+    use anchor_lang::solana_program::{program_option::COption, program_pack::Pack};
+    use anchor_spl::token::spl_token::state::{Account, AccountState};
+
+    use super::*;
+
+    fn token_account(owner: Pubkey, delegate: Option<Pubkey>, close_authority: Option<Pubkey>) -> TokenAccount {
+        let account = Account {
+            mint: Pubkey::new_unique(),
+            owner,
+            amount: 1_000,
+            delegate: delegate.map_or(COption::None, COption::Some),
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: if delegate.is_some() { 1_000 } else { 0 },
+            close_authority: close_authority.map_or(COption::None, COption::Some),
+        };
+        let mut data = [0; Account::LEN];
+        Account::pack(account, &mut data).unwrap();
+        TokenAccount::try_deserialize(&mut &data[..]).unwrap()
+    }
+
+    #[test]
+    fn swapped_accounts_must_stay_the_vaults_alone() {
+        let vault = Pubkey::new_unique();
+        let thief = Pubkey::new_unique();
+        let check = |account: TokenAccount| SwapAdapter::check_account_after_swap(&account, &vault);
+
+        check(token_account(vault, None, None)).unwrap();
+        for account in [
+            token_account(thief, None, None),
+            token_account(vault, Some(thief), None),
+            token_account(vault, None, Some(thief)),
+        ] {
+            assert_eq!(check(account).unwrap_err(), ErrorCode::SwapAccountTampered.into());
+        }
+    }
+
+    #[test]
+    fn swap_data_matches_the_mock_swap_interface() {
+        assert_eq!(SWAP_DISCRIMINATOR, mock_swap::instruction::Swap::DISCRIMINATOR);
+
+        let args = mock_swap::instruction::Swap { amount_in: 7, min_amount_out: 5 };
+        let mut data = SWAP_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&7u64.to_le_bytes());
+        data.extend_from_slice(&5u64.to_le_bytes());
+        assert_eq!(data, anchor_lang::InstructionData::data(&args));
+    }
+}
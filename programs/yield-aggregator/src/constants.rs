@@ -2,3 +2,54 @@ use anchor_lang::prelude::*;
 
 #[constant]
 pub const SEED: &str = "anchor";
+
+/// Kamino Lend (klend) program
+#[constant]
+pub const KAMINO_LENDING_PROGRAM_ID: Pubkey = pubkey!("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD");
+
+/// Precision of `Vault::acc_per_share`
+pub const ACC_PER_SHARE_SCALER: u128 = 1_000_000_000_000;
+
+/// Precision of `LendingAdapter::exchange_rate`
+pub const EXCHANGE_RATE_SCALE: u128 = 1_000_000_000_000;
+
+/// Precision of the share prices reported in events, USDC per share
+pub const SHARE_PRICE_SCALE: u128 = 1_000_000_000_000;
+
+/// Shares and USDC every vault is priced as if it already held, 1 USDC worth. They keep the
+/// first share price at 1:1, and make a donation to an empty or near-empty vault mostly accrue to
+/// these virtual shares, so inflating the share price to round the next depositor down to
+/// nothing costs the attacker far more than it takes.
+#[constant]
+pub const VIRTUAL_SHARES: u64 = 1_000_000;
+#[constant]
+pub const VIRTUAL_ASSETS: u64 = 1_000_000;
+
+/// Oldest Kamino reserve refresh, in slots, a reserve can be registered as a strategy with.
+/// Everything that prices the vault, views included, needs a refresh in the same slot, see `Freshness`.
+pub const MAX_RESERVE_STALENESS_SLOTS: u64 = 9_000;
+
+/// Most lending markets a single vault can allocate to
+pub const MAX_STRATEGIES: usize = 8;
+
+/// Allocation weights are expressed in basis points, 10_000 = 100%
+#[constant]
+pub const ALLOCATION_SCALE: u16 = 10_000;
+
+/// Hard caps on the fees the admin can set, in bps
+#[constant]
+pub const MAX_PERFORMANCE_FEE_BPS: u16 = 3_000;
+#[constant]
+pub const MAX_MANAGEMENT_FEE_BPS: u16 = 500;
+#[constant]
+pub const MAX_DEPOSIT_FEE_BPS: u16 = 100;
+#[constant]
+pub const MAX_WITHDRAW_FEE_BPS: u16 = 100;
+
+/// Shortest timelock delay, in seconds, a vault can run with. New vaults start at it, so
+/// depositors always get a day to exit before a queued change applies.
+#[constant]
+pub const MIN_TIMELOCK_DELAY: i64 = 24 * 60 * 60;
+
+/// The management fee is an annual rate streamed per second
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
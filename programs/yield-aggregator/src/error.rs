@@ -2,12 +2,147 @@ use anchor_lang::prelude::*;
 
 #[error_code]
 pub enum ErrorCode {
-    #[msg("Invalid allocation ratio provided.")]
+    #[msg("Allocation weights must cover every strategy and sum to 10,000 bps.")]
     InvalidAllocation,
 
+    #[msg("Allocation weight is outside the strategy's min and max bounds.")]
+    AllocationOutOfBounds,
+
+    #[msg("Allocation weight changes by more than the max change per update.")]
+    AllocationChangeTooLarge,
+
+    #[msg("Allocation weights were changed too recently.")]
+    AllocationCooldownActive,
+
+    #[msg("Allocation bounds are inconsistent or leave no valid allocation.")]
+    InvalidAllocationBounds,
+
     #[msg("CPI_TO_LENDING_PROGRAM_FAILED")]
     CpiToLendingProgramFailed,
 
     #[msg("MATH_OVERFLOW")]
-    MathOverflow
+    MathOverflow,
+
+    #[msg("Share mint supply exceeds the vault's total shares.")]
+    ShareSupplyMismatch,
+
+    #[msg("Idle buffer cannot exceed 100% of the vault.")]
+    InvalidIdleBuffer,
+
+    #[msg("Vault does not hold enough liquidity for this withdrawal.")]
+    InsufficientLiquidity,
+
+    #[msg("Amount must be greater than zero.")]
+    ZeroAmount,
+
+    #[msg("User does not own enough vault shares.")]
+    InsufficientShares,
+
+    #[msg("Lending account does not match the vault's f-token or underlying mint.")]
+    InvalidLendingAccount,
+
+    #[msg("Not enough accounts passed for the lending adapter.")]
+    MissingAdapterAccounts,
+
+    #[msg("Vault already holds the maximum number of strategies.")]
+    TooManyStrategies,
+
+    #[msg("A strategy for this market is already registered.")]
+    StrategyAlreadyExists,
+
+    #[msg("No strategy of the vault matches these accounts.")]
+    StrategyNotFound,
+
+    #[msg("Strategy is disabled and cannot take new funds or weight.")]
+    StrategyDisabled,
+
+    #[msg("Strategy still holds a position or target weight.")]
+    StrategyNotEmpty,
+
+    #[msg("Remaining accounts do not match the vault's strategies.")]
+    StrategyAccountMismatch,
+
+    #[msg("Deposit would take the strategy over its cap.")]
+    StrategyCapExceeded,
+
+    #[msg("Deposit is smaller than the vault's minimum deposit.")]
+    DepositTooSmall,
+
+    #[msg("Deposit would take the vault over its TVL cap.")]
+    VaultCapExceeded,
+
+    #[msg("Deposit would take the user over the per-user cap.")]
+    UserCapExceeded,
+
+    #[msg("Minimum deposit cannot exceed the deposit caps.")]
+    InvalidDepositLimits,
+
+    #[msg("Vault is not accepting deposits.")]
+    DepositsPaused,
+
+    #[msg("Vault is not deploying funds into strategies.")]
+    AllocationsPaused,
+
+    #[msg("Vault must be in WithdrawOnly or Shutdown to be unwound.")]
+    VaultNotHalted,
+
+    #[msg("Signer does not hold the role this instruction requires.")]
+    Unauthorized,
+
+    #[msg("Only the admin can relax the vault status.")]
+    GuardianCannotResume,
+
+    #[msg("This change has to go through the timelock queue.")]
+    TimelockRequired,
+
+    #[msg("Queued action cannot be executed before its delay has passed.")]
+    TimelockNotExpired,
+
+    #[msg("Timelock delay is below the minimum delay.")]
+    InvalidTimelockDelay,
+
+    #[msg("Fee exceeds its hard cap.")]
+    FeeTooHigh,
+
+    #[msg("Accrued fee shares must be claimed before changing the fee recipient.")]
+    UnclaimedFees,
+
+    #[msg("Deposit is too small to be worth a single share.")]
+    ZeroShares,
+
+    #[msg("Shares or assets received fall outside the caller's slippage bound.")]
+    SlippageExceeded,
+
+    #[msg("Lending market reports a zero exchange rate.")]
+    InvalidExchangeRate,
+
+    #[msg("Kamino reserve has not been refreshed recently enough to be priced.")]
+    StaleReserve,
+
+    #[msg("Vault has no swap program to compound rewards through.")]
+    SwapNotConfigured,
+
+    #[msg("Reward mint cannot be USDC, the share mint or a strategy position token.")]
+    InvalidRewardMint,
+
+    #[msg("Swap accounts cannot include the vault's share mint or strategy positions.")]
+    SwapAccountNotAllowed,
+
+    #[msg("Swap spent more reward tokens than the amount in.")]
+    SwapOverspent,
+
+    #[msg("Legacy account has nothing left to migrate.")]
+    NothingToMigrate,
+
+    #[msg("Account is not a legacy vault or user position of this program.")]
+    InvalidLegacyAccount,
+
+    #[msg("Action was queued before the admin or fee manager changed, it can only be cancelled.")]
+    ActionInvalidated,
+
+    #[msg("Guardian cannot cancel the action replacing it.")]
+    GuardianCannotCancel,
+
+    #[msg("Swap changed the owner, delegate or close authority of a vault token account.")]
+    SwapAccountTampered,
 }
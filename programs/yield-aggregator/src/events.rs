@@ -0,0 +1,282 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::{AccountMeta, Instruction}, program::invoke_signed};
+
+use crate::{AdapterKind, Role, TimelockAction, VaultStatus};
+
+/// Emits events through a self-CPI signed by the `#[event_cpi]` event authority, which keeps them
+/// out of the truncatable program logs. Same as `emit_cpi!`, which only works where the
+/// `Context` is in scope, so the instruction impls and `Vault` get this handed down instead.
+pub struct EventEmitter<'info> {
+    pub event_authority: AccountInfo<'info>,
+    pub bump: u8,
+}
+
+impl<'info> EventEmitter<'info> {
+    pub fn new(event_authority: &AccountInfo<'info>, bump: u8) -> Self {
+        Self { event_authority: event_authority.clone(), bump }
+    }
+
+    pub fn emit<E: anchor_lang::Event>(&self, event: E) -> Result<()> {
+        let data = anchor_lang::event::EVENT_IX_TAG_LE
+            .iter()
+            .copied()
+            .chain(event.data())
+            .collect::<Vec<u8>>();
+        let ix = Instruction::new_with_bytes(
+            crate::ID,
+            &data,
+            vec![AccountMeta::new_readonly(self.event_authority.key(), true)],
+        );
+        invoke_signed(&ix, std::slice::from_ref(&self.event_authority), &[&[b"__event_authority", &[self.bump]]])
+            .map_err(Into::into)
+    }
+}
+
+/// Emitted once by `initialize_vault`
+#[event]
+pub struct VaultInitialized {
+    pub vault: Pubkey,
+    pub authority: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub vault_index: u16,
+    pub share_mint: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Emitted on every user deposit, `deposit` and `deposit_and_allocate`.
+/// Share prices are USDC per share scaled by `SHARE_PRICE_SCALE`.
+#[event]
+pub struct DepositEvent {
+    pub vault: Pubkey,
+    pub user: Pubkey,
+    /// USDC taken from the user
+    pub amount: u64,
+    /// Vault shares minted to the user
+    pub shares: u64,
+    /// Vault shares booked for the fee recipient as the deposit fee
+    pub fee_shares: u64,
+    pub share_price_before: u64,
+    pub share_price_after: u64,
+    /// Value of each strategy position the deposit was priced with, in registry order
+    pub strategy_values: Vec<u64>,
+    pub total_underlying: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted on every user withdrawal with the USDC actually sourced from each place
+#[event]
+pub struct WithdrawEvent {
+    pub vault: Pubkey,
+    pub user: Pubkey,
+    /// Vault shares burned for this withdrawal
+    pub shares: u64,
+    /// USDC sent to the user
+    pub amount: u64,
+    /// Part of `amount` paid from idle USDC already sitting in the vault ATA
+    pub from_idle: u64,
+    /// USDC redeemed from each strategy, in registry order
+    pub from_strategies: Vec<u64>,
+    pub share_price_before: u64,
+    pub share_price_after: u64,
+    pub total_underlying: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AllocationDirection {
+    /// Idle USDC supplied to the market
+    Allocate,
+    /// Position redeemed back into idle USDC
+    Deallocate,
+}
+
+/// Emitted by `jup_deposit`, `jup_withdraw`, `kamino_deposit`, `kamino_withdraw`, and for each
+/// strategy funded by `deposit_and_allocate`
+#[event]
+pub struct AllocationEvent {
+    pub vault: Pubkey,
+    pub kind: AdapterKind,
+    pub market: Pubkey,
+    pub direction: AllocationDirection,
+    /// USDC that left (allocate) or came back to (deallocate) the vault ATA
+    pub usdc_amount: u64,
+    pub position_value_before: u64,
+    pub position_value_after: u64,
+    /// USDC left idle in the vault ATA
+    pub idle: u64,
+    pub share_price_before: u64,
+    pub share_price_after: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by `rebalance` with the vault before and after funds moved
+#[event]
+pub struct RebalanceEvent {
+    pub vault: Pubkey,
+    /// Target weight of each strategy, in registry order
+    pub weights: Vec<u16>,
+    pub strategy_values_before: Vec<u64>,
+    pub strategy_values_after: Vec<u64>,
+    pub idle_before: u64,
+    pub idle_after: u64,
+    /// Yield realized since the last snapshot, negative when the positions lost value
+    pub realized_yield: i64,
+    pub share_price_before: u64,
+    pub share_price_after: u64,
+    pub total_underlying: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by `compound` for every batch of reward tokens sold, before the proceeds are deployed
+#[event]
+pub struct CompoundEvent {
+    pub vault: Pubkey,
+    pub reward_mint: Pubkey,
+    /// Reward tokens the swap took from the vault
+    pub reward_amount: u64,
+    /// USDC the swap paid into the vault, booked as yield
+    pub usdc_received: u64,
+    /// Part of `usdc_received` taken as performance fee
+    pub performance_fee: u64,
+    pub share_price_before: u64,
+    pub share_price_after: u64,
+    pub total_underlying: u64,
+    pub total_shares: u64,
+    pub acc_per_share: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by `harvest` and `claim_jup_rewards` every time the vault positions are marked to market
+#[event]
+pub struct HarvestEvent {
+    pub vault: Pubkey,
+    /// Live value of each strategy position, in registry order
+    pub strategy_values: Vec<u64>,
+    /// Yield realized since the last snapshot, negative when the positions lost value
+    pub realized_yield: i64,
+    pub share_price_before: u64,
+    pub share_price_after: u64,
+    pub total_underlying: u64,
+    pub total_shares: u64,
+    pub acc_per_share: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted whenever marking the strategies to market finds them worth less than their snapshots.
+/// The loss is shared by every holder through a lower share price.
+#[event]
+pub struct LossEvent {
+    pub vault: Pubkey,
+    pub loss: u64,
+    pub strategy_values_before: Vec<u64>,
+    pub strategy_values_after: Vec<u64>,
+    pub share_price_before: u64,
+    pub share_price_after: u64,
+    /// Share price the vault has to get back to before performance fees are charged again
+    pub high_water_mark: u64,
+    pub total_underlying: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by `emergency_unwind` once every position is back in the vault ATA
+#[event]
+pub struct EmergencyUnwindEvent {
+    pub vault: Pubkey,
+    pub signer: Pubkey,
+    pub strategy_values_before: Vec<u64>,
+    /// USDC redeemed from each strategy, in registry order
+    pub recovered: Vec<u64>,
+    pub idle_after: u64,
+    pub share_price_before: u64,
+    pub share_price_after: u64,
+    pub total_underlying: u64,
+    pub timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FeeKind {
+    Performance,
+    Management,
+    Deposit,
+    Withdraw,
+}
+
+/// Emitted every time fee shares are booked for the fee recipient
+#[event]
+pub struct FeeAccrued {
+    pub vault: Pubkey,
+    pub kind: FeeKind,
+    /// USDC value of the fee when it was charged
+    pub fee_assets: u64,
+    /// Vault shares booked for `fee_recipient`, minted to it by `claim_fees`
+    pub fee_shares: u64,
+    pub fee_recipient: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Emitted by `claim_fees` when the booked fee shares are minted
+#[event]
+pub struct FeesClaimed {
+    pub vault: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub fee_shares: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by `migrate_vault` once the funds of a legacy vault moved into `vault`
+#[event]
+pub struct VaultMigrated {
+    pub vault: Pubkey,
+    pub legacy_vault: Pubkey,
+    /// USDC value moved, idle USDC and position tokens together
+    pub assets: u64,
+    /// Shares booked for the legacy holders, minted to each of them by `migrate_user_position`
+    pub shares: u64,
+    pub legacy_shares: u64,
+    pub share_price: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by `migrate_user_position` when a legacy position is exchanged for share tokens
+#[event]
+pub struct PositionMigrated {
+    pub vault: Pubkey,
+    pub user: Pubkey,
+    pub legacy_shares: u64,
+    pub shares: u64,
+    pub timestamp: i64,
+}
+
+/// A vault configuration change, the new value in each case
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum ConfigChange {
+    IdleBuffer { idle_buffer_bps: u16 },
+    DepositLimits { deposit_cap: u64, user_deposit_cap: u64, min_deposit: u64 },
+    FeeRecipient { fee_recipient: Pubkey },
+    StrategyWeights { weights: Vec<u16> },
+    /// `removed` is false when the strategy still held funds and was only disabled
+    StrategyRemoved { market: Pubkey, removed: bool },
+    /// `Pubkey::default()` when the role was revoked
+    Role { role: Role, key: Pubkey },
+    Status { status: VaultStatus },
+    AuthorityProposed { pending_authority: Pubkey },
+    AuthorityAccepted { authority: Pubkey },
+    ActionQueued { id: u64, action: TimelockAction, eta: i64 },
+    ActionExecuted { id: u64, action: TimelockAction },
+    ActionCancelled { id: u64 },
+}
+
+/// Emitted by every instruction that changes the vault configuration
+#[event]
+pub struct ConfigChanged {
+    pub vault: Pubkey,
+    /// Who signed the change
+    pub signer: Pubkey,
+    pub change: ConfigChange,
+    pub timestamp: i64,
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, Vault, error::ErrorCode};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub new_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.pending_authority == new_authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub main_vault: Account<'info, Vault>,
+}
+
+impl<'info> AcceptAuthority<'info> {
+    pub fn accept_authority(&mut self, events: &EventEmitter<'info>) -> Result<()> {
+        self.main_vault.set_authority(self.new_authority.key());
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.new_authority.key(),
+            change: ConfigChange::AuthorityAccepted { authority: self.new_authority.key() },
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<AcceptAuthority>) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.accept_authority(&events)?;
+    Ok(())
+}
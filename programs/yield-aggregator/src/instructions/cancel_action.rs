@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, QueuedAction, Role, TimelockAction, Vault, error::ErrorCode};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelAction<'info> {
+    /// The vault admin or its guardian
+    pub signer: Signer<'info>,
+
+    #[account(
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.has_role(signer.key(), Role::Guardian) @ ErrorCode::Unauthorized
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"queued_action", main_vault.key().as_ref(), &queued_action.id.to_le_bytes()],
+        bump = queued_action.bump,
+        has_one = proposer
+    )]
+    pub queued_action: Box<Account<'info, QueuedAction>>,
+
+    /// CHECK: receives the rent of the queued action
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+}
+
+impl<'info> CancelAction<'info> {
+    /// Closing the queued action is all there is to it. Only the admin can cancel a guardian
+    /// change, the guardian would otherwise veto its own replacement.
+    pub fn cancel_action(&mut self, events: &EventEmitter<'info>) -> Result<()> {
+        if let TimelockAction::SetGuardian { .. } = self.queued_action.action {
+            require_keys_eq!(self.signer.key(), self.main_vault.authority, ErrorCode::GuardianCannotCancel);
+        }
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.signer.key(),
+            change: ConfigChange::ActionCancelled { id: self.queued_action.id },
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<CancelAction>) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.cancel_action(&events)?;
+    Ok(())
+}
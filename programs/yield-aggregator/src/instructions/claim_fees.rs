@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{associated_token::AssociatedToken, token_interface::{Mint, MintTo, TokenAccount, TokenInterface, mint_to}};
+
+use crate::{EventEmitter, FeesClaimed, Vault};
+use crate::vault_signer_seeds;
+
+/// Permissionless: fee shares can only ever be minted to the vault's fee recipient
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimFees<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+
+    /// CHECK: only used as the owner of the fee share ATA, matched with the vault
+    #[account(constraint = main_vault.fee_recipient == fee_recipient.key())]
+    pub fee_recipient: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"share_mint", main_vault.key().as_ref()],
+        bump,
+        constraint = main_vault.share_mint == share_mint.key()
+    )]
+    pub share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = share_mint,
+        associated_token::authority = fee_recipient,
+        associated_token::token_program = token_program
+    )]
+    pub fee_recipient_share_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> ClaimFees<'info> {
+    /// Mints the fee shares booked so far to the fee recipient
+    pub fn claim_fees(&mut self, events: &EventEmitter<'info>) -> Result<()> {
+        let fee_shares = self.main_vault.accrued_fee_shares;
+        if fee_shares == 0 {
+            return Ok(());
+        }
+
+        let accounts = MintTo {
+            mint: self.share_mint.to_account_info(),
+            to: self.fee_recipient_share_ata.to_account_info(),
+            authority: self.main_vault.to_account_info(),
+        };
+        let signer_seeds: &[&[&[u8]]] = vault_signer_seeds!(self.main_vault);
+        let cpi_context = CpiContext::new_with_signer(self.token_program.to_account_info(), accounts, signer_seeds);
+        mint_to(cpi_context, fee_shares)?;
+
+        self.main_vault.accrued_fee_shares = 0;
+        self.share_mint.reload()?;
+        self.main_vault.check_share_supply(self.share_mint.supply)?;
+
+        events.emit(FeesClaimed {
+            vault: self.main_vault.key(),
+            fee_recipient: self.fee_recipient.key(),
+            fee_shares,
+            total_shares: self.main_vault.total_shares,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<ClaimFees>) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.claim_fees(&events)?;
+    Ok(())
+}
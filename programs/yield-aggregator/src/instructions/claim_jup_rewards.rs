@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{token::Token, token_interface::{Mint, TokenAccount}};
+
+use crate::instructions::harvest::book_harvest;
+use crate::{Adapter, AdapterAccounts, EventEmitter, Freshness, Vault, VaultValuation, load_strategy_adapters};
+
+/// Permissionless, like `harvest`. JupLend pays the incentives of its `rewards_rate_model`
+/// through the f-token exchange price rather than in a reward token, so claiming them is
+/// accruing them into that price with `update_rate` and booking the gain as yield.
+/// Remaining accounts: the CPI accounts of every strategy, in registry order
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimJupRewards<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        constraint = main_vault.vault_usdc_ata.key() == main_vault_usdc_ata.key(),
+        associated_token::mint=usdc_mint,
+        associated_token::authority=main_vault,
+        associated_token::token_program=token_program
+    )]
+    pub main_vault_usdc_ata : Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = main_vault.usdc_mint.key() == usdc_mint.key(),
+        mint::token_program=token_program
+    )]
+    pub usdc_mint : Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> ClaimJupRewards<'info> {
+    /// Updates the rate of every JupLend strategy, then marks the vault to market
+    pub fn claim(&mut self, adapters: &[Adapter<'_, 'info>], events: &EventEmitter<'info>) -> Result<()> {
+        let usdc_mint = self.usdc_mint.to_account_info();
+        for adapter in adapters {
+            if let Adapter::JupLend(jup) = adapter {
+                jup.update_rate(&usdc_mint)?;
+            }
+        }
+
+        let valuation = VaultValuation::load(self.main_vault_usdc_ata.amount, adapters)?;
+        book_harvest(&mut self.main_vault, &valuation, events)
+    }
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, ClaimJupRewards<'info>>) -> Result<()> {
+    let vault_key = ctx.accounts.main_vault.key();
+    let adapters = load_strategy_adapters(&ctx.accounts.main_vault, vault_key, ctx.remaining_accounts, AdapterAccounts::Cpi, Freshness::Current)?;
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.claim(&adapters, &events)?;
+    Ok(())
+}
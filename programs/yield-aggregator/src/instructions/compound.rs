@@ -0,0 +1,156 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{token::Token, token_interface::{Mint, TokenAccount}};
+
+use crate::instructions::deposit_and_allocate::allocate_by_weights;
+use crate::{Adapter, AdapterAccounts, CompoundEvent, EventEmitter, Freshness, Role, SwapAdapter, Vault, VaultCpiAccounts, VaultValuation, load_strategy_adapters, strategy_accounts_len, error::ErrorCode};
+use crate::vault_signer_seeds;
+
+/// Sells reward tokens held in a vault-owned ATA for USDC through the vault's swap program,
+/// books the USDC received as yield and deploys it into the strategies by their target weights.
+///
+/// Any incentive token paid to the vault ends up in its ATA of that mint. JupLend incentives never
+/// get here: they are paid through the f-token exchange price, which `claim_jup_rewards` books.
+/// Kamino farm rewards are not captured at all: the farms only track collateral deposited in an
+/// obligation, and the vault holds plain collateral tokens.
+///
+/// Remaining accounts: the CPI accounts of every strategy, in registry order, then the swap
+/// program and the accounts of its pool
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Compound<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.has_role(keeper.key(), Role::Keeper) @ ErrorCode::Unauthorized
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        mut,
+        constraint = main_vault.vault_usdc_ata.key() == main_vault_usdc_ata.key(),
+        associated_token::mint=usdc_mint,
+        associated_token::authority=main_vault,
+        associated_token::token_program=token_program
+    )]
+    pub main_vault_usdc_ata : Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = main_vault.usdc_mint.key() == usdc_mint.key(),
+        mint::token_program=token_program
+    )]
+    pub usdc_mint : Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        constraint = main_vault.is_reward_mint(&reward_mint.key()) @ ErrorCode::InvalidRewardMint,
+        mint::token_program=token_program
+    )]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint=reward_mint,
+        associated_token::authority=main_vault,
+        associated_token::token_program=token_program
+    )]
+    pub vault_reward_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> Compound<'info> {
+    pub fn valuation(&mut self, adapters: &[Adapter<'_, 'info>]) -> Result<VaultValuation> {
+        self.main_vault_usdc_ata.reload()?;
+        VaultValuation::load(self.main_vault_usdc_ata.amount, adapters)
+    }
+
+    /// Swaps `amount_in` reward tokens for at least `min_amount_out` USDC and books the USDC
+    /// received as realized yield, so the gain shows up in `acc_per_share`, then redeploys it
+    /// unless the vault stopped allocating.
+    pub fn compound(
+        &mut self,
+        adapters: &[Adapter<'_, 'info>],
+        swap: &SwapAdapter<'_, 'info>,
+        amount_in: u64,
+        min_amount_out: u64,
+        events: &EventEmitter<'info>,
+    ) -> Result<()> {
+        require!(amount_in > 0, ErrorCode::ZeroAmount);
+
+        // Book the strategies' own yield first, so the proceeds are the only gain left to charge
+        let now = Clock::get()?.unix_timestamp;
+        let before = self.valuation(adapters)?;
+        let vault_key = self.main_vault.key();
+        self.main_vault.mark_to_market(vault_key, &before, now, events)?;
+        let share_price_before = self.main_vault.share_price(before.total()?);
+
+        let reward_before = self.vault_reward_ata.amount;
+        let signer_seeds: &[&[&[u8]]] = vault_signer_seeds!(self.main_vault);
+        let vault = VaultCpiAccounts {
+            vault: self.main_vault.to_account_info(),
+            vault_usdc_ata: self.main_vault_usdc_ata.to_account_info(),
+            usdc_mint: self.usdc_mint.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            signer_seeds,
+        };
+        swap.swap(&vault, &self.vault_reward_ata.to_account_info(), amount_in, min_amount_out)?;
+
+        // The swap program is trusted with the vault's signature, so only balances are believed
+        self.vault_reward_ata.reload()?;
+        SwapAdapter::check_account_after_swap(&self.vault_reward_ata, &vault_key)?;
+        let reward_amount = reward_before.saturating_sub(self.vault_reward_ata.amount);
+        require!(reward_amount <= amount_in, ErrorCode::SwapOverspent);
+        let after = self.valuation(adapters)?;
+        SwapAdapter::check_account_after_swap(&self.main_vault_usdc_ata, &vault_key)?;
+        let usdc_received = after.idle.checked_sub(before.idle).ok_or(ErrorCode::SlippageExceeded)?;
+        require!(usdc_received >= min_amount_out, ErrorCode::SlippageExceeded);
+
+        let total_after = after.total()?;
+        let performance_fee = self.main_vault.book_external_yield(vault_key, usdc_received, total_after, now, events)?;
+
+        let vault = &self.main_vault;
+        events.emit(CompoundEvent {
+            vault: vault_key,
+            reward_mint: self.reward_mint.key(),
+            reward_amount,
+            usdc_received,
+            performance_fee,
+            share_price_before,
+            share_price_after: vault.share_price(total_after),
+            total_underlying: vault.total_underlying,
+            total_shares: vault.total_shares,
+            acc_per_share: vault.acc_per_share,
+            timestamp: now,
+        })?;
+
+        if self.main_vault.status.allows_allocations() {
+            allocate_by_weights(
+                &mut self.main_vault,
+                &mut self.main_vault_usdc_ata,
+                self.usdc_mint.to_account_info(),
+                self.token_program.to_account_info(),
+                adapters,
+                usdc_received,
+                events,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, Compound<'info>>, amount_in: u64, min_amount_out: u64) -> Result<()> {
+    let vault_key = ctx.accounts.main_vault.key();
+    let strategy_accounts = strategy_accounts_len(&ctx.accounts.main_vault, AdapterAccounts::Cpi);
+    require!(ctx.remaining_accounts.len() > strategy_accounts, ErrorCode::MissingAdapterAccounts);
+    let (strategy_accounts, swap_accounts) = ctx.remaining_accounts.split_at(strategy_accounts);
+
+    let adapters = load_strategy_adapters(&ctx.accounts.main_vault, vault_key, strategy_accounts, AdapterAccounts::Cpi, Freshness::Current)?;
+    let swap = SwapAdapter::load(&ctx.accounts.main_vault, vault_key, swap_accounts)?;
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.compound(&adapters, &swap, amount_in, min_amount_out, &events)?;
+    Ok(())
+}
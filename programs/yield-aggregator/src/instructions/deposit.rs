@@ -1,8 +1,12 @@
 use anchor_lang::prelude::*;
-use anchor_spl::{associated_token::AssociatedToken, token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked}};
+use anchor_spl::{associated_token::AssociatedToken, token_interface::{Mint, MintTo, TokenAccount, TokenInterface, TransferChecked, mint_to, transfer_checked}};
 
-use crate::{UserPosition, Vault};
+use crate::{ACC_PER_SHARE_SCALER, Adapter, AdapterAccounts, DepositEvent, EventEmitter, FeeKind, Freshness, UserPosition, Vault, VaultValuation, load_strategy_adapters, error::ErrorCode};
+use crate::vault_signer_seeds;
 
+/// Remaining accounts: the valuation accounts of every strategy, in registry order,
+/// read to price the new shares at the live vault value
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Deposit<'info> {
     #[account(mut)]
@@ -12,16 +16,16 @@ pub struct Deposit<'info> {
         mut,
         constraint = vault.usdc_mint == usdc_mint.key()
     )]
-    pub vault: Account<'info, Vault>,
+    pub vault: Box<Account<'info, Vault>>,
 
     #[account(
         init_if_needed,
         payer = user,
         space = 8 + UserPosition::INIT_SPACE,
-        seeds = [b"user_position", user.key().as_ref()],
+        seeds = [b"user_position", vault.key().as_ref(), user.key().as_ref()],
         bump
     )]
-    pub user_position: Account<'info, UserPosition>,
+    pub user_position: Box<Account<'info, UserPosition>>,
 
     pub usdc_mint: InterfaceAccount<'info, Mint>,
 
@@ -32,7 +36,7 @@ pub struct Deposit<'info> {
         associated_token::authority = user,
         associated_token::token_program = token_program
     )]
-    pub user_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+    pub user_usdc_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         mut,
@@ -40,7 +44,25 @@ pub struct Deposit<'info> {
         associated_token::authority = vault,
         associated_token::token_program = token_program
     )]
-    pub vault_usdc_ata: InterfaceAccount<'info, TokenAccount>,
+    pub vault_usdc_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"share_mint", vault.key().as_ref()],
+        bump,
+        constraint = vault.share_mint == share_mint.key()
+    )]
+    pub share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    // Receives the minted vault shares
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = share_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_share_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     pub system_program: Program<'info, System>,
     pub token_program: Interface<'info, TokenInterface>,
@@ -48,58 +70,69 @@ pub struct Deposit<'info> {
 }
 
 impl<'info> Deposit<'info> {
-    pub fn update_states(&mut self, amount: u64, bump: u8) -> Result<()> {
-        let current_time = Clock::get().unwrap().unix_timestamp;
-        let existed = self.user_position.shares > 0;
+    /// Realizes any yield or loss since the last snapshot and returns the live valuation of the vault
+    pub fn mark_to_market(&mut self, adapters: &[Adapter<'_, 'info>], events: &EventEmitter<'info>) -> Result<VaultValuation> {
+        let valuation = VaultValuation::load(self.vault_usdc_ata.amount, adapters)?;
+        let vault_key = self.vault.key();
+        self.vault.mark_to_market(vault_key, &valuation, Clock::get()?.unix_timestamp, events)?;
+        Ok(valuation)
+    }
+
+    /// Updates the vault and user position books and returns the number of shares to mint to the user,
+    /// net of the deposit fee, and the fee shares. `total_assets` is the vault value before this deposit.
+    /// Fails with `SlippageExceeded` when the user would get fewer than `min_shares_out` shares.
+    pub fn update_states(&mut self, amount: u64, min_shares_out: u64, bump: u8, total_assets: u64, events: &EventEmitter<'info>) -> Result<(u64, u64)> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // Shares the user already owns
+        let user_shares = self.user_share_ata.amount;
 
-        if !existed {
-            // Initialize user position if needed
+        if self.user_position.user == Pubkey::default() {
+            // Position `init_if_needed` just created. Shares the user got by transfer before it
+            // existed start earning rewards from here.
             self.user_position.user = self.user.key();
             self.user_position.vault = self.vault.key();
-            // self.user_position.shares = amount;
-            self.user_position.reward_debt = (amount as u128) * (self.vault.acc_per_share as u128);
             self.user_position.pending_rewards = 0;
-            self.user_position.last_updated = current_time;
             self.user_position.bump = bump;
         } else {
             // Getting pending rewards that the user has
-            let pending = (self.user_position.shares as u128)
+            let pending = (user_shares as u128)
                 .checked_mul(self.vault.acc_per_share as u128)
-                .unwrap()
-                .saturating_sub(self.user_position.reward_debt);
-            self.user_position.pending_rewards = self.user_position.pending_rewards.checked_add(pending as u64).unwrap();
-
-            // Update shares & reward checkpoint
-            // self.user_position.shares = self.user_position.shares.checked_add(amount).unwrap();
-            self.user_position.reward_debt = (self.user_position.shares as u128)
-                .checked_mul(self.vault.acc_per_share as u128)
-                .unwrap();
+                .ok_or(ErrorCode::MathOverflow)?
+                .saturating_sub(self.user_position.reward_debt)
+                / ACC_PER_SHARE_SCALER;
+            let pending = u64::try_from(pending).map_err(|_| ErrorCode::MathOverflow)?;
+            self.user_position.pending_rewards = self.user_position.pending_rewards.checked_add(pending).ok_or(ErrorCode::MathOverflow)?;
         }
 
-        let shares_to_mint = if self.vault.total_shares == 0 || self.vault.total_underlying == 0 {
-            // first depositor -> 1 share = 1 USDC
-            amount
-        } else {
-            // proportional share 
-            (amount as u128)
-                .checked_mul(self.vault.total_shares as u128)
-                .unwrap()
-                .checked_div(self.vault.total_underlying as u128)
-                .unwrap() as u64
-        };
+        let user_assets = if user_shares == 0 { 0 } else { self.vault.convert_to_assets(user_shares, total_assets)? };
+        self.vault.check_deposit(amount, user_assets, total_assets)?;
 
-        self.user_position.shares = self.user_position.shares.checked_add(shares_to_mint).unwrap();
-        self.vault.total_shares = self.vault.total_shares.checked_add(shares_to_mint).unwrap();
+        let shares = self.vault.convert_to_shares(amount, total_assets)?;
+        let fee_shares = self.vault.deposit_fee_shares(shares);
+        require!(shares > fee_shares, ErrorCode::ZeroShares);
+        let fee_assets = if shares == 0 { 0 } else { (amount as u128 * fee_shares as u128 / shares as u128) as u64 };
+        let vault_key = self.vault.key();
+        self.vault.book_fee_shares(vault_key, FeeKind::Deposit, fee_assets, fee_shares, current_time, events)?;
+        let shares_to_mint = shares - fee_shares;
+        require!(shares_to_mint >= min_shares_out, ErrorCode::SlippageExceeded);
+
+        // Update reward checkpoint with the shares the user holds after this deposit
+        self.user_position.reward_debt = (user_shares.checked_add(shares_to_mint).ok_or(ErrorCode::MathOverflow)? as u128)
+            .checked_mul(self.vault.acc_per_share as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        self.vault.total_shares = self.vault.total_shares.checked_add(shares_to_mint).ok_or(ErrorCode::MathOverflow)?;
         // Update vault underlying
-        self.vault.total_underlying = self.vault.total_underlying.checked_add(amount).unwrap();
+        self.vault.total_underlying = self.vault.total_underlying.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
         self.user_position.last_updated = current_time;
 
-        Ok(())
+        Ok((shares_to_mint, fee_shares))
     }
 
     pub fn desposit_to_vault_ata(&mut self, deposited_amount : u64) -> Result<()>{
         // deposit usdc to main_vault_usdc_ata from user account
-        let accounts = TransferChecked { 
+        let accounts = TransferChecked {
             authority: self.user.to_account_info(),
             from: self.user_usdc_ata.to_account_info(),
             to: self.vault_usdc_ata.to_account_info(),
@@ -109,10 +142,51 @@ impl<'info> Deposit<'info> {
 
         transfer_checked(cpi_context, deposited_amount, self.usdc_mint.decimals)
     }
+
+    pub fn mint_shares(&mut self, shares: u64) -> Result<()> {
+        let accounts = MintTo {
+            mint: self.share_mint.to_account_info(),
+            to: self.user_share_ata.to_account_info(),
+            authority: self.vault.to_account_info(),
+        };
+
+        let signer_seeds: &[&[&[u8]]] = vault_signer_seeds!(self.vault);
+        let cpi_context = CpiContext::new_with_signer(self.token_program.to_account_info(), accounts, signer_seeds);
+        mint_to(cpi_context, shares)?;
+
+        self.share_mint.reload()?;
+        self.vault.check_share_supply(self.share_mint.supply)
+    }
+
+    /// `valuation` and `share_price_before` are what the deposit was priced at
+    pub fn emit_deposit(&self, events: &EventEmitter<'info>, amount: u64, shares: u64, fee_shares: u64, valuation: VaultValuation, share_price_before: u64) -> Result<()> {
+        let total_assets = valuation.total()?.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+        events.emit(DepositEvent {
+            vault: self.vault.key(),
+            user: self.user.key(),
+            amount,
+            shares,
+            fee_shares,
+            share_price_before,
+            share_price_after: self.vault.share_price(total_assets),
+            strategy_values: valuation.positions,
+            total_underlying: self.vault.total_underlying,
+            total_shares: self.vault.total_shares,
+            timestamp: self.user_position.last_updated,
+        })
+    }
 }
 
-pub fn handler(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-    ctx.accounts.update_states(amount, ctx.bumps.user_position)?;
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, Deposit<'info>>, amount: u64, min_shares_out: u64) -> Result<()> {
+    let vault_key = ctx.accounts.vault.key();
+    let adapters = load_strategy_adapters(&ctx.accounts.vault, vault_key, ctx.remaining_accounts, AdapterAccounts::Valuation, Freshness::Current)?;
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    let valuation = ctx.accounts.mark_to_market(&adapters, &events)?;
+    let total_assets = valuation.total()?;
+    let share_price_before = ctx.accounts.vault.share_price(total_assets);
+    let (shares, fee_shares) = ctx.accounts.update_states(amount, min_shares_out, ctx.bumps.user_position, total_assets, &events)?;
     ctx.accounts.desposit_to_vault_ata(amount)?;
+    ctx.accounts.mint_shares(shares)?;
+    ctx.accounts.emit_deposit(&events, amount, shares, fee_shares, valuation, share_price_before)?;
     Ok(())
-}
\ No newline at end of file
+}
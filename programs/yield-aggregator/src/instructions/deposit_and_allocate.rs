@@ -0,0 +1,114 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{token::Token, token_interface::TokenAccount};
+
+use crate::instructions::deposit::*;
+use crate::{Adapter, AdapterAccounts, AllocationDirection, AllocationEvent, EventEmitter, Freshness, LendingAdapter, Vault, VaultCpiAccounts, VaultValuation, load_strategy_adapters};
+use crate::vault_signer_seeds;
+
+/// Same as `Deposit`, but the deposited USDC is split between the strategies
+/// by their target weights in the same transaction.
+/// Remaining accounts: the CPI accounts of every strategy, in registry order.
+#[derive(Accounts)]
+pub struct DepositAndAllocate<'info> {
+    pub deposit: Deposit<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> DepositAndAllocate<'info> {
+    /// Splits the deposited `amount` between the strategies by their target weights
+    pub fn allocate(&mut self, adapters: &[Adapter<'_, 'info>], amount: u64, events: &EventEmitter<'info>) -> Result<()> {
+        let deposit = &mut self.deposit;
+        allocate_by_weights(
+            &mut deposit.vault,
+            &mut deposit.vault_usdc_ata,
+            deposit.usdc_mint.to_account_info(),
+            self.token_program.to_account_info(),
+            adapters,
+            amount,
+            events,
+        )
+    }
+}
+
+/// Splits up to `amount` of the vault's idle USDC between the strategies by their target weights.
+/// Only idle USDC above the vault's idle buffer is deployed, so the buffer is topped up first.
+pub fn allocate_by_weights<'info>(
+    vault: &mut Account<'info, Vault>,
+    vault_usdc_ata: &mut InterfaceAccount<'info, TokenAccount>,
+    usdc_mint: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    adapters: &[Adapter<'_, 'info>],
+    amount: u64,
+    events: &EventEmitter<'info>,
+) -> Result<()> {
+    vault_usdc_ata.reload()?;
+    let before = VaultValuation::load(vault_usdc_ata.amount, adapters)?;
+    let buffer = vault.idle_buffer_target(before.total()?);
+    let deployable = before.idle.saturating_sub(buffer).min(amount);
+
+    let signer_seeds: &[&[&[u8]]] = vault_signer_seeds!(vault);
+    let cpi_accounts = VaultCpiAccounts {
+        vault: vault.to_account_info(),
+        vault_usdc_ata: vault_usdc_ata.to_account_info(),
+        usdc_mint,
+        token_program,
+        signer_seeds,
+    };
+    let mut allocated = vec![0; adapters.len()];
+    for (i, adapter) in adapters.iter().enumerate() {
+        // Caps bound what a strategy holds, the part of the deposit over a cap stays idle
+        let strategy = &vault.strategies[i];
+        let share = strategy.target_value(deployable);
+        let headroom = strategy.cap.saturating_sub(before.positions[i]);
+        let amount = share.min(headroom);
+        if amount > 0 {
+            adapter.deposit(&cpi_accounts, amount)?;
+            allocated[i] = amount;
+        }
+    }
+
+    vault_usdc_ata.reload()?;
+    let after = VaultValuation::load(vault_usdc_ata.amount, adapters)?;
+    for (i, (value_before, value_after)) in before.positions.iter().zip(&after.positions).enumerate() {
+        vault.track_position(i, *value_before, *value_after);
+    }
+
+    let share_price_before = vault.share_price(before.total()?);
+    let share_price_after = vault.share_price(after.total()?);
+    let timestamp = Clock::get()?.unix_timestamp;
+    for (i, strategy) in vault.strategies.iter().enumerate().filter(|(i, _)| allocated[*i] > 0) {
+        events.emit(AllocationEvent {
+            vault: vault.key(),
+            kind: strategy.kind,
+            market: strategy.market,
+            direction: AllocationDirection::Allocate,
+            usdc_amount: allocated[i],
+            position_value_before: before.positions[i],
+            position_value_after: after.positions[i],
+            idle: after.idle,
+            share_price_before,
+            share_price_after,
+            timestamp,
+        })?;
+    }
+    Ok(())
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, DepositAndAllocate<'info>>, amount: u64, min_shares_out: u64) -> Result<()> {
+    let vault_key = ctx.accounts.deposit.vault.key();
+    let adapters = load_strategy_adapters(&ctx.accounts.deposit.vault, vault_key, ctx.remaining_accounts, AdapterAccounts::Cpi, Freshness::Current)?;
+
+    let accounts = &mut ctx.accounts.deposit;
+    let events = EventEmitter::new(&accounts.event_authority, ctx.bumps.deposit.event_authority);
+    let valuation = accounts.mark_to_market(&adapters, &events)?;
+    let total_assets = valuation.total()?;
+    let share_price_before = accounts.vault.share_price(total_assets);
+    let (shares, fee_shares) = accounts.update_states(amount, min_shares_out, ctx.bumps.deposit.user_position, total_assets, &events)?;
+    accounts.desposit_to_vault_ata(amount)?;
+    accounts.mint_shares(shares)?;
+    accounts.emit_deposit(&events, amount, shares, fee_shares, valuation, share_price_before)?;
+
+    ctx.accounts.allocate(&adapters, amount, &events)?;
+    Ok(())
+}
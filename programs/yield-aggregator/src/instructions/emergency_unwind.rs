@@ -0,0 +1,106 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{token::Token, token_interface::{Mint, TokenAccount}};
+
+use crate::{Adapter, AdapterAccounts, EmergencyUnwindEvent, EventEmitter, Freshness, LendingAdapter, Role, Vault, VaultCpiAccounts, VaultValuation, load_strategy_adapters, error::ErrorCode};
+use crate::vault_signer_seeds;
+
+/// Remaining accounts: the CPI accounts of every strategy, in registry order
+#[event_cpi]
+#[derive(Accounts)]
+pub struct EmergencyUnwind<'info> {
+    /// The vault admin or its guardian
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.has_role(signer.key(), Role::Guardian) @ ErrorCode::Unauthorized
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        mut,
+        constraint = main_vault.vault_usdc_ata.key() == main_vault_usdc_ata.key(),
+        associated_token::mint=usdc_mint,
+        associated_token::authority=main_vault,
+        associated_token::token_program=token_program
+    )]
+    pub main_vault_usdc_ata : Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = main_vault.usdc_mint.key() == usdc_mint.key(),
+        mint::token_program=token_program
+    )]
+    pub usdc_mint : Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> EmergencyUnwind<'info> {
+    pub fn valuation(&mut self, adapters: &[Adapter<'_, 'info>]) -> Result<VaultValuation> {
+        self.main_vault_usdc_ata.reload()?;
+        VaultValuation::load(self.main_vault_usdc_ata.amount, adapters)
+    }
+
+    /// Redeems every position of every strategy back into the vault ATA. Only allowed once the
+    /// vault is halted, so nothing redeploys the funds afterwards.
+    pub fn emergency_unwind(&mut self, adapters: &[Adapter<'_, 'info>], events: &EventEmitter<'info>) -> Result<()> {
+        require!(!self.main_vault.status.allows_allocations(), ErrorCode::VaultNotHalted);
+
+        // Book what the positions gained or lost before they are closed
+        let before = self.valuation(adapters)?;
+        let share_price_before = self.main_vault.share_price(before.total()?);
+        let vault_key = self.main_vault.key();
+        self.main_vault.mark_to_market(vault_key, &before, Clock::get()?.unix_timestamp, events)?;
+
+        let signer_seeds: &[&[&[u8]]] = vault_signer_seeds!(self.main_vault);
+        let vault = VaultCpiAccounts {
+            vault: self.main_vault.to_account_info(),
+            vault_usdc_ata: self.main_vault_usdc_ata.to_account_info(),
+            usdc_mint: self.usdc_mint.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            signer_seeds,
+        };
+        let mut recovered = Vec::with_capacity(adapters.len());
+        for adapter in adapters {
+            let position_amount = adapter.position_amount()?;
+            let idle_before = self.main_vault_usdc_ata.amount;
+            if position_amount > 0 {
+                adapter.withdraw(&vault, position_amount)?;
+                self.main_vault_usdc_ata.reload()?;
+            }
+            recovered.push(self.main_vault_usdc_ata.amount.saturating_sub(idle_before));
+        }
+
+        // Whatever the redemptions lost to rounding leaves the books here
+        let after = self.valuation(adapters)?;
+        let vault = &mut self.main_vault;
+        for (strategy, value) in vault.strategies.iter_mut().zip(&after.positions) {
+            strategy.last_value = *value;
+        }
+        vault.total_underlying = after.total()?;
+        vault.last_update_ts = Clock::get()?.unix_timestamp;
+
+        events.emit(EmergencyUnwindEvent {
+            vault: vault_key,
+            signer: self.signer.key(),
+            strategy_values_before: before.positions,
+            recovered,
+            idle_after: after.idle,
+            share_price_before,
+            share_price_after: vault.share_price(after.total()?),
+            total_underlying: vault.total_underlying,
+            timestamp: vault.last_update_ts,
+        })
+    }
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, EmergencyUnwind<'info>>) -> Result<()> {
+    let vault_key = ctx.accounts.main_vault.key();
+    let adapters = load_strategy_adapters(&ctx.accounts.main_vault, vault_key, ctx.remaining_accounts, AdapterAccounts::Cpi, Freshness::Current)?;
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.emergency_unwind(&adapters, &events)?;
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, MIN_TIMELOCK_DELAY, QueuedAction, Role, TimelockAction, Vault, error::ErrorCode};
+
+/// Remaining accounts: for `TimelockAction::AddStrategy`, the vault's position token account and
+/// the market of the new strategy
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecuteAction<'info> {
+    /// Anyone can execute an action once its delay has passed
+    pub executor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"queued_action", main_vault.key().as_ref(), &queued_action.id.to_le_bytes()],
+        bump = queued_action.bump,
+        has_one = proposer
+    )]
+    pub queued_action: Box<Account<'info, QueuedAction>>,
+
+    /// CHECK: receives the rent of the queued action
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+}
+
+impl<'info> ExecuteAction<'info> {
+    pub fn execute_action(&mut self, remaining_accounts: &[AccountInfo<'info>], events: &EventEmitter<'info>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= self.queued_action.eta, ErrorCode::TimelockNotExpired);
+        require!(self.main_vault.is_action_valid(self.queued_action.id), ErrorCode::ActionInvalidated);
+
+        let vault_key = self.main_vault.key();
+        let vault = &mut self.main_vault;
+        match &self.queued_action.action {
+            TimelockAction::SetAllocationBounds { bounds, max_weight_change_bps, weight_change_cooldown } => {
+                vault.set_allocation_bounds(bounds, *max_weight_change_bps, *weight_change_cooldown)
+            }
+            TimelockAction::SetFees { fees } => vault.set_fees(vault_key, fees, now, events),
+            TimelockAction::AddStrategy { kind, cap, position_token_account, market, program } => {
+                // Executors only supply the accounts, the strategy is the one reviewed at queue time
+                let accounts = remaining_accounts.get(..2).ok_or(ErrorCode::MissingAdapterAccounts)?;
+                require_keys_eq!(accounts[0].key(), *position_token_account, ErrorCode::StrategyAccountMismatch);
+                require_keys_eq!(accounts[1].key(), *market, ErrorCode::StrategyAccountMismatch);
+                vault.add_strategy(vault_key, *kind, *cap, *program, accounts)
+            }
+            TimelockAction::SetGuardian { guardian } => {
+                vault.set_role(Role::Guardian, *guardian);
+                Ok(())
+            }
+            TimelockAction::SetTimelockDelay { delay } => {
+                require!(*delay >= MIN_TIMELOCK_DELAY, ErrorCode::InvalidTimelockDelay);
+                vault.timelock_delay = *delay;
+                Ok(())
+            }
+            TimelockAction::SetSwapProgram { swap_program } => {
+                vault.swap_program = *swap_program;
+                Ok(())
+            }
+        }?;
+
+        events.emit(ConfigChanged {
+            vault: vault_key,
+            signer: self.executor.key(),
+            change: ConfigChange::ActionExecuted {
+                id: self.queued_action.id,
+                action: self.queued_action.action.clone(),
+            },
+            timestamp: now,
+        })
+    }
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, ExecuteAction<'info>>) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.execute_action(ctx.remaining_accounts, &events)?;
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{token::Token, token_interface::{Mint, TokenAccount}};
+
+use crate::{AdapterAccounts, EventEmitter, Freshness, HarvestEvent, Vault, VaultValuation, load_strategy_adapters, error::ErrorCode};
+
+/// Permissionless: only reads balances and exchange rates, so keepers can run it without admin keys.
+/// Remaining accounts: the valuation accounts of every strategy, in registry order
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Harvest<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        constraint = main_vault.vault_usdc_ata.key() == main_vault_usdc_ata.key(),
+        associated_token::mint=usdc_mint,
+        associated_token::authority=main_vault,
+        associated_token::token_program=token_program
+    )]
+    pub main_vault_usdc_ata : Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = main_vault.usdc_mint.key() == usdc_mint.key(),
+        mint::token_program=token_program
+    )]
+    pub usdc_mint : Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> Harvest<'info> {
+    pub fn harvest(&mut self, valuation: &VaultValuation, events: &EventEmitter<'info>) -> Result<()> {
+        book_harvest(&mut self.main_vault, valuation, events)
+    }
+}
+
+/// Marks every strategy to market, books the gain or loss since the last snapshot and
+/// moves the snapshots forward.
+pub fn book_harvest<'info>(vault: &mut Account<'info, Vault>, valuation: &VaultValuation, events: &EventEmitter<'info>) -> Result<()> {
+    let vault_key = vault.key();
+    let total_assets = valuation.total()?;
+    let share_price_before = vault.share_price(total_assets);
+    let realized_yield = vault.mark_to_market(vault_key, valuation, Clock::get()?.unix_timestamp, events)?;
+
+    events.emit(HarvestEvent {
+        vault: vault_key,
+        strategy_values: valuation.positions.clone(),
+        realized_yield: i64::try_from(realized_yield).map_err(|_| ErrorCode::MathOverflow)?,
+        share_price_before,
+        share_price_after: vault.share_price(total_assets),
+        total_underlying: vault.total_underlying,
+        total_shares: vault.total_shares,
+        acc_per_share: vault.acc_per_share,
+        timestamp: vault.last_update_ts,
+    })
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, Harvest<'info>>) -> Result<()> {
+    let vault_key = ctx.accounts.main_vault.key();
+    let adapters = load_strategy_adapters(&ctx.accounts.main_vault, vault_key, ctx.remaining_accounts, AdapterAccounts::Valuation, Freshness::Current)?;
+    let valuation = VaultValuation::load(ctx.accounts.main_vault_usdc_ata.amount, &adapters)?;
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.harvest(&valuation, &events)?;
+    Ok(())
+}
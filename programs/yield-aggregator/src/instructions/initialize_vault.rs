@@ -1,13 +1,28 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
 use anchor_spl::{associated_token::AssociatedToken, token_interface::{Mint, TokenAccount, TokenInterface}};
 
-use crate::Vault;
+use crate::{ALLOCATION_SCALE, EventEmitter, MIN_TIMELOCK_DELAY, SHARE_PRICE_SCALE, Vault, VaultInitialized, VaultStatus, error::ErrorCode};
 
+/// Only the program's upgrade authority opens vaults, so nobody can take a (mint, index) slot
+/// first and hand depositors a vault they control. `admin` becomes the vault's authority.
+#[event_cpi]
 #[derive(Accounts)]
+#[instruction(vault_index: u16)]
 pub struct InitializeVault<'info> {
     #[account(mut)]
     pub admin : Signer<'info>,
 
+    pub creator: Signer<'info>,
+
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(creator.key()) @ ErrorCode::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
     #[account(
         mint::token_program=token_program
     )]
@@ -26,39 +41,22 @@ pub struct InitializeVault<'info> {
         init,
         payer = admin,
         space = 8 + Vault::INIT_SPACE,
-        seeds = [b"vault", admin.key().as_ref()],
+        seeds = [b"vault", usdc_mint.key().as_ref(), &vault_index.to_le_bytes()],
         bump
     )]
     pub vault: Account<'info, Vault>,   // Global vault
 
-    // Jup f_token_account init
-    #[account(
-        init,
-        payer = admin,
-        associated_token::mint = jup_f_token_mint,
-        associated_token::authority = vault,
-        associated_token::token_program = token_program
-    )]
-    pub vault_f_token_ata: InterfaceAccount<'info, TokenAccount>,
-
-    #[account(
-        mint::token_program=token_program
-    )]
-    pub jup_f_token_mint : InterfaceAccount<'info, Mint>,
-
-    #[account(
-        mint::token_program=token_program
-    )]
-    pub kamino_collateral_mint: InterfaceAccount<'info, Mint>,
-
+    // Vault share token, minted on deposit and burned on withdraw
     #[account(
         init,
         payer = admin,
-        associated_token::mint= kamino_collateral_mint,
-        associated_token::authority= vault,
-        associated_token::token_program=token_program,
+        seeds = [b"share_mint", vault.key().as_ref()],
+        bump,
+        mint::decimals = usdc_mint.decimals,
+        mint::authority = vault,
+        mint::token_program = token_program
     )]
-    pub vault_kamino_token_ata: InterfaceAccount<'info, TokenAccount>,
+    pub share_mint: Box<InterfaceAccount<'info, Mint>>,
 
     pub system_program: Program<'info, System>,
     pub token_program: Interface<'info, TokenInterface>,
@@ -67,29 +65,59 @@ pub struct InitializeVault<'info> {
 }
 
 impl<'info> InitializeVault<'info> {
-    pub fn initialize_vault(&mut self, vault_bump: u8) -> Result<()>{
+    pub fn initialize_vault(&mut self, vault_index: u16, vault_bump: u8, events: &EventEmitter<'info>) -> Result<()>{
         let current_time = Clock::get().unwrap().unix_timestamp;
 
         // vault states
         self.vault.authority = self.admin.key();
+        self.vault.pending_authority = Pubkey::default();
+        self.vault.keeper = Pubkey::default(); // roles are unassigned, the admin holds them until `set_role`
+        self.vault.guardian = Pubkey::default();
+        self.vault.fee_manager = Pubkey::default();
         self.vault.usdc_mint = self.usdc_mint.key();
+        self.vault.vault_index = vault_index;
         self.vault.vault_usdc_ata = self.vault_usdc_ata.key();
+        self.vault.share_mint = self.share_mint.key();
         self.vault.total_shares = 0;
         self.vault.acc_per_share = 0;
         self.vault.total_underlying = 0;
-        self.vault.jup_lend_balance = 0;
-        self.vault.kamino_balance = 0;
-        self.vault.last_jup_value = 0;
-        self.vault.last_kamino_value = 0;
-        self.vault.jup_allocation = 5000; // 50 %
-        self.vault.kamino_allocation = 5000; // 50 %
+        self.vault.high_water_mark = SHARE_PRICE_SCALE as u64;
+        self.vault.strategies = Vec::new(); // markets are registered through `TimelockAction::AddStrategy`
+        self.vault.max_weight_change_bps = ALLOCATION_SCALE; // weights can move freely until limited
+        self.vault.weight_change_cooldown = 0;
+        self.vault.last_weight_change_ts = 0;
+        self.vault.idle_buffer_bps = 0; // everything above the buffer is deployed
+        self.vault.deposit_cap = u64::MAX;
+        self.vault.user_deposit_cap = u64::MAX;
+        self.vault.min_deposit = 0;
+        self.vault.status = VaultStatus::Active;
+        self.vault.timelock_delay = MIN_TIMELOCK_DELAY;
+        self.vault.next_action_id = 0;
+        self.vault.first_valid_action_id = 0;
+        self.vault.fee_recipient = self.admin.key();
+        self.vault.performance_fee_bps = 0;
+        self.vault.management_fee_bps = 0;
+        self.vault.deposit_fee_bps = 0;
+        self.vault.withdraw_fee_bps = 0;
+        self.vault.accrued_fee_shares = 0;
+        self.vault.last_fee_accrual_ts = current_time;
+        self.vault.swap_program = Pubkey::default(); // compounding stays off until `TimelockAction::SetSwapProgram`
         self.vault.last_update_ts = current_time;
         self.vault.bump = vault_bump;
-        Ok(())
+
+        events.emit(VaultInitialized {
+            vault: self.vault.key(),
+            authority: self.admin.key(),
+            usdc_mint: self.usdc_mint.key(),
+            vault_index,
+            share_mint: self.share_mint.key(),
+            timestamp: current_time,
+        })
     }
 }
 
-pub fn handler(ctx: Context<InitializeVault>)  -> Result<()> {
-    ctx.accounts.initialize_vault(ctx.bumps.vault)?;
+pub fn handler(ctx: Context<InitializeVault>, vault_index: u16)  -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.initialize_vault(vault_index, ctx.bumps.vault, &events)?;
     Ok(())
 }
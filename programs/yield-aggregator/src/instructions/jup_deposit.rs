@@ -1,22 +1,24 @@
 use anchor_lang::prelude::*;
 
-use anchor_spl::{associated_token::AssociatedToken, token::Token, token_interface::{Mint, TokenAccount, TokenInterface}};
+use anchor_spl::{associated_token::AssociatedToken, token::Token, token_interface::{Mint, TokenAccount}};
 
-use crate::error::ErrorCode;
-use crate::{Vault, jup_cpi};
-use crate::jup_accounts;
+use crate::{Adapter, AdapterAccounts, AdapterKind, AllocationDirection, AllocationEvent, EventEmitter, Freshness, JupLendAdapter, LendingAdapter, Role, Vault, VaultCpiAccounts, VaultValuation, load_strategy_adapters, error::ErrorCode};
 use crate::JupLendingProgram;
+use crate::vault_signer_seeds;
 
+/// Remaining accounts: the valuation accounts of every strategy, in registry order, read to
+/// report the share price at the live vault value
+#[event_cpi]
 #[derive(Accounts)]
 pub struct JupDeposit<'info> {
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub keeper: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"vault", admin.key().as_ref()],
-        // bump = main_vault.bump,
-        bump
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.has_role(keeper.key(), Role::Keeper) @ ErrorCode::Unauthorized
     )]
     pub main_vault: Account<'info, Vault>,
 
@@ -37,7 +39,8 @@ pub struct JupDeposit<'info> {
 
     #[account(
         mut,
-        constraint = main_vault.vault_usdc_ata.key() == main_vault_usdc_ata.key(), // We add this later
+        constraint = main_vault_f_token_ata.owner == main_vault.key()
+            && main_vault_f_token_ata.mint == f_token_mint.key() @ ErrorCode::InvalidLendingAccount,
         associated_token::mint=f_token_mint,
         associated_token::authority=main_vault,
         associated_token::token_program=token_program
@@ -56,17 +59,17 @@ pub struct JupDeposit<'info> {
 
     /// lending (mutable)
     #[account(mut)]
-    /// CHECK: Validated by lending program
+    /// CHECK: Must be the strategy's `market`
     pub lending: AccountInfo<'info>,
     
     /// supply_token_reserves_liquidity (mutable)
     #[account(mut)]
-    /// CHECK: Validated by lending program
+    /// CHECK: Must be the one `lending` points to
     pub supply_token_reserves_liquidity: AccountInfo<'info>,
     
     /// lending_supply_position_on_liquidity (mutable)
     #[account(mut)]
-    /// CHECK: Validated by lending program
+    /// CHECK: Must be the one `lending` points to
     pub lending_supply_position_on_liquidity: AccountInfo<'info>,
     
     /// rate_model (read-only)
@@ -94,7 +97,7 @@ pub struct JupDeposit<'info> {
     pub liquidity_program: AccountInfo<'info>,
 
     /// rewards_rate_model (read-only)
-    /// /// CHECK: Validated by lending program
+    /// CHECK: Must be the one `lending` points to
     pub rewards_rate_model: AccountInfo<'info>,
 
     /// CHECK: Validated by lending program
@@ -106,42 +109,74 @@ pub struct JupDeposit<'info> {
 }
 
 impl<'info> JupDeposit<'info> {
-    pub fn jup_deposit(&mut self, deposited_amount : u64) -> Result<()> {
-        // transfer to jup
-        let jup_accounts = jup_accounts::Deposit{
-            signer: self.main_vault.to_account_info() ,
-            depositor_token_account : self.main_vault_usdc_ata.to_account_info(),
-            recipient_token_account: self.main_vault_f_token_ata.to_account_info(),
-
-            associated_token_program : self.associated_token_program.to_account_info(),
-            f_token_mint: self.f_token_mint.to_account_info(),
-            lending: self.lending.to_account_info(),
-            lending_admin: self.lending_admin.to_account_info() ,
-            lending_supply_position_on_liquidity: self.lending_supply_position_on_liquidity.to_account_info() ,
-            liquidity: self.liquidity.to_account_info() , 
-            liquidity_program: self.liquidity_program.to_account_info() ,
-            mint: self.usdc_mint.to_account_info() , 
-            rate_model: self.rate_model.to_account_info() ,
-            rewards_rate_model: self.rewards_rate_model.to_account_info() ,
-            supply_token_reserves_liquidity: self.supply_token_reserves_liquidity.to_account_info() ,
-            system_program: self.system_program.to_account_info() ,
-            token_program: self.token_program.to_account_info() ,
-            vault: self.vault.to_account_info() , 
-        };
-
-        let admin_key = self.admin.key();
-        let signer_seeds: &[&[&[u8]]] = &[&[b"vault", admin_key.as_ref(),&[self.main_vault.bump]]];
+    /// Accounts of the JupLend adapter, in `JupLendAdapter` order
+    pub fn jup_accounts(&self) -> Vec<AccountInfo<'info>> {
+        vec![
+            self.main_vault_f_token_ata.to_account_info(),
+            self.lending.to_account_info(),
+            self.f_token_mint.to_account_info(),
+            self.lending_admin.to_account_info(),
+            self.supply_token_reserves_liquidity.to_account_info(),
+            self.lending_supply_position_on_liquidity.to_account_info(),
+            self.rate_model.to_account_info(),
+            self.vault.to_account_info(),
+            self.liquidity.to_account_info(),
+            self.liquidity_program.to_account_info(),
+            self.rewards_rate_model.to_account_info(),
+            self.lending_program.to_account_info(),
+            self.associated_token_program.to_account_info(),
+            self.system_program.to_account_info(),
+        ]
+    }
 
-        let jup_cpi_program = self.lending_program.to_account_info();
-        let jup_cpi_context = CpiContext::new_with_signer(jup_cpi_program, jup_accounts, signer_seeds);
-        match jup_cpi::deposit(jup_cpi_context, deposited_amount){
-            Ok(_) => Ok(()),
-            Err(_) => Err(ErrorCode::CpiToLendingProgramFailed.into())
-        }
+    /// Supplies `amount` idle USDC to JupLend and tracks the position value it added, which must
+    /// stay within the strategy's cap
+    pub fn jup_deposit(&mut self, adapters: &[Adapter<'_, 'info>], amount: u64, events: &EventEmitter<'info>) -> Result<()> {
+        let idle_before = self.main_vault_usdc_ata.amount;
+        let before = VaultValuation::load(idle_before, adapters)?;
+        let index = self.main_vault.find_strategy(AdapterKind::JupLend, &self.main_vault_f_token_ata.key(), &self.lending.key())?;
+        let jup_accounts = self.jup_accounts();
+        let jup = JupLendAdapter::load(&self.main_vault.strategies[index], self.main_vault.key(), &jup_accounts)?;
+        require!(self.main_vault.status.allows_allocations(), ErrorCode::AllocationsPaused);
+        require!(self.main_vault.strategies[index].enabled, ErrorCode::StrategyDisabled);
+        let value_before = jup.position_value()?;
+        let signer_seeds: &[&[&[u8]]] = vault_signer_seeds!(self.main_vault);
+        let vault = VaultCpiAccounts {
+            vault: self.main_vault.to_account_info(),
+            vault_usdc_ata: self.main_vault_usdc_ata.to_account_info(),
+            usdc_mint: self.usdc_mint.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            signer_seeds,
+        };
+        jup.deposit(&vault, amount)?;
+
+        let value_after = jup.position_value()?;
+        self.main_vault.strategies[index].check_cap(value_after)?;
+        self.main_vault.track_position(index, value_before, value_after);
+
+        self.main_vault_usdc_ata.reload()?;
+        let idle = self.main_vault_usdc_ata.amount;
+        let after = VaultValuation::load(idle, adapters)?;
+        events.emit(AllocationEvent {
+            vault: self.main_vault.key(),
+            kind: AdapterKind::JupLend,
+            market: self.lending.key(),
+            direction: AllocationDirection::Allocate,
+            usdc_amount: idle_before.saturating_sub(idle),
+            position_value_before: value_before,
+            position_value_after: value_after,
+            idle,
+            share_price_before: self.main_vault.share_price(before.total()?),
+            share_price_after: self.main_vault.share_price(after.total()?),
+            timestamp: Clock::get()?.unix_timestamp,
+        })
     }
 }
 
-pub fn handler(ctx: Context<JupDeposit>, amount: u64) -> Result<()> {
-    ctx.accounts.jup_deposit(amount)?;
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, JupDeposit<'info>>, amount: u64) -> Result<()> {
+    let vault_key = ctx.accounts.main_vault.key();
+    let adapters = load_strategy_adapters(&ctx.accounts.main_vault, vault_key, ctx.remaining_accounts, AdapterAccounts::Valuation, Freshness::Current)?;
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.jup_deposit(&adapters, amount, &events)?;
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,19 +1,22 @@
-pub use anchor_lang::prelude::*;
-use anchor_lang::solana_program::{instruction::Instruction, program::{invoke_signed}};
+use anchor_lang::{prelude::*, solana_program::sysvar};
 use anchor_spl::{associated_token::AssociatedToken, token::Token, token_interface::{Mint, TokenAccount}};
-use crate::Vault;
+use crate::{Adapter, AdapterAccounts, AdapterKind, AllocationDirection, AllocationEvent, EventEmitter, Freshness, KAMINO_LENDING_PROGRAM_ID, KaminoAdapter, LendingAdapter, Role, Vault, VaultCpiAccounts, VaultValuation, load_strategy_adapters, error::ErrorCode};
+use crate::vault_signer_seeds;
 
+/// Remaining accounts: the valuation accounts of every strategy, in registry order, read to
+/// report the share price at the live vault value
+#[event_cpi]
 #[derive(Accounts)]
 pub struct KaminoDeposit<'info> {
 
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub keeper: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"vault", admin.key().as_ref()],
-        // bump = main_vault.bump,
-        bump
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.has_role(keeper.key(), Role::Keeper) @ ErrorCode::Unauthorized
     )]
     pub main_vault: Account<'info, Vault>,
 
@@ -32,21 +35,21 @@ pub struct KaminoDeposit<'info> {
     )]
     pub usdc_mint : InterfaceAccount<'info, Mint>,   // USDC Mint
 
-    /// CHECK: Kamino reserve account
+    /// CHECK: Kamino reserve account, must be the strategy's `market`
     #[account(mut)]
     pub reserve: UncheckedAccount<'info>,
 
-    /// CHECK: Lending market that the reserve belongs to
+    /// CHECK: Lending market that the reserve belongs to, must be the strategy's `lending_market`
     pub lending_market: UncheckedAccount<'info>,
 
     /// CHECK: PDA authority for the lending market
     pub lending_market_authority: UncheckedAccount<'info>,
 
-    /// CHECK: Token account that stores liquidity supplied to reserve
+    /// CHECK: Token account that stores liquidity supplied to reserve, must be the reserve's supply vault
     #[account(mut)]
     pub reserve_liquidity_supply: UncheckedAccount<'info>,
 
-    /// CHECK: Mint of the collateral token
+    /// CHECK: Mint of the collateral token, must be the strategy's `position_mint`
     #[account(mut)]
     pub reserve_collateral_mint: UncheckedAccount<'info>,
 
@@ -72,9 +75,11 @@ pub struct KaminoDeposit<'info> {
     pub liquidity_token_program: UncheckedAccount<'info>,
 
     /// CHECK: Instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
     pub instruction_sysvar_account: UncheckedAccount<'info>,
 
     /// CHECK : klend program account
+    #[account(address = KAMINO_LENDING_PROGRAM_ID)]
     pub klend_program: UncheckedAccount<'info>,
 
     pub system_program: Program<'info, System>,
@@ -82,62 +87,72 @@ pub struct KaminoDeposit<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
-pub fn get_deposit_reserve_liquidity_discriminator()-> Vec<u8> {
-    // discriminator = sha256("global:deposit_reserve_liquidity")[0..8]
-    vec![169, 201, 30, 126, 6, 205, 102, 68]
-}
-
 impl<'info> KaminoDeposit<'info> {
-    pub fn kamino_deposit(&mut self, deposited_amount : u64) -> Result<()>{
-        let mut instruction_data = get_deposit_reserve_liquidity_discriminator();
-        instruction_data.extend_from_slice(&deposited_amount.to_le_bytes());
-
-        let accounts = vec![
-            AccountMeta::new_readonly(self.main_vault.key(), true),    // signer
-            AccountMeta::new(self.reserve.key(), false),
-            AccountMeta::new_readonly(self.lending_market.key(), false),
-            AccountMeta::new_readonly(self.lending_market_authority.key(), false),
-            AccountMeta::new_readonly(self.usdc_mint.key(), false),
-            AccountMeta::new(self.reserve_liquidity_supply.key(), false),
-            AccountMeta::new(self.reserve_collateral_mint.key(), false),
-            AccountMeta::new(self.main_vault_usdc_ata.key(), false),
-            AccountMeta::new(self.main_vault_kamino_token_ata_collateral.key(), false),
-            AccountMeta::new_readonly(self.collateral_token_program.key(), false),
-            AccountMeta::new_readonly(self.liquidity_token_program.key(), false),
-            AccountMeta::new_readonly(self.instruction_sysvar_account.key(), false),
-        ];
-        
-        let ix = Instruction {
-            program_id: self.klend_program.key(),
-            accounts,
-            data : instruction_data,
-        };
-
-        let account_infos = [
-            self.main_vault.to_account_info(),
+    /// Accounts of the Kamino adapter, in `KaminoAdapter` order
+    pub fn kamino_accounts(&self) -> Vec<AccountInfo<'info>> {
+        vec![
+            self.main_vault_kamino_token_ata_collateral.to_account_info(),
             self.reserve.to_account_info(),
             self.lending_market.to_account_info(),
             self.lending_market_authority.to_account_info(),
-            self.usdc_mint.to_account_info(),
             self.reserve_liquidity_supply.to_account_info(),
             self.reserve_collateral_mint.to_account_info(),
-            self.main_vault_usdc_ata.to_account_info(),
-            self.main_vault_kamino_token_ata_collateral.to_account_info(),
             self.collateral_token_program.to_account_info(),
             self.liquidity_token_program.to_account_info(),
             self.instruction_sysvar_account.to_account_info(),
-        ];
-
-        let admin_key = self.admin.key();
-        let signer_seeds: &[&[&[u8]]] = &[&[b"vault", admin_key.as_ref(),&[self.main_vault.bump]]];
-
-        invoke_signed(&ix, &account_infos, signer_seeds)?;
+            self.klend_program.to_account_info(),
+        ]
+    }
 
-        Ok(())
+    /// Supplies `amount` idle USDC to the Kamino reserve and tracks the position value it added,
+    /// which must stay within the strategy's cap
+    pub fn kamino_deposit(&mut self, adapters: &[Adapter<'_, 'info>], amount: u64, events: &EventEmitter<'info>) -> Result<()> {
+        let idle_before = self.main_vault_usdc_ata.amount;
+        let before = VaultValuation::load(idle_before, adapters)?;
+        let index = self.main_vault.find_strategy(AdapterKind::Kamino, &self.main_vault_kamino_token_ata_collateral.key(), &self.reserve.key())?;
+        let kamino_accounts = self.kamino_accounts();
+        let kamino = KaminoAdapter::load(&self.main_vault.strategies[index], self.main_vault.key(), &kamino_accounts, Freshness::Current)?;
+        require!(self.main_vault.status.allows_allocations(), ErrorCode::AllocationsPaused);
+        require!(self.main_vault.strategies[index].enabled, ErrorCode::StrategyDisabled);
+        let value_before = kamino.position_value()?;
+
+        let signer_seeds: &[&[&[u8]]] = vault_signer_seeds!(self.main_vault);
+        let vault = VaultCpiAccounts {
+            vault: self.main_vault.to_account_info(),
+            vault_usdc_ata: self.main_vault_usdc_ata.to_account_info(),
+            usdc_mint: self.usdc_mint.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            signer_seeds,
+        };
+        kamino.deposit(&vault, amount)?;
+
+        let value_after = kamino.position_value()?;
+        self.main_vault.strategies[index].check_cap(value_after)?;
+        self.main_vault.track_position(index, value_before, value_after);
+
+        self.main_vault_usdc_ata.reload()?;
+        let idle = self.main_vault_usdc_ata.amount;
+        let after = VaultValuation::load(idle, adapters)?;
+        events.emit(AllocationEvent {
+            vault: self.main_vault.key(),
+            kind: AdapterKind::Kamino,
+            market: self.reserve.key(),
+            direction: AllocationDirection::Allocate,
+            usdc_amount: idle_before.saturating_sub(idle),
+            position_value_before: value_before,
+            position_value_after: value_after,
+            idle,
+            share_price_before: self.main_vault.share_price(before.total()?),
+            share_price_after: self.main_vault.share_price(after.total()?),
+            timestamp: Clock::get()?.unix_timestamp,
+        })
     }
 }
 
-pub fn handler(ctx : Context<KaminoDeposit>, amount : u64) -> Result<()>{
-    ctx.accounts.kamino_deposit(amount)?;
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, KaminoDeposit<'info>>, amount: u64) -> Result<()> {
+    let vault_key = ctx.accounts.main_vault.key();
+    let adapters = load_strategy_adapters(&ctx.accounts.main_vault, vault_key, ctx.remaining_accounts, AdapterAccounts::Valuation, Freshness::Current)?;
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.kamino_deposit(&adapters, amount, &events)?;
     Ok(())
-}
\ No newline at end of file
+}
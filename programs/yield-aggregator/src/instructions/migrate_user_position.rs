@@ -0,0 +1,149 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{associated_token::AssociatedToken, token_interface::{Mint, MintTo, TokenAccount, TokenInterface, mint_to}};
+
+use crate::{ACC_PER_SHARE_SCALER, EventEmitter, LegacyUserPosition, MigratedVault, PositionMigrated, UserPosition, Vault, error::ErrorCode};
+use crate::vault_signer_seeds;
+
+/// Exchanges a legacy position for share tokens, its pro-rata part of the vault shares `migrate_vault`
+/// booked for the legacy vault, and closes it. Its rewards carry over to the user's position.
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateUserPosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(constraint = vault.share_mint == share_mint.key())]
+    pub vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", migrated_vault.authority.as_ref()],
+        bump = migrated_vault.bump,
+        has_one = vault
+    )]
+    pub migrated_vault: Box<Account<'info, MigratedVault>>,
+
+    /// CHECK: read with `LegacyUserPosition::load`, closed once migrated
+    #[account(
+        mut,
+        seeds = [b"user_position", user.key().as_ref()],
+        bump
+    )]
+    pub legacy_position: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", vault.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub user_position: Box<Account<'info, UserPosition>>,
+
+    #[account(
+        mut,
+        seeds = [b"share_mint", vault.key().as_ref()],
+        bump
+    )]
+    pub share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = share_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program
+    )]
+    pub user_share_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> MigrateUserPosition<'info> {
+    /// Adds the legacy rewards, and the ones accrued on the shares the user already holds, to the
+    /// user's position and checkpoints it on the shares held once `shares` are minted
+    pub fn settle_rewards(&mut self, legacy: &LegacyUserPosition, shares: u64, bump: u8) -> Result<()> {
+        let acc_per_share = self.vault.acc_per_share as u128;
+        let user_shares = self.user_share_ata.amount;
+        let position = &mut self.user_position;
+        if position.user == Pubkey::default() {
+            position.vault = self.vault.key();
+            position.user = self.user.key();
+            position.bump = bump;
+        }
+
+        let accrued = (user_shares as u128)
+            .checked_mul(acc_per_share)
+            .ok_or(ErrorCode::MathOverflow)?
+            .saturating_sub(position.reward_debt);
+        let legacy_accrued = (legacy.shares as u128)
+            .checked_mul(self.migrated_vault.legacy_acc_per_share as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .saturating_sub(legacy.reward_debt);
+        let pending = (position.pending_rewards as u128)
+            .checked_add(legacy.pending_rewards as u128)
+            .and_then(|v| v.checked_add(accrued / ACC_PER_SHARE_SCALER))
+            .and_then(|v| v.checked_add(legacy_accrued / ACC_PER_SHARE_SCALER))
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        position.pending_rewards = u64::try_from(pending).map_err(|_| ErrorCode::MathOverflow)?;
+        position.reward_debt = (user_shares as u128)
+            .checked_add(shares as u128)
+            .and_then(|v| v.checked_mul(acc_per_share))
+            .ok_or(ErrorCode::MathOverflow)?;
+        position.last_updated = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Mints `shares` to the user. `Vault::total_shares` already counts them since `migrate_vault`.
+    pub fn mint_shares(&mut self, shares: u64) -> Result<()> {
+        let accounts = MintTo {
+            mint: self.share_mint.to_account_info(),
+            to: self.user_share_ata.to_account_info(),
+            authority: self.vault.to_account_info(),
+        };
+
+        let signer_seeds: &[&[&[u8]]] = vault_signer_seeds!(self.vault);
+        let cpi_context = CpiContext::new_with_signer(self.token_program.to_account_info(), accounts, signer_seeds);
+        mint_to(cpi_context, shares)?;
+
+        self.share_mint.reload()?;
+        self.vault.check_share_supply(self.share_mint.supply)
+    }
+
+    /// Returns the legacy position's rent to the user and hands the account back to the system program
+    pub fn close_legacy_position(&self) -> Result<()> {
+        let info = self.legacy_position.to_account_info();
+        let user = self.user.to_account_info();
+        **user.try_borrow_mut_lamports()? = user.lamports().checked_add(info.lamports()).ok_or(ErrorCode::MathOverflow)?;
+        **info.try_borrow_mut_lamports()? = 0;
+        info.resize(0)?;
+        info.assign(&anchor_lang::system_program::ID);
+        Ok(())
+    }
+}
+
+pub fn handler(ctx: Context<MigrateUserPosition>) -> Result<()> {
+    let legacy = LegacyUserPosition::load(&ctx.accounts.legacy_position)?;
+    require_keys_eq!(legacy.vault, ctx.accounts.migrated_vault.key(), ErrorCode::InvalidLegacyAccount);
+    require_keys_eq!(legacy.user, ctx.accounts.user.key(), ErrorCode::InvalidLegacyAccount);
+
+    let shares = ctx.accounts.migrated_vault.take_shares(legacy.shares)?;
+    ctx.accounts.settle_rewards(&legacy, shares, ctx.bumps.user_position)?;
+    // Dust positions can round down to nothing, they are closed all the same
+    if shares > 0 {
+        ctx.accounts.mint_shares(shares)?;
+    }
+    ctx.accounts.close_legacy_position()?;
+
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    events.emit(PositionMigrated {
+        vault: ctx.accounts.vault.key(),
+        user: ctx.accounts.user.key(),
+        legacy_shares: legacy.shares,
+        shares,
+        timestamp: ctx.accounts.user_position.last_updated,
+    })
+}
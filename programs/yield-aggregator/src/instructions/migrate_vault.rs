@@ -0,0 +1,181 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, Transfer, transfer};
+
+use crate::{AdapterAccounts, EventEmitter, Freshness, LegacyVault, MigratedVault, Vault, VaultMigrated, VaultValuation, load_strategy_adapters, read_vault_token_account, strategy_accounts_len, error::ErrorCode};
+
+/// Moves everything a legacy vault holds into `vault` and books vault shares for its holders at
+/// the live share price. The legacy account is rewritten in place as a `MigratedVault`, which its
+/// holders then claim their shares from with `migrate_user_position`.
+///
+/// Remaining accounts: the valuation accounts of every strategy of `vault`, in registry order,
+/// then the legacy vault's position token accounts, each moved into the strategy of its mint
+#[event_cpi]
+#[derive(Accounts)]
+pub struct MigrateVault<'info> {
+    // Admin of the legacy vault, and of the vault its funds move to
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: read with `LegacyVault::load`, then rewritten as a `MigratedVault`
+    #[account(
+        mut,
+        seeds = [b"vault", authority.key().as_ref()],
+        bump
+    )]
+    pub legacy_vault: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = vault.usdc_mint == usdc_mint.key()
+    )]
+    pub vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        mut,
+        token::mint = usdc_mint,
+        token::authority = legacy_vault,
+        token::token_program = token_program
+    )]
+    pub legacy_usdc_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = vault.vault_usdc_ata == vault_usdc_ata.key(),
+        associated_token::mint = usdc_mint,
+        associated_token::authority = vault,
+        associated_token::token_program = token_program
+    )]
+    pub vault_usdc_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub usdc_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateVault<'info> {
+    /// Transfers `amount` tokens out of an account of the legacy vault
+    fn transfer_from_legacy(&self, from: AccountInfo<'info>, to: AccountInfo<'info>, amount: u64, bump: u8) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let authority = self.authority.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"vault".as_ref(), authority.as_ref(), &[bump]]];
+        transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                Transfer { from, to, authority: self.legacy_vault.to_account_info() },
+                signer_seeds,
+            ),
+            amount,
+        )
+    }
+
+    /// Moves the legacy USDC into the vault ATA and every legacy position token account into the
+    /// position of the strategy holding the same mint
+    pub fn move_funds(&mut self, legacy: &LegacyVault, strategy_accounts: &[AccountInfo<'info>], legacy_positions: &[AccountInfo<'info>], bump: u8) -> Result<()> {
+        require_keys_eq!(self.legacy_usdc_ata.key(), legacy.vault_usdc_ata, ErrorCode::InvalidLegacyAccount);
+        self.transfer_from_legacy(
+            self.legacy_usdc_ata.to_account_info(),
+            self.vault_usdc_ata.to_account_info(),
+            self.legacy_usdc_ata.amount,
+            bump,
+        )?;
+
+        for info in legacy_positions {
+            let legacy_position = read_vault_token_account(info, &self.legacy_vault.key())?;
+            let strategy = self
+                .vault
+                .strategies
+                .iter()
+                .find(|strategy| strategy.position_mint == legacy_position.mint)
+                .ok_or(ErrorCode::StrategyNotFound)?;
+            // The strategy's position account is among its valuation accounts, already checked by its adapter
+            let position = strategy_accounts
+                .iter()
+                .find(|account| account.key() == strategy.position_token_account)
+                .ok_or(ErrorCode::MissingAdapterAccounts)?;
+            self.transfer_from_legacy(info.clone(), position.clone(), legacy_position.amount, bump)?;
+        }
+
+        self.vault_usdc_ata.reload()
+    }
+
+    /// Books the value `after` gained over `before` as vault shares for the legacy holders, priced
+    /// like a deposit at the vault value before the move. Returns the shares and the value moved.
+    pub fn book_shares(&mut self, before: &VaultValuation, after: &VaultValuation) -> Result<(u64, u64)> {
+        let total_before = before.total()?;
+        let assets = after.total()?.checked_sub(total_before).ok_or(ErrorCode::MathOverflow)?;
+        require!(assets > 0, ErrorCode::NothingToMigrate);
+
+        let shares = self.vault.convert_to_shares(assets, total_before)?;
+        require!(shares > 0, ErrorCode::ZeroShares);
+
+        for i in 0..self.vault.strategies.len() {
+            self.vault.track_position(i, before.positions[i], after.positions[i]);
+        }
+        self.vault.total_shares = self.vault.total_shares.checked_add(shares).ok_or(ErrorCode::MathOverflow)?;
+        self.vault.total_underlying = self.vault.total_underlying.checked_add(assets).ok_or(ErrorCode::MathOverflow)?;
+        Ok((shares, assets))
+    }
+
+    /// Rewrites the legacy vault as `migrated`, shrinking it and refunding the rent it no longer needs
+    pub fn rewrite_legacy_vault(&self, migrated: &MigratedVault) -> Result<()> {
+        let info = self.legacy_vault.to_account_info();
+        let len = 8 + MigratedVault::INIT_SPACE;
+        {
+            let mut data = info.try_borrow_mut_data()?;
+            let mut writer: &mut [u8] = &mut data;
+            migrated.try_serialize(&mut writer)?;
+        }
+        info.resize(len)?;
+
+        let excess = info.lamports().saturating_sub(Rent::get()?.minimum_balance(len));
+        **info.try_borrow_mut_lamports()? -= excess;
+        **self.authority.to_account_info().try_borrow_mut_lamports()? += excess;
+        Ok(())
+    }
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, MigrateVault<'info>>) -> Result<()> {
+    let legacy = LegacyVault::load(&ctx.accounts.legacy_vault)?;
+    require!(legacy.total_shares > 0, ErrorCode::NothingToMigrate);
+    require_keys_eq!(legacy.usdc_mint, ctx.accounts.vault.usdc_mint, ErrorCode::InvalidLegacyAccount);
+    require!(ctx.accounts.vault.status.accepts_deposits(), ErrorCode::DepositsPaused);
+
+    let vault_key = ctx.accounts.vault.key();
+    let (strategy_accounts, legacy_positions) = ctx
+        .remaining_accounts
+        .split_at(strategy_accounts_len(&ctx.accounts.vault, AdapterAccounts::Valuation).min(ctx.remaining_accounts.len()));
+    let adapters = load_strategy_adapters(&ctx.accounts.vault, vault_key, strategy_accounts, AdapterAccounts::Valuation, Freshness::Current)?;
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+
+    // Yield earned so far belongs to the current holders, book it before the legacy funds arrive
+    let before = VaultValuation::load(ctx.accounts.vault_usdc_ata.amount, &adapters)?;
+    ctx.accounts.vault.mark_to_market(vault_key, &before, Clock::get()?.unix_timestamp, &events)?;
+
+    ctx.accounts.move_funds(&legacy, strategy_accounts, legacy_positions, ctx.bumps.legacy_vault)?;
+    let after = VaultValuation::load(ctx.accounts.vault_usdc_ata.amount, &adapters)?;
+    let (shares, assets) = ctx.accounts.book_shares(&before, &after)?;
+
+    let migrated = MigratedVault {
+        authority: ctx.accounts.authority.key(),
+        vault: vault_key,
+        legacy_shares: legacy.total_shares,
+        vault_shares: shares,
+        legacy_acc_per_share: legacy.acc_per_share,
+        bump: ctx.bumps.legacy_vault,
+    };
+    ctx.accounts.rewrite_legacy_vault(&migrated)?;
+
+    events.emit(VaultMigrated {
+        vault: vault_key,
+        legacy_vault: ctx.accounts.legacy_vault.key(),
+        assets,
+        shares,
+        legacy_shares: legacy.total_shares,
+        share_price: ctx.accounts.vault.share_price(after.total()?),
+        timestamp: ctx.accounts.vault.last_update_ts,
+    })
+}
@@ -1,17 +1,65 @@
+#![allow(ambiguous_glob_reexports)]
+
 pub mod initialize_vault;
 pub mod deposit;
+pub mod deposit_and_allocate;
+pub mod set_idle_buffer;
+pub mod set_deposit_limits;
+pub mod set_fee_recipient;
+pub mod claim_fees;
 pub mod withdraw;
+pub mod withdraw_shares;
+pub mod withdraw_assets;
 pub mod kamino_deposit;
 pub mod kamino_withdraw;
-pub mod sync_vault_state;
 pub mod jup_deposit;
 pub mod jup_withdraw;
+pub mod remove_strategy;
+pub mod set_strategy_weight;
+pub mod propose_authority;
+pub mod accept_authority;
+pub mod queue_action;
+pub mod execute_action;
+pub mod cancel_action;
+pub mod set_vault_status;
+pub mod set_role;
+pub mod revoke_role;
+pub mod emergency_unwind;
+pub mod rebalance;
+pub mod harvest;
+pub mod compound;
+pub mod claim_jup_rewards;
+pub mod vault_views;
+pub mod migrate_vault;
+pub mod migrate_user_position;
 
 pub use initialize_vault::*;
 pub use deposit::*;
+pub use deposit_and_allocate::*;
+pub use set_idle_buffer::*;
+pub use set_deposit_limits::*;
+pub use set_fee_recipient::*;
+pub use claim_fees::*;
 pub use withdraw::*;
 pub use kamino_deposit::*;
 pub use kamino_withdraw::*;
-pub use sync_vault_state::*;
 pub use jup_deposit::*;
-pub use jup_withdraw::*;
\ No newline at end of file
+pub use jup_withdraw::*;
+pub use remove_strategy::*;
+pub use set_strategy_weight::*;
+pub use propose_authority::*;
+pub use accept_authority::*;
+pub use queue_action::*;
+pub use execute_action::*;
+pub use cancel_action::*;
+pub use set_vault_status::*;
+pub use set_role::*;
+pub use revoke_role::*;
+pub use emergency_unwind::*;
+pub use rebalance::*;
+pub use harvest::*;
+pub use compound::*;
+pub use claim_jup_rewards::*;
+pub use vault_views::*;
+pub use migrate_vault::*;
+pub use migrate_user_position::*;
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, Vault};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.authority == admin.key()
+    )]
+    pub main_vault: Account<'info, Vault>,
+
+    /// CHECK: any wallet can be proposed, it has to sign `accept_authority` to take over
+    pub new_authority: UncheckedAccount<'info>,
+}
+
+impl<'info> ProposeAuthority<'info> {
+    /// Proposing `Pubkey::default()` cancels a pending transfer
+    pub fn propose_authority(&mut self, events: &EventEmitter<'info>) -> Result<()> {
+        self.main_vault.pending_authority = self.new_authority.key();
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.admin.key(),
+            change: ConfigChange::AuthorityProposed { pending_authority: self.new_authority.key() },
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<ProposeAuthority>) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.propose_authority(&events)?;
+    Ok(())
+}
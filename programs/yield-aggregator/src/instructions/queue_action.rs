@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, MIN_TIMELOCK_DELAY, QueuedAction, Role, TimelockAction, Vault, error::ErrorCode};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct QueueAction<'info> {
+    /// The admin, or the fee manager for `TimelockAction::SetFees`
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + QueuedAction::INIT_SPACE,
+        seeds = [b"queued_action", main_vault.key().as_ref(), &main_vault.next_action_id.to_le_bytes()],
+        bump
+    )]
+    pub queued_action: Box<Account<'info, QueuedAction>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> QueueAction<'info> {
+    /// Queues `action`, it can be executed once the vault's timelock delay has passed
+    pub fn queue_action(&mut self, action: TimelockAction, bump: u8, events: &EventEmitter<'info>) -> Result<()> {
+        let vault = &mut self.main_vault;
+        let proposer = self.proposer.key();
+        let authorized = match action {
+            TimelockAction::SetFees { .. } => vault.has_role(proposer, Role::FeeManager),
+            _ => proposer == vault.authority,
+        };
+        require!(authorized, ErrorCode::Unauthorized);
+        if let TimelockAction::SetTimelockDelay { delay } = action {
+            require!(delay >= MIN_TIMELOCK_DELAY, ErrorCode::InvalidTimelockDelay);
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        let eta = now
+            .checked_add(vault.timelock_delay)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let id = vault.next_action_id;
+        self.queued_action.set_inner(QueuedAction {
+            vault: vault.key(),
+            id,
+            proposer,
+            action: action.clone(),
+            eta,
+            bump,
+        });
+        vault.next_action_id = id.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.proposer.key(),
+            change: ConfigChange::ActionQueued { id, action, eta },
+            timestamp: now,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<QueueAction>, action: TimelockAction) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.queue_action(action, ctx.bumps.queued_action, &events)?;
+    Ok(())
+}
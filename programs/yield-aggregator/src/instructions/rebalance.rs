@@ -0,0 +1,141 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{token::Token, token_interface::{Mint, TokenAccount}};
+
+use crate::{Adapter, AdapterAccounts, EventEmitter, Freshness, LendingAdapter, RebalanceEvent, Role, Rounding, Vault, VaultCpiAccounts, VaultValuation, load_strategy_adapters, error::ErrorCode};
+use crate::vault_signer_seeds;
+
+/// Remaining accounts: the CPI accounts of every strategy, in registry order
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Rebalance<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.has_role(keeper.key(), Role::Keeper) @ ErrorCode::Unauthorized
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+
+    #[account(
+        mut,
+        constraint = main_vault.vault_usdc_ata.key() == main_vault_usdc_ata.key(),
+        associated_token::mint=usdc_mint,
+        associated_token::authority=main_vault,
+        associated_token::token_program=token_program
+    )]
+    pub main_vault_usdc_ata : Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = main_vault.usdc_mint.key() == usdc_mint.key(),
+        mint::token_program=token_program
+    )]
+    pub usdc_mint : Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> Rebalance<'info> {
+    pub fn valuation(&mut self, adapters: &[Adapter<'_, 'info>]) -> Result<VaultValuation> {
+        self.main_vault_usdc_ata.reload()?;
+        VaultValuation::load(self.main_vault_usdc_ata.amount, adapters)
+    }
+
+    /// Moves funds between the strategies so each one holds its target share of the
+    /// vault's total value, then derives the vault accounting from the resulting balances.
+    pub fn rebalance(&mut self, adapters: &[Adapter<'_, 'info>], weights: &[u16], events: &EventEmitter<'info>) -> Result<()> {
+        require!(self.main_vault.status.allows_allocations(), ErrorCode::AllocationsPaused);
+        let now = Clock::get()?.unix_timestamp;
+        self.main_vault.set_weights(weights, now)?;
+
+        let before = self.valuation(adapters)?;
+        let total_before = before.total()?;
+        let share_price_before = self.main_vault.share_price(total_before);
+
+        // Book the yield since the last snapshot, and the fees owed on it, before funds move
+        let vault_key = self.main_vault.key();
+        let realized_yield = self.main_vault.mark_to_market(vault_key, &before, now, events)?;
+
+        // The idle buffer stays in the vault ATA, only the rest is split between the strategies
+        let investable = total_before.saturating_sub(self.main_vault.idle_buffer_target(total_before));
+        let targets = self.main_vault.strategy_targets(investable);
+
+        let signer_seeds: &[&[&[u8]]] = vault_signer_seeds!(self.main_vault);
+        let vault = VaultCpiAccounts {
+            vault: self.main_vault.to_account_info(),
+            vault_usdc_ata: self.main_vault_usdc_ata.to_account_info(),
+            usdc_mint: self.usdc_mint.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            signer_seeds,
+        };
+
+        // Pull funds out of the over-allocated strategies first so they can be redeployed. A strategy
+        // with no target redeems its whole position, rounding down would leave dust that blocks its removal.
+        for (i, adapter) in adapters.iter().enumerate() {
+            if targets[i] == 0 {
+                let position_amount = adapter.position_amount()?;
+                if position_amount > 0 {
+                    adapter.withdraw(&vault, position_amount)?;
+                }
+            } else if before.positions[i] > targets[i] {
+                adapter.withdraw_value(&vault, before.positions[i] - targets[i], Rounding::Down)?;
+            }
+        }
+
+        // Deploy idle USDC into the under-allocated strategies
+        for (i, adapter) in adapters.iter().enumerate() {
+            if targets[i] > before.positions[i] {
+                self.main_vault_usdc_ata.reload()?;
+                let amount = (targets[i] - before.positions[i]).min(self.main_vault_usdc_ata.amount);
+                if amount > 0 {
+                    adapter.deposit(&vault, amount)?;
+                }
+            }
+        }
+
+        let after = self.valuation(adapters)?;
+        self.update_states(&after)?;
+        let total_after = after.total()?;
+
+        let vault = &self.main_vault;
+        events.emit(RebalanceEvent {
+            vault: vault_key,
+            weights: weights.to_vec(),
+            strategy_values_before: before.positions,
+            strategy_values_after: after.positions,
+            idle_before: before.idle,
+            idle_after: after.idle,
+            realized_yield: i64::try_from(realized_yield).map_err(|_| ErrorCode::MathOverflow)?,
+            share_price_before,
+            share_price_after: vault.share_price(total_after),
+            total_underlying: vault.total_underlying,
+            total_shares: vault.total_shares,
+            timestamp: vault.last_update_ts,
+        })
+    }
+
+    /// Accounting is derived from on-chain balances only, the yield was booked by `mark_to_market`
+    /// before any funds moved.
+    fn update_states(&mut self, after: &VaultValuation) -> Result<()> {
+        let vault = &mut self.main_vault;
+
+        for (strategy, value) in vault.strategies.iter_mut().zip(&after.positions) {
+            strategy.last_value = *value;
+        }
+        vault.total_underlying = after.total()?;
+        vault.last_update_ts = Clock::get()?.unix_timestamp;
+
+        Ok(())
+    }
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, Rebalance<'info>>, weights: Vec<u16>) -> Result<()> {
+    let vault_key = ctx.accounts.main_vault.key();
+    let adapters = load_strategy_adapters(&ctx.accounts.main_vault, vault_key, ctx.remaining_accounts, AdapterAccounts::Cpi, Freshness::Current)?;
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.rebalance(&adapters, &weights, &events)?;
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::{Adapter, AdapterAccounts, ConfigChange, ConfigChanged, EventEmitter, Freshness, LendingAdapter, Vault, error::ErrorCode};
+
+/// Remaining accounts: the valuation accounts of the strategy to remove
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RemoveStrategy<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.authority == admin.key()
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+}
+
+impl<'info> RemoveStrategy<'info> {
+    /// Retires a strategy with no target weight, see `Vault::remove_strategy`. Calling this again
+    /// once the strategy is drained drops it from the registry.
+    pub fn remove_strategy(&mut self, strategy_index: u8, accounts: &[AccountInfo<'info>], events: &EventEmitter<'info>) -> Result<()> {
+        let index = strategy_index as usize;
+        let vault_key = self.main_vault.key();
+        let strategy = self.main_vault.strategies.get(index).ok_or(ErrorCode::StrategyNotFound)?;
+        let market = strategy.market;
+        require!(accounts.len() == strategy.kind.accounts_len(AdapterAccounts::Valuation), ErrorCode::MissingAdapterAccounts);
+        let position_value = Adapter::load(strategy, vault_key, accounts, Freshness::Current)?.position_value()?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let removed = self.main_vault.remove_strategy(vault_key, index, position_value, now, events)?;
+
+        events.emit(ConfigChanged {
+            vault: vault_key,
+            signer: self.admin.key(),
+            change: ConfigChange::StrategyRemoved { market, removed },
+            timestamp: now,
+        })
+    }
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, RemoveStrategy<'info>>, strategy_index: u8) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.remove_strategy(strategy_index, ctx.remaining_accounts, &events)?;
+    Ok(())
+}
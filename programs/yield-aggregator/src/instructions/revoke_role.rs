@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, Role, Vault};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RevokeRole<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.authority == admin.key()
+    )]
+    pub main_vault: Account<'info, Vault>,
+}
+
+impl<'info> RevokeRole<'info> {
+    /// Unassigns `role`, only the admin holds it afterwards. Unlike appointing one, revoking the
+    /// guardian is immediate, so a rogue guardian can't hold the queue hostage by cancelling its
+    /// own replacement.
+    pub fn revoke_role(&mut self, role: Role, events: &EventEmitter<'info>) -> Result<()> {
+        self.main_vault.set_role(role, Pubkey::default());
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.admin.key(),
+            change: ConfigChange::Role { role, key: Pubkey::default() },
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<RevokeRole>, role: Role) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.revoke_role(role, &events)?;
+    Ok(())
+}
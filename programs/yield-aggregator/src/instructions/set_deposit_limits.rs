@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, Vault};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetDepositLimits<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.authority == admin.key()
+    )]
+    pub main_vault: Account<'info, Vault>,
+}
+
+impl<'info> SetDepositLimits<'info> {
+    /// Lowering a cap below what is already deposited only blocks new deposits, nobody is forced out
+    pub fn set_deposit_limits(&mut self, deposit_cap: u64, user_deposit_cap: u64, min_deposit: u64, events: &EventEmitter<'info>) -> Result<()> {
+        self.main_vault.set_deposit_limits(deposit_cap, user_deposit_cap, min_deposit)?;
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.admin.key(),
+            change: ConfigChange::DepositLimits { deposit_cap, user_deposit_cap, min_deposit },
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<SetDepositLimits>, deposit_cap: u64, user_deposit_cap: u64, min_deposit: u64) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.set_deposit_limits(deposit_cap, user_deposit_cap, min_deposit, &events)?;
+    Ok(())
+}
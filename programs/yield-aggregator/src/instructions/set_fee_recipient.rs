@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, Role, Vault, error::ErrorCode};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetFeeRecipient<'info> {
+    pub fee_manager: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.has_role(fee_manager.key(), Role::FeeManager) @ ErrorCode::Unauthorized
+    )]
+    pub main_vault: Account<'info, Vault>,
+
+    /// CHECK: any wallet can receive fee shares, they are minted to its share ATA by `claim_fees`
+    pub fee_recipient: UncheckedAccount<'info>,
+}
+
+impl<'info> SetFeeRecipient<'info> {
+    /// Fee shares booked for the current recipient have to be claimed first, so they cannot be redirected
+    pub fn set_fee_recipient(&mut self, events: &EventEmitter<'info>) -> Result<()> {
+        require!(self.main_vault.accrued_fee_shares == 0, ErrorCode::UnclaimedFees);
+        self.main_vault.fee_recipient = self.fee_recipient.key();
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.fee_manager.key(),
+            change: ConfigChange::FeeRecipient { fee_recipient: self.fee_recipient.key() },
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<SetFeeRecipient>) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.set_fee_recipient(&events)?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::{ALLOCATION_SCALE, ConfigChange, ConfigChanged, EventEmitter, Vault, error::ErrorCode};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetIdleBuffer<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.authority == admin.key()
+    )]
+    pub main_vault: Account<'info, Vault>,
+}
+
+impl<'info> SetIdleBuffer<'info> {
+    pub fn set_idle_buffer(&mut self, idle_buffer_bps: u16, events: &EventEmitter<'info>) -> Result<()> {
+        require!(idle_buffer_bps <= ALLOCATION_SCALE, ErrorCode::InvalidIdleBuffer);
+        self.main_vault.idle_buffer_bps = idle_buffer_bps;
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.admin.key(),
+            change: ConfigChange::IdleBuffer { idle_buffer_bps },
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<SetIdleBuffer>, idle_buffer_bps: u16) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.set_idle_buffer(idle_buffer_bps, &events)?;
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, Role, Vault, error::ErrorCode};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetRole<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.authority == admin.key()
+    )]
+    pub main_vault: Account<'info, Vault>,
+
+    /// CHECK: any wallet can hold a role, it only ever signs
+    pub member: UncheckedAccount<'info>,
+}
+
+impl<'info> SetRole<'info> {
+    /// Guardian changes go through the timelock, see `TimelockAction::SetGuardian`
+    pub fn set_role(&mut self, role: Role, events: &EventEmitter<'info>) -> Result<()> {
+        require!(role != Role::Guardian, ErrorCode::TimelockRequired);
+        self.main_vault.set_role(role, self.member.key());
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.admin.key(),
+            change: ConfigChange::Role { role, key: self.member.key() },
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<SetRole>, role: Role) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.set_role(role, &events)?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, Role, Vault, error::ErrorCode};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetStrategyWeight<'info> {
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.has_role(keeper.key(), Role::Keeper) @ ErrorCode::Unauthorized
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+}
+
+impl<'info> SetStrategyWeight<'info> {
+    /// Sets the target weight of every strategy, in registry order. Funds only move on the next
+    /// rebalance, or as deposits are allocated.
+    pub fn set_strategy_weight(&mut self, weights: &[u16], events: &EventEmitter<'info>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        self.main_vault.set_weights(weights, now)?;
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.keeper.key(),
+            change: ConfigChange::StrategyWeights { weights: weights.to_vec() },
+            timestamp: now,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<SetStrategyWeight>, weights: Vec<u16>) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.set_strategy_weight(&weights, &events)?;
+    Ok(())
+}
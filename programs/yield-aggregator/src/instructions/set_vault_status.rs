@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::{ConfigChange, ConfigChanged, EventEmitter, Role, Vault, VaultStatus, error::ErrorCode};
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SetVaultStatus<'info> {
+    /// The vault admin or its guardian
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump,
+        constraint = main_vault.has_role(signer.key(), Role::Guardian) @ ErrorCode::Unauthorized
+    )]
+    pub main_vault: Box<Account<'info, Vault>>,
+}
+
+impl<'info> SetVaultStatus<'info> {
+    pub fn set_vault_status(&mut self, status: VaultStatus, events: &EventEmitter<'info>) -> Result<()> {
+        self.main_vault.set_status(self.signer.key(), status)?;
+
+        events.emit(ConfigChanged {
+            vault: self.main_vault.key(),
+            signer: self.signer.key(),
+            change: ConfigChange::Status { status },
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+pub fn handler(ctx: Context<SetVaultStatus>, status: VaultStatus) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.set_vault_status(status, &events)?;
+    Ok(())
+}
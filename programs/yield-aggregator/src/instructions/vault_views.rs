@@ -0,0 +1,148 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::{AdapterAccounts, Freshness, Vault, VaultStatus, VaultValuation, load_strategy_adapters, error::ErrorCode};
+
+// Read-only ERC-4626 style views. They never write, so they are meant to be simulated and
+// their result read from the return data. The math is the same `Vault` math the mutating
+// instructions use, priced at the live vault value with the same `Freshness::Current` markets,
+// so the client simulates them after `refresh_reserve` like it sends deposits and withdrawals.
+// Every view takes the valuation accounts of every strategy as remaining accounts, in registry order.
+
+#[derive(Accounts)]
+pub struct VaultView<'info> {
+    pub main_vault: Box<Account<'info, Vault>>,
+
+    #[account(constraint = main_vault.vault_usdc_ata == main_vault_usdc_ata.key())]
+    pub main_vault_usdc_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+impl<'info> VaultView<'info> {
+    fn valuation(&self, remaining: &[AccountInfo<'info>]) -> Result<VaultValuation> {
+        let adapters = load_strategy_adapters(&self.main_vault, self.main_vault.key(), remaining, AdapterAccounts::Valuation, Freshness::Current)?;
+        VaultValuation::load(self.main_vault_usdc_ata.amount, &adapters)
+    }
+
+    /// The vault as the next mutating instruction prices it, with the management and performance
+    /// fee shares owed since the last snapshot already issued, and its live value
+    pub fn priced_vault(&self, remaining: &[AccountInfo<'info>]) -> Result<(Vault, u64)> {
+        let valuation = self.valuation(remaining)?;
+        let total_assets = valuation.total()?;
+        let mut vault = Vault::clone(&self.main_vault);
+        let fees = vault.pending_fees(vault.unrealized_yield(&valuation), total_assets, Clock::get()?.unix_timestamp)?;
+        vault.total_shares = vault
+            .total_shares
+            .checked_add(fees.management_shares + fees.performance_shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok((vault, total_assets))
+    }
+
+    /// The vault and the value withdrawals are priced at. In Shutdown `withdraw_idle` pays out
+    /// against the idle USDC alone, without booking fees or charging the withdraw fee, and the
+    /// strategy accounts are not needed.
+    fn withdrawal_pricing(&self, remaining: &[AccountInfo<'info>]) -> Result<(Vault, u64, bool)> {
+        if self.main_vault.status == VaultStatus::Shutdown {
+            return Ok((Vault::clone(&self.main_vault), self.main_vault_usdc_ata.amount, false));
+        }
+        let (vault, total_assets) = self.priced_vault(remaining)?;
+        Ok((vault, total_assets, true))
+    }
+
+    /// Live value of everything the vault holds
+    pub fn total_assets(&self, remaining: &[AccountInfo<'info>]) -> Result<u64> {
+        self.valuation(remaining)?.total()
+    }
+
+    /// Shares `assets` are worth, without deposit or withdraw fees
+    pub fn convert_to_shares(&self, remaining: &[AccountInfo<'info>], assets: u64) -> Result<u64> {
+        let (vault, total_assets) = self.priced_vault(remaining)?;
+        vault.convert_to_shares(assets, total_assets)
+    }
+
+    /// Assets `shares` are worth, without deposit or withdraw fees
+    pub fn convert_to_assets(&self, remaining: &[AccountInfo<'info>], shares: u64) -> Result<u64> {
+        let (vault, total_assets) = self.priced_vault(remaining)?;
+        vault.convert_to_assets(shares, total_assets)
+    }
+
+    /// Shares `deposit` would mint to the user for `assets`, net of the deposit fee
+    pub fn preview_deposit(&self, remaining: &[AccountInfo<'info>], assets: u64) -> Result<u64> {
+        let (vault, total_assets) = self.priced_vault(remaining)?;
+        let shares = vault.convert_to_shares(assets, total_assets)?;
+        Ok(shares - vault.deposit_fee_shares(shares))
+    }
+
+    /// Shares `withdraw_assets` would take from the user to pay out `assets`, withdraw fee included.
+    /// In Shutdown no fee is charged and the shares are priced against the idle USDC.
+    pub fn preview_withdraw(&self, remaining: &[AccountInfo<'info>], assets: u64) -> Result<u64> {
+        let (vault, total_assets, charges_fee) = self.withdrawal_pricing(remaining)?;
+        let net_shares = vault.assets_to_shares_rounded_up(assets, total_assets)?;
+        if !charges_fee {
+            return Ok(net_shares);
+        }
+        vault.shares_with_withdraw_fee(net_shares)
+    }
+
+    /// Assets `user_shares` are worth in the priced vault
+    fn user_assets(vault: &Vault, user_shares: u64, total_assets: u64) -> Result<u64> {
+        if user_shares == 0 {
+            return Ok(0);
+        }
+        vault.convert_to_assets(user_shares, total_assets)
+    }
+}
+
+#[derive(Accounts)]
+pub struct MaxDeposit<'info> {
+    pub view: VaultView<'info>,
+
+    /// CHECK: depositor, only used to check the share ATA
+    pub user: UncheckedAccount<'info>,
+
+    // Users that never deposited have no share ATA yet
+    #[account(
+        token::mint = view.main_vault.share_mint,
+        token::authority = user
+    )]
+    pub user_share_ata: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+}
+
+impl<'info> MaxDeposit<'info> {
+    /// Largest deposit `user` can make before hitting the TVL or per-user cap, 0 when the
+    /// headroom left is below the minimum deposit
+    pub fn max_deposit(&self, remaining: &[AccountInfo<'info>]) -> Result<u64> {
+        let user_shares = self.user_share_ata.as_ref().map_or(0, |ata| ata.amount);
+
+        let (vault, total_assets) = self.view.priced_vault(remaining)?;
+        let user_assets = VaultView::user_assets(&vault, user_shares, total_assets)?;
+        Ok(vault.max_deposit(user_assets, total_assets))
+    }
+}
+
+#[derive(Accounts)]
+pub struct MaxWithdraw<'info> {
+    pub view: VaultView<'info>,
+
+    /// CHECK: owner of the shares, only used to check the share ATA
+    pub user: UncheckedAccount<'info>,
+
+    #[account(
+        token::mint = view.main_vault.share_mint,
+        token::authority = user
+    )]
+    pub user_share_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+impl<'info> MaxWithdraw<'info> {
+    /// Largest amount `user` can withdraw with `withdraw_assets`, net of the withdraw fee
+    pub fn max_withdraw(&self, remaining: &[AccountInfo<'info>]) -> Result<u64> {
+        let user_shares = self.user_share_ata.amount;
+        if user_shares == 0 {
+            return Ok(0);
+        }
+
+        let (vault, total_assets, charges_fee) = self.view.withdrawal_pricing(remaining)?;
+        let fee_shares = if charges_fee { vault.withdraw_fee_shares(user_shares) } else { 0 };
+        vault.convert_to_assets(user_shares - fee_shares, total_assets)
+    }
+}
@@ -1,28 +1,34 @@
-use anchor_lang::{prelude::*, solana_program::{instruction::Instruction, program::invoke_signed}};
-use anchor_spl::{associated_token::AssociatedToken, token::Token, token_interface::{Mint, TokenAccount, TokenInterface}};
+use anchor_lang::prelude::*;
+use anchor_spl::{associated_token::AssociatedToken, token_interface::{Burn, Mint, TokenAccount, TokenInterface, TransferChecked, burn, transfer_checked}};
 
-use crate::{Vault, error::ErrorCode, Lending as JupLending, Reserve};
-use crate::jup_cpi;
-use crate::jup_accounts;
-use crate::JupLendingProgram;
+use crate::{ACC_PER_SHARE_SCALER, Adapter, EventEmitter, FeeKind, LendingAdapter, Rounding, UserPosition, Vault, VaultCpiAccounts, VaultValuation, WithdrawEvent, error::ErrorCode};
+use crate::vault_signer_seeds;
 
+/// Accounts shared by `withdraw_shares` and `withdraw_assets`.
+/// Remaining accounts: the CPI accounts of every strategy, in registry order.
+#[event_cpi]
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
-    /// CHECK: admin details required to derive vault
     #[account(
-        constraint = main_vault.authority.key() == admin.key()
+        mut,
+        seeds = [b"vault", main_vault.usdc_mint.as_ref(), &main_vault.vault_index.to_le_bytes()],
+        bump = main_vault.bump
     )]
-    pub admin : AccountInfo<'info>,
+    pub main_vault: Box<Account<'info, Vault>>,
 
+    // Holders that got their shares by transfer or from `claim_fees` never deposited,
+    // their position is opened here with no reward debt
     #[account(
-        mut,
-        seeds = [b"vault", admin.key().as_ref()],
+        init_if_needed,
+        payer = user,
+        space = 8 + UserPosition::INIT_SPACE,
+        seeds = [b"user_position", main_vault.key().as_ref(), user.key().as_ref()],
         bump
     )]
-    pub main_vault: Box<Account<'info, Vault>>,
+    pub user_position: Box<Account<'info, UserPosition>>,
 
     #[account(
         mut,
@@ -33,14 +39,6 @@ pub struct Withdraw<'info> {
     )]
     pub main_vault_usdc_ata : Box<InterfaceAccount<'info, TokenAccount>>,
 
-    #[account(
-        mut,
-        associated_token::mint=usdc_mint,
-        associated_token::authority=admin,
-        associated_token::token_program=token_program
-    )]
-    pub admin_usdc_ata : Box<InterfaceAccount<'info, TokenAccount>>,
-
     #[account(
         mut,
         associated_token::mint=usdc_mint,
@@ -55,286 +53,236 @@ pub struct Withdraw<'info> {
     )]
     pub usdc_mint : Box<InterfaceAccount<'info, Mint>>,
 
-    /// Jup related accounts
     #[account(
         mut,
-        constraint = main_vault.vault_usdc_ata.key() == main_vault_usdc_ata.key(), // We add this later
-        associated_token::mint=f_token_mint,
-        associated_token::authority=main_vault,
-        associated_token::token_program=token_program
+        seeds = [b"share_mint", main_vault.key().as_ref()],
+        bump,
+        constraint = main_vault.share_mint == share_mint.key()
     )]
-    pub main_vault_f_token_ata : Box<InterfaceAccount<'info, TokenAccount>>,
+    pub share_mint : Box<InterfaceAccount<'info, Mint>>,
 
     #[account(
         mut,
-        mint::token_program=token_program
+        associated_token::mint=share_mint,
+        associated_token::authority=user,
+        associated_token::token_program=token_program
     )]
-    pub f_token_mint : Box<InterfaceAccount<'info, Mint>>,
-
-    /// lending_admin (read-only)
-    /// CHECK: Validated by lending program
-    pub lending_admin: AccountInfo<'info>,
-
-    /// lending (mutable)
-    #[account(mut)]
-    /// CHECK: Validated by lending program
-    pub lending: AccountInfo<'info>,
-    
-    /// supply_token_reserves_liquidity (mutable)
-    #[account(mut)]
-    /// CHECK: Validated by lending program
-    pub supply_token_reserves_liquidity: AccountInfo<'info>,
-    
-    /// lending_supply_position_on_liquidity (mutable)
-    #[account(mut)]
-    /// CHECK: Validated by lending program
-    pub lending_supply_position_on_liquidity: AccountInfo<'info>,
-    
-    /// rate_model (read-only)
-    /// CHECK: Validated by lending program
-    pub rate_model: AccountInfo<'info>,
-    
-    /// vault (mutable)
-    #[account(mut)]
-    /// CHECK: Validated by lending program
-    pub vault: AccountInfo<'info>,
-
-    #[account(mut)]
-    /// CHECK: Validated by lending program
-    pub claim_account: AccountInfo<'info>,
+    pub user_share_ata : Box<InterfaceAccount<'info, TokenAccount>>,
 
-    /// liquidity (mutable)
-    #[account(mut)]
-    /// CHECK: Validated by lending program
-    pub liquidity: AccountInfo<'info>,
-
-    /// liquidity_program (mutable)
-    #[account(mut)]
-    /// CHECK: Validated by lending program
-    pub liquidity_program: AccountInfo<'info>,
-
-    /// rewards_rate_model (read-only)
-    /// /// CHECK: Validated by lending program
-    pub rewards_rate_model: AccountInfo<'info>,
-
-    /// CHECK: Validated by lending program
-    pub lending_program: Program<'info, JupLendingProgram>,
-
-    // Kamino Accounts
-    /// CHECK: Kamino reserve account
-    #[account(mut)]
-    pub reserve: UncheckedAccount<'info>,
-
-    /// CHECK: Lending market that the reserve belongs to
-    pub lending_market: UncheckedAccount<'info>,
-
-    /// CHECK: PDA authority for the lending market
-    pub lending_market_authority: UncheckedAccount<'info>,
-
-    /// CHECK: Token account that stores liquidity supplied to reserve
-    #[account(mut)]
-    pub reserve_liquidity_supply: UncheckedAccount<'info>,
-
-    /// CHECK: Mint of the collateral token
-    #[account(mut)]
-    pub reserve_collateral_mint: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
 
-    /// CHECK: User's (or PDA's) token account receiving collateral tokens
-    /// Kamino named this variable as user_destination_collateral
-    #[account(
-        mut,
-        associated_token::mint=reserve_collateral_mint,
-        associated_token::authority=main_vault,
-        associated_token::token_program=token_program,
-    )]
-    pub main_vault_kamino_token_ata_collateral: Box<InterfaceAccount<'info, TokenAccount>>,
+impl<'info> Withdraw<'info> {
+    pub fn valuation(&mut self, adapters: &[Adapter<'_, 'info>]) -> Result<VaultValuation> {
+        self.main_vault_usdc_ata.reload()?;
+        VaultValuation::load(self.main_vault_usdc_ata.amount, adapters)
+    }
 
-    /// CHECK: Token program for the collateral mint (usually TOKEN_PROGRAM_ID)
-    pub collateral_token_program: UncheckedAccount<'info>,
+    /// Realizes any yield or loss since the last snapshot and returns the live value of the vault,
+    /// so shares are priced at what the vault holds right now
+    pub fn mark_to_market(&mut self, adapters: &[Adapter<'_, 'info>], events: &EventEmitter<'info>) -> Result<u64> {
+        let valuation = self.valuation(adapters)?;
+        let vault_key = self.main_vault.key();
+        self.main_vault.mark_to_market(vault_key, &valuation, Clock::get()?.unix_timestamp, events)?;
+        valuation.total()
+    }
 
-    /// CHECK: Token program for the liquidity mint (usually TOKEN_PROGRAM_ID)
-    pub liquidity_token_program: UncheckedAccount<'info>,
+    /// Fills in a position `init_if_needed` just created, which has no rewards or reward debt yet
+    pub fn init_position_if_needed(&mut self, bump: u8) {
+        if self.user_position.user == Pubkey::default() {
+            self.user_position.vault = self.main_vault.key();
+            self.user_position.user = self.user.key();
+            self.user_position.bump = bump;
+        }
+    }
 
-    /// CHECK: Instructions sysvar
-    pub instruction_sysvar_account: UncheckedAccount<'info>,
+    /// Shares the user owns
+    pub fn user_shares(&self) -> Result<u64> {
+        Ok(self.user_share_ata.amount)
+    }
 
-    /// CHECK : klend program account
-    pub klend_program: UncheckedAccount<'info>,
+    /// Burns `shares` of the user and settles the user's rewards
+    pub fn burn_shares(&mut self, shares: u64) -> Result<()> {
+        require!(shares > 0, ErrorCode::ZeroAmount);
+        let shares_before = self.user_shares()?;
+        require!(shares <= shares_before, ErrorCode::InsufficientShares);
 
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-}
+        burn(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                Burn {
+                    mint: self.share_mint.to_account_info(),
+                    from: self.user_share_ata.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ),
+            shares,
+        )?;
+        self.user_share_ata.reload()?;
 
-pub fn get_redeem_reserve_collateral_discriminator() -> Vec<u8> {
-    // discriminator = sha256("global:redeem_reserve_collateral")[0..8]
-    vec![234, 117, 181, 125, 185, 142, 220, 29]
-}
+        self.settle_rewards(shares_before, shares_before - shares)?;
 
-impl<'info> Withdraw<'info> {
-    pub fn jup_withdraw(&mut self, f_token_amount: u64) -> Result<()> {
-        // withdraw from jup
-        let jup_accounts = jup_accounts::Redeem{
-            signer: self.main_vault.to_account_info(),
-            owner_token_account: self.main_vault_f_token_ata.to_account_info(),
-            recipient_token_account: self.main_vault_usdc_ata.to_account_info(),
-            f_token_mint: self.f_token_mint.to_account_info(),
-            lending: self.lending.to_account_info(),
-            lending_admin: self.lending_admin.to_account_info(),
-            lending_supply_position_on_liquidity: self.lending_supply_position_on_liquidity.to_account_info(),
-            liquidity: self.liquidity.to_account_info(),
-            liquidity_program: self.liquidity_program.to_account_info(),
-            mint: self.usdc_mint.to_account_info(),
-            rate_model: self.rate_model.to_account_info(),
-            rewards_rate_model: self.rewards_rate_model.to_account_info(),
-            supply_token_reserves_liquidity: self.supply_token_reserves_liquidity.to_account_info(),
-            vault: self.vault.to_account_info(),
-            claim_account: self.claim_account.to_account_info(),
-            associated_token_program: self.associated_token_program.to_account_info(),
-            token_program: self.token_program.to_account_info(),
-            system_program: self.system_program.to_account_info(),
-        };
+        self.main_vault.total_shares = self
+            .main_vault
+            .total_shares
+            .checked_sub(shares)
+            .ok_or(ErrorCode::MathOverflow)?;
 
-        let admin_key = self.main_vault.authority;
-        let signer_seeds: &[&[&[u8]]] = &[&[b"vault", admin_key.as_ref(), &[self.main_vault.bump]]];
+        self.share_mint.reload()?;
+        self.main_vault.check_share_supply(self.share_mint.supply)
+    }
 
-        let jup_cpi_program = self.lending_program.to_account_info();
-        let jup_cpi_context = CpiContext::new_with_signer(jup_cpi_program, jup_accounts, signer_seeds);
-        match jup_cpi::redeem(jup_cpi_context, f_token_amount) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(ErrorCode::CpiToLendingProgramFailed.into())
-        }
+    /// Hands the withdraw fee part of the redeemed shares to the fee recipient instead of burning it
+    pub fn charge_withdraw_fee(&mut self, fee_assets: u64, fee_shares: u64, events: &EventEmitter<'info>) -> Result<()> {
+        let vault_key = self.main_vault.key();
+        let now = Clock::get()?.unix_timestamp;
+        self.main_vault.book_fee_shares(vault_key, FeeKind::Withdraw, fee_assets, fee_shares, now, events)
     }
 
-    pub fn kamino_withdraw(&mut self, collateral_amount: u64) -> Result<()> {
-        let mut instruction_data = get_redeem_reserve_collateral_discriminator();
-        instruction_data.extend_from_slice(&collateral_amount.to_le_bytes());
-
-        let accounts = vec![
-            AccountMeta::new_readonly(self.main_vault.key(), true), // owner/signer
-            AccountMeta::new_readonly(self.lending_market.key(), false),
-            AccountMeta::new(self.reserve.key(), false),
-            AccountMeta::new_readonly(self.lending_market_authority.key(), false),
-            AccountMeta::new(self.usdc_mint.key(), false), // reserveLiquidityMint (writable)
-            AccountMeta::new(self.reserve_collateral_mint.key(), false),
-            AccountMeta::new(self.reserve_liquidity_supply.key(), false),
-            AccountMeta::new(self.main_vault_kamino_token_ata_collateral.key(), false), // userSourceCollateral
-            AccountMeta::new(self.main_vault_usdc_ata.key(), false), // userDestinationLiquidity
-            AccountMeta::new_readonly(self.collateral_token_program.key(), false),
-            AccountMeta::new_readonly(self.liquidity_token_program.key(), false),
-            AccountMeta::new_readonly(self.instruction_sysvar_account.key(), false),
-        ];
-
-        let ix = Instruction {
-            program_id: self.klend_program.key(),
-            accounts,
-            data: instruction_data,
+    /// Brings the user's pending rewards up to date, drops the part paid out with the burned
+    /// shares and checkpoints `reward_debt` on the shares left
+    fn settle_rewards(&mut self, shares_before: u64, shares_after: u64) -> Result<()> {
+        let acc_per_share = self.main_vault.acc_per_share as u128;
+        let position = &mut self.user_position;
+
+        let accrued = (shares_before as u128)
+            .checked_mul(acc_per_share)
+            .ok_or(ErrorCode::MathOverflow)?
+            .saturating_sub(position.reward_debt);
+        let pending = (position.pending_rewards as u128)
+            .checked_add(accrued / ACC_PER_SHARE_SCALER)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        position.pending_rewards = if shares_before == 0 {
+            0
+        } else {
+            (pending * shares_after as u128 / shares_before as u128) as u64
         };
-
-        let account_infos = [
-            self.main_vault.to_account_info(),
-            self.lending_market.to_account_info(),
-            self.reserve.to_account_info(),
-            self.lending_market_authority.to_account_info(),
-            self.usdc_mint.to_account_info(),
-            self.reserve_collateral_mint.to_account_info(),
-            self.reserve_liquidity_supply.to_account_info(),
-            self.main_vault_kamino_token_ata_collateral.to_account_info(),
-            self.main_vault_usdc_ata.to_account_info(),
-            self.collateral_token_program.to_account_info(),
-            self.liquidity_token_program.to_account_info(),
-            self.instruction_sysvar_account.to_account_info(),
-        ];
-
-        let admin_key = self.main_vault.authority;
-        let signer_seeds: &[&[&[u8]]] = &[&[b"vault", admin_key.as_ref(), &[self.main_vault.bump]]];
-
-        invoke_signed(&ix, &account_infos, signer_seeds)?;
+        position.reward_debt = (shares_after as u128)
+            .checked_mul(acc_per_share)
+            .ok_or(ErrorCode::MathOverflow)?;
+        position.last_updated = Clock::get()?.unix_timestamp;
 
         Ok(())
     }
 
-    pub fn withdraw(&mut self, withdraw_amount: u64) -> Result<()> {
-        // Get current allocation percentages 
-        let jup_allocation = self.main_vault.jup_allocation as u64;
-        let kamino_allocation = self.main_vault.kamino_allocation as u64;
-        let scale = 10_000u64;
-
-        // Compute how much USDC to withdraw from each platform 
-        let jup_withdraw_usdc = (withdraw_amount as u128 * jup_allocation as u128 / scale as u128) as u64;
-        let kamino_withdraw_usdc = (withdraw_amount as u128 * kamino_allocation as u128 / scale as u128) as u64;
-
-        // Get Jup + Kamino exchange rates (to compute token withdrawals)
-        let lending_data = JupLending::try_deserialize(&mut &self.lending.data.borrow()[..])?;
-        let token_exchange_price = lending_data.token_exchange_price;
-        let jup_exchange_rate = (token_exchange_price as u128) / 10u128.pow(6);
-
-        let reserve_data = Reserve::try_deserialize(&mut &self.reserve.data.borrow()[..])?;
-        let total_liquidity = reserve_data.liquidity.available_amount;
-        let total_collateral_supply = reserve_data.collateral.mint_total_supply;
-        let kamino_exchange_rate = (total_liquidity as u128 * 10u128.pow(6)) / total_collateral_supply as u128;
-
-        // Convert desired USDC withdrawal to platform token amounts
-        let jup_token_amount = ((jup_withdraw_usdc as u128 * 10u128.pow(6)) / jup_exchange_rate as u128) as u64;
-        let kamino_token_amount = ((kamino_withdraw_usdc as u128 * 10u128.pow(6)) / kamino_exchange_rate) as u64;
-
-        // Perform actual withdrawals from Jup + Kamino
-        self.jup_withdraw(jup_token_amount)?;
-        self.kamino_withdraw(kamino_token_amount)?;
-
-        // Compute yield (acc_per_share logic)
-        const SCALER: u128 = 1_000_000_000_000; // 1e12 precision (or same as used in deposit/rebalance)
-        let total_underlying_before = self.main_vault.total_underlying as u128;
-        let total_underlying_after = total_underlying_before.saturating_sub(withdraw_amount as u128);
-
-        // Yield from this withdrawal (if any)
-        let yield_generated = total_underlying_before.saturating_sub(total_underlying_after);
-
-        // Increment acc_per_share (scaled)
-        let acc_increment = if yield_generated > 0 && self.main_vault.total_shares > 0 {
-            (yield_generated * SCALER) / self.main_vault.total_shares as u128
-        } else {
-            0
-        };
-        let new_acc_per_share = (self.main_vault.acc_per_share as u128).saturating_add(acc_increment);
-        self.main_vault.acc_per_share = new_acc_per_share as u64; // store scaled down
+    /// Returns the position rent to the user once they hold no shares anymore
+    pub fn close_position_if_empty(&mut self) -> Result<()> {
+        if self.user_shares()? == 0 {
+            self.user_position.close(self.user.to_account_info())?;
+        }
+        Ok(())
+    }
 
-        // Transfer final USDC to user
-        anchor_spl::token::transfer(
+    /// Transfers `amount` USDC from the vault ATA to the user
+    fn pay_user(&self, amount: u64) -> Result<()> {
+        transfer_checked(
             CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
-                anchor_spl::token::Transfer {
+                TransferChecked {
                     from: self.main_vault_usdc_ata.to_account_info(),
+                    mint: self.usdc_mint.to_account_info(),
                     to: self.user_usdc_ata.to_account_info(),
                     authority: self.main_vault.to_account_info(),
                 },
-                &[&[b"vault", self.main_vault.authority.as_ref(), &[self.main_vault.bump]]],
+                vault_signer_seeds!(self.main_vault),
             ),
-            withdraw_amount,
-        )?;
+            amount,
+            self.usdc_mint.decimals,
+        )
+    }
 
-        // Update vault states
-        self.main_vault.jup_lend_balance = self
-            .main_vault
-            .jup_lend_balance
-            .saturating_sub(jup_withdraw_usdc);
-        self.main_vault.kamino_balance = self
-            .main_vault
-            .kamino_balance
-            .saturating_sub(kamino_withdraw_usdc);
-        self.main_vault.total_underlying = total_underlying_after as u64;
+    /// Shutdown exit: burns `shares` for `amount`, their pro-rata share of the idle USDC, without
+    /// pricing or touching the strategies. No withdraw fee is charged. Holders only get a fair share
+    /// of the whole vault once `emergency_unwind` has brought everything back to the vault ATA.
+    pub fn withdraw_idle(&mut self, shares: u64, amount: u64, events: &EventEmitter<'info>) -> Result<()> {
+        require!(amount > 0, ErrorCode::ZeroAmount);
+        let idle = self.main_vault_usdc_ata.amount;
+        let share_price_before = self.main_vault.share_price(idle);
+
+        self.burn_shares(shares)?;
+        self.pay_user(amount)?;
+
+        let vault = &mut self.main_vault;
+        vault.total_underlying = vault.total_underlying.saturating_sub(amount);
+        vault.last_update_ts = Clock::get()?.unix_timestamp;
+
+        events.emit(WithdrawEvent {
+            vault: vault.key(),
+            user: self.user.key(),
+            shares,
+            amount,
+            from_idle: amount,
+            from_strategies: vec![0; vault.strategies.len()],
+            share_price_before,
+            share_price_after: vault.share_price(idle - amount),
+            total_underlying: vault.total_underlying,
+            total_shares: vault.total_shares,
+            timestamp: vault.last_update_ts,
+        })
+    }
 
-        // Update snapshots for next rebalance baseline
-        self.main_vault.last_jup_value = self.main_vault.last_jup_value.saturating_sub(jup_withdraw_usdc);
-        self.main_vault.last_kamino_value = self.main_vault.last_kamino_value.saturating_sub(kamino_withdraw_usdc);
-        self.main_vault.last_update_ts = Clock::get()?.unix_timestamp;
+    /// Redeems at least `usdc_amount` from `adapter` (capped by the position) and returns the USDC received
+    fn redeem_from(&mut self, adapter: &impl LendingAdapter<'info>, usdc_amount: u64) -> Result<u64> {
+        let idle_before = self.main_vault_usdc_ata.amount;
 
-        Ok(())
+        let signer_seeds: &[&[&[u8]]] = vault_signer_seeds!(self.main_vault);
+        let vault = VaultCpiAccounts {
+            vault: self.main_vault.to_account_info(),
+            vault_usdc_ata: self.main_vault_usdc_ata.to_account_info(),
+            usdc_mint: self.usdc_mint.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            signer_seeds,
+        };
+        adapter.withdraw_value(&vault, usdc_amount, Rounding::Up)?;
+
+        self.main_vault_usdc_ata.reload()?;
+        Ok(self.main_vault_usdc_ata.amount.saturating_sub(idle_before))
     }
 
-}
+    /// Pays `withdraw_amount` USDC to the user, drawing on idle USDC before touching the protocols.
+    /// `share_price_before` is the price the burned `shares` were redeemed at.
+    pub fn withdraw(&mut self, adapters: &[Adapter<'_, 'info>], withdraw_amount: u64, shares: u64, share_price_before: u64, events: &EventEmitter<'info>) -> Result<()> {
+        let before = self.valuation(adapters)?;
+        let plan = before.plan_withdrawal(&self.main_vault, withdraw_amount)?;
+
+        let mut from_strategies = vec![0u64; adapters.len()];
+        for (i, adapter) in adapters.iter().enumerate() {
+            if plan.from_strategies[i] > 0 {
+                from_strategies[i] = self.redeem_from(adapter, plan.from_strategies[i])?;
+            }
+        }
+        let redeemed: u64 = from_strategies.iter().sum();
+
+        self.main_vault_usdc_ata.reload()?;
+        require!(self.main_vault_usdc_ata.amount >= withdraw_amount, ErrorCode::InsufficientLiquidity);
 
-pub fn handler(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
-    ctx.accounts.withdraw(amount)?;
-    Ok(())
-}
\ No newline at end of file
+        self.pay_user(withdraw_amount)?;
+
+        // Update vault states, strategy snapshots move by the value actually redeemed
+        let after = self.valuation(adapters)?;
+        let vault = &mut self.main_vault;
+        for (i, (value_before, value_after)) in before.positions.iter().zip(&after.positions).enumerate() {
+            vault.track_position(i, *value_before, *value_after);
+        }
+        vault.total_underlying = vault.total_underlying.saturating_sub(withdraw_amount);
+        vault.last_update_ts = Clock::get()?.unix_timestamp;
+
+        events.emit(WithdrawEvent {
+            vault: vault.key(),
+            user: self.user.key(),
+            shares,
+            amount: withdraw_amount,
+            from_idle: withdraw_amount.saturating_sub(redeemed),
+            from_strategies,
+            share_price_before,
+            share_price_after: vault.share_price(after.total()?),
+            total_underlying: vault.total_underlying,
+            total_shares: vault.total_shares,
+            timestamp: vault.last_update_ts,
+        })
+    }
+
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::{AdapterAccounts, EventEmitter, Freshness, VaultStatus, Withdraw, load_strategy_adapters, error::ErrorCode};
+
+/// Pays out exactly `amount` USDC and burns the shares backing it at the live vault value,
+/// plus the withdraw fee. In Shutdown the shares are priced against idle USDC only and no
+/// remaining accounts are needed. Fails with `SlippageExceeded` when more than `max_shares_in` shares
+/// would be burned.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>, amount: u64, max_shares_in: u64) -> Result<()> {
+    require!(amount > 0, ErrorCode::ZeroAmount);
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.init_position_if_needed(ctx.bumps.user_position);
+    if ctx.accounts.main_vault.status == VaultStatus::Shutdown {
+        let idle = ctx.accounts.main_vault_usdc_ata.amount;
+        let shares = ctx.accounts.main_vault.assets_to_shares_rounded_up(amount, idle)?;
+        require!(shares <= max_shares_in, ErrorCode::SlippageExceeded);
+        ctx.accounts.withdraw_idle(shares, amount, &events)?;
+        return ctx.accounts.close_position_if_empty();
+    }
+
+    let vault_key = ctx.accounts.main_vault.key();
+    let adapters = load_strategy_adapters(&ctx.accounts.main_vault, vault_key, ctx.remaining_accounts, AdapterAccounts::Cpi, Freshness::Current)?;
+    let total_assets = ctx.accounts.mark_to_market(&adapters, &events)?;
+    let vault = &ctx.accounts.main_vault;
+    let share_price_before = vault.share_price(total_assets);
+    let net_shares = vault.assets_to_shares_rounded_up(amount, total_assets)?;
+    let shares = vault.shares_with_withdraw_fee(net_shares)?;
+    require!(shares <= max_shares_in, ErrorCode::SlippageExceeded);
+    let fee_shares = shares - net_shares;
+    let fee_assets = vault.convert_to_assets(fee_shares, total_assets)?;
+
+    ctx.accounts.burn_shares(shares)?;
+    ctx.accounts.charge_withdraw_fee(fee_assets, fee_shares, &events)?;
+    ctx.accounts.withdraw(&adapters, amount, shares, share_price_before, &events)?;
+    ctx.accounts.close_position_if_empty()?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::{AdapterAccounts, EventEmitter, Freshness, VaultStatus, Withdraw, load_strategy_adapters, error::ErrorCode};
+
+/// Burns `shares` of the caller and pays out what they are worth at the live vault value,
+/// less the withdraw fee. In Shutdown the shares are paid from idle USDC only and no
+/// remaining accounts are needed. Fails with `SlippageExceeded` when that is less than `min_assets_out`.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>, shares: u64, min_assets_out: u64) -> Result<()> {
+    let events = EventEmitter::new(&ctx.accounts.event_authority, ctx.bumps.event_authority);
+    ctx.accounts.init_position_if_needed(ctx.bumps.user_position);
+    if ctx.accounts.main_vault.status == VaultStatus::Shutdown {
+        // Pro-rata share of the idle USDC, rounded down
+        let idle = ctx.accounts.main_vault_usdc_ata.amount;
+        let amount = ctx.accounts.main_vault.convert_to_assets(shares, idle)?;
+        require!(amount >= min_assets_out, ErrorCode::SlippageExceeded);
+        ctx.accounts.withdraw_idle(shares, amount, &events)?;
+        return ctx.accounts.close_position_if_empty();
+    }
+
+    let vault_key = ctx.accounts.main_vault.key();
+    let adapters = load_strategy_adapters(&ctx.accounts.main_vault, vault_key, ctx.remaining_accounts, AdapterAccounts::Cpi, Freshness::Current)?;
+    let total_assets = ctx.accounts.mark_to_market(&adapters, &events)?;
+    let vault = &ctx.accounts.main_vault;
+    let share_price_before = vault.share_price(total_assets);
+    let fee_shares = vault.withdraw_fee_shares(shares);
+    let assets = vault.convert_to_assets(shares - fee_shares, total_assets)?;
+    require!(assets >= min_assets_out, ErrorCode::SlippageExceeded);
+    let fee_assets = vault.convert_to_assets(fee_shares, total_assets)?;
+
+    ctx.accounts.burn_shares(shares)?;
+    ctx.accounts.charge_withdraw_fee(fee_assets, fee_shares, &events)?;
+    ctx.accounts.withdraw(&adapters, assets, shares, share_price_before, &events)?;
+    ctx.accounts.close_position_if_empty()?;
+    Ok(())
+}
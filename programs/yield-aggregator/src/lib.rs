@@ -1,12 +1,19 @@
+// anchor 0.31 `#[program]` still expands to the deprecated `AccountInfo::realloc`
+#![allow(deprecated)]
+
+pub mod adapters;
 pub mod constants;
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod state;
 // pub mod jup_lend_interface;
 
 use anchor_lang::prelude::*;
 
+pub use adapters::*;
 pub use constants::*;
+pub use events::*;
 pub use instructions::*;
 pub use state::*;
 
@@ -19,62 +26,178 @@ pub use jup_lend::{cpi as jup_cpi, cpi::accounts as jup_accounts, program::Lendi
 pub mod yield_aggregator {
     use super::*;
 
-    pub fn initialize_vault(ctx: Context<InitializeVault>) -> Result<()> {
-        initialize_vault::handler(ctx)
+    pub fn initialize_vault(ctx: Context<InitializeVault>, vault_index: u16) -> Result<()> {
+        initialize_vault::handler(ctx, vault_index)
     }
 
-    pub fn deposit(ctx : Context<Deposit>, amount : u64) -> Result<()>{
+    pub fn deposit<'info>(ctx: Context<'_, '_, '_, 'info, Deposit<'info>>, amount: u64, min_shares_out: u64) -> Result<()>{
         msg!("Running deposit handler");
-        deposit::handler(ctx, amount)
+        deposit::handler(ctx, amount, min_shares_out)
+    }
+
+    pub fn deposit_and_allocate<'info>(ctx: Context<'_, '_, '_, 'info, DepositAndAllocate<'info>>, amount: u64, min_shares_out: u64) -> Result<()> {
+        msg!("Running deposit and allocate handler");
+        deposit_and_allocate::handler(ctx, amount, min_shares_out)
+    }
+
+    pub fn set_idle_buffer(ctx: Context<SetIdleBuffer>, idle_buffer_bps: u16) -> Result<()> {
+        msg!("Running set idle buffer handler");
+        set_idle_buffer::handler(ctx, idle_buffer_bps)
+    }
+
+    pub fn set_deposit_limits(ctx: Context<SetDepositLimits>, deposit_cap: u64, user_deposit_cap: u64, min_deposit: u64) -> Result<()> {
+        msg!("Running set deposit limits handler");
+        set_deposit_limits::handler(ctx, deposit_cap, user_deposit_cap, min_deposit)
+    }
+
+    pub fn set_fee_recipient(ctx: Context<SetFeeRecipient>) -> Result<()> {
+        msg!("Running set fee recipient handler");
+        set_fee_recipient::handler(ctx)
+    }
+
+    pub fn claim_fees(ctx: Context<ClaimFees>) -> Result<()> {
+        msg!("Running claim fees handler");
+        claim_fees::handler(ctx)
+    }
+
+    pub fn remove_strategy<'info>(ctx: Context<'_, '_, '_, 'info, RemoveStrategy<'info>>, strategy_index: u8) -> Result<()> {
+        msg!("Running remove strategy handler");
+        remove_strategy::handler(ctx, strategy_index)
+    }
+
+    pub fn set_strategy_weight(ctx: Context<SetStrategyWeight>, weights: Vec<u16>) -> Result<()> {
+        msg!("Running set strategy weight handler");
+        set_strategy_weight::handler(ctx, weights)
     }
 
-    pub fn kamino_deposit(ctx : Context<KaminoDeposit>, amount : u64) -> Result<()>{
+    pub fn kamino_deposit<'info>(ctx: Context<'_, '_, '_, 'info, KaminoDeposit<'info>>, amount: u64) -> Result<()> {
         msg!("Running kamino handler");
         kamino_deposit::handler(ctx, amount)
         // Ok(())
     }
 
-    pub fn kamino_withdraw(ctx : Context<KaminoWithdraw>, amount : u64) -> Result<()>{
+    pub fn kamino_withdraw<'info>(ctx: Context<'_, '_, '_, 'info, KaminoWithdraw<'info>>, amount: u64) -> Result<()> {
         msg!("Running kamino withdraw handler");
         kamino_withdraw::handler(ctx, amount)
     }
 
-    pub fn sync_vault_state(
-        ctx: Context<SyncVaultState>,
-        new_jup_allocation : u16,
-        new_kamino_allocation : u16,
-        new_jup_lend_balance : u64,
-        new_kamino_balance : u64,
-        new_acc_per_share: u64,
-        new_total_underlying: u64,
-        new_jup_value: u64,
-        new_kamino_value: u64,
-    ) -> Result<()> {
-        sync_vault_state::handler(ctx, 
-            new_jup_allocation,
-            new_kamino_allocation,
-            new_jup_lend_balance,
-            new_kamino_balance,
-            new_acc_per_share,
-            new_total_underlying,
-            new_jup_value,
-            new_kamino_value,
-        )
-    }
-
-    pub fn jup_deposit(ctx: Context<JupDeposit>, amount: u64) -> Result<()> {
+    pub fn rebalance<'info>(ctx: Context<'_, '_, '_, 'info, Rebalance<'info>>, weights: Vec<u16>) -> Result<()> {
+        msg!("Running rebalance handler");
+        rebalance::handler(ctx, weights)
+    }
+
+    pub fn propose_authority(ctx: Context<ProposeAuthority>) -> Result<()> {
+        msg!("Running propose authority handler");
+        propose_authority::handler(ctx)
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        msg!("Running accept authority handler");
+        accept_authority::handler(ctx)
+    }
+
+    pub fn queue_action(ctx: Context<QueueAction>, action: TimelockAction) -> Result<()> {
+        msg!("Running queue action handler");
+        queue_action::handler(ctx, action)
+    }
+
+    pub fn execute_action<'info>(ctx: Context<'_, '_, '_, 'info, ExecuteAction<'info>>) -> Result<()> {
+        msg!("Running execute action handler");
+        execute_action::handler(ctx)
+    }
+
+    pub fn cancel_action(ctx: Context<CancelAction>) -> Result<()> {
+        msg!("Running cancel action handler");
+        cancel_action::handler(ctx)
+    }
+
+    pub fn set_vault_status(ctx: Context<SetVaultStatus>, status: VaultStatus) -> Result<()> {
+        msg!("Running set vault status handler");
+        set_vault_status::handler(ctx, status)
+    }
+
+    pub fn set_role(ctx: Context<SetRole>, role: Role) -> Result<()> {
+        msg!("Running set role handler");
+        set_role::handler(ctx, role)
+    }
+
+    pub fn revoke_role(ctx: Context<RevokeRole>, role: Role) -> Result<()> {
+        msg!("Running revoke role handler");
+        revoke_role::handler(ctx, role)
+    }
+
+    pub fn emergency_unwind<'info>(ctx: Context<'_, '_, '_, 'info, EmergencyUnwind<'info>>) -> Result<()> {
+        msg!("Running emergency unwind handler");
+        emergency_unwind::handler(ctx)
+    }
+
+    pub fn harvest<'info>(ctx: Context<'_, '_, '_, 'info, Harvest<'info>>) -> Result<()> {
+        msg!("Running harvest handler");
+        harvest::handler(ctx)
+    }
+
+    pub fn compound<'info>(ctx: Context<'_, '_, '_, 'info, Compound<'info>>, amount_in: u64, min_amount_out: u64) -> Result<()> {
+        msg!("Running compound handler");
+        compound::handler(ctx, amount_in, min_amount_out)
+    }
+
+    pub fn claim_jup_rewards<'info>(ctx: Context<'_, '_, '_, 'info, ClaimJupRewards<'info>>) -> Result<()> {
+        msg!("Running claim jup rewards handler");
+        claim_jup_rewards::handler(ctx)
+    }
+
+    pub fn jup_deposit<'info>(ctx: Context<'_, '_, '_, 'info, JupDeposit<'info>>, amount: u64) -> Result<()> {
         msg!("Running jup deposit handler");
         jup_deposit::handler(ctx, amount)
     }
 
-    pub fn jup_withdraw(ctx: Context<JupWithdraw>, amount: u64) -> Result<()> {
+    pub fn jup_withdraw<'info>(ctx: Context<'_, '_, '_, 'info, JupWithdraw<'info>>, amount: u64) -> Result<()> {
         msg!("Running jup withdraw handler");
         jup_withdraw::handler(ctx, amount)
     }
 
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
-        msg!("Running withdraw handler");
-        withdraw::handler(ctx, amount)
+    pub fn withdraw_shares<'info>(ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>, shares: u64, min_assets_out: u64) -> Result<()> {
+        msg!("Running withdraw shares handler");
+        withdraw_shares::handler(ctx, shares, min_assets_out)
+    }
+
+    pub fn withdraw_assets<'info>(ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>, amount: u64, max_shares_in: u64) -> Result<()> {
+        msg!("Running withdraw assets handler");
+        withdraw_assets::handler(ctx, amount, max_shares_in)
+    }
+
+    pub fn convert_to_shares<'info>(ctx: Context<'_, '_, '_, 'info, VaultView<'info>>, assets: u64) -> Result<u64> {
+        ctx.accounts.convert_to_shares(ctx.remaining_accounts, assets)
+    }
+
+    pub fn convert_to_assets<'info>(ctx: Context<'_, '_, '_, 'info, VaultView<'info>>, shares: u64) -> Result<u64> {
+        ctx.accounts.convert_to_assets(ctx.remaining_accounts, shares)
+    }
+
+    pub fn preview_deposit<'info>(ctx: Context<'_, '_, '_, 'info, VaultView<'info>>, assets: u64) -> Result<u64> {
+        ctx.accounts.preview_deposit(ctx.remaining_accounts, assets)
+    }
+
+    pub fn preview_withdraw<'info>(ctx: Context<'_, '_, '_, 'info, VaultView<'info>>, assets: u64) -> Result<u64> {
+        ctx.accounts.preview_withdraw(ctx.remaining_accounts, assets)
+    }
+
+    pub fn max_deposit<'info>(ctx: Context<'_, '_, '_, 'info, MaxDeposit<'info>>) -> Result<u64> {
+        ctx.accounts.max_deposit(ctx.remaining_accounts)
+    }
+
+    pub fn max_withdraw<'info>(ctx: Context<'_, '_, '_, 'info, MaxWithdraw<'info>>) -> Result<u64> {
+        ctx.accounts.max_withdraw(ctx.remaining_accounts)
+    }
+
+    pub fn migrate_vault<'info>(ctx: Context<'_, '_, '_, 'info, MigrateVault<'info>>) -> Result<()> {
+        msg!("Running migrate vault handler");
+        migrate_vault::handler(ctx)
+    }
+
+    pub fn migrate_user_position(ctx: Context<MigrateUserPosition>) -> Result<()> {
+        msg!("Running migrate user position handler");
+        migrate_user_position::handler(ctx)
     }
 
 }
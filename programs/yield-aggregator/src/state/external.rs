@@ -0,0 +1,32 @@
+use std::cell::Ref;
+
+use anchor_lang::prelude::*;
+use bytemuck::Pod;
+
+use crate::error::ErrorCode;
+
+/// An account of another program the vault reads in place. Implementors mirror the account's
+/// layout after its 8 byte discriminator, and only expose the fields the vault uses.
+pub trait ExternalAccount: Pod {
+    /// Program the account has to be owned by
+    const OWNER: Pubkey;
+    const DISCRIMINATOR: [u8; 8];
+
+    /// Borrows the layout from raw account data, which has to start with the discriminator
+    /// followed by `Self`. Anything past it is ignored, so fields the owner appends to the
+    /// account in an upgrade don't stop the vault from reading the ones it knows.
+    fn from_data(data: &[u8]) -> Result<&Self> {
+        let len = 8 + std::mem::size_of::<Self>();
+        require!(data.len() >= len, ErrorCode::InvalidLendingAccount);
+        require!(data[..8] == Self::DISCRIMINATOR, ErrorCode::InvalidLendingAccount);
+        bytemuck::try_from_bytes(&data[8..len]).map_err(|_| ErrorCode::InvalidLendingAccount.into())
+    }
+
+    /// Borrows the layout from `info` once it is known to be owned by `OWNER`
+    fn load<'a>(info: &'a AccountInfo) -> Result<Ref<'a, Self>> {
+        require_keys_eq!(*info.owner, Self::OWNER, ErrorCode::InvalidLendingAccount);
+        let data = info.try_borrow_data()?;
+        Self::from_data(&data)?;
+        Ok(Ref::map(data, |data| bytemuck::from_bytes(&data[8..8 + std::mem::size_of::<Self>()])))
+    }
+}
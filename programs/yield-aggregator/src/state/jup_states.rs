@@ -1,17 +1,297 @@
 use anchor_lang::prelude::*;
+use bytemuck::{Pod, Zeroable};
 
-#[account]
-#[derive(Debug)]
+use crate::{EXCHANGE_RATE_SCALE, ExternalAccount, PodU16, PodU64, Rounding, error::ErrorCode, jup_lend};
+
+/// Precision of `Lending::token_exchange_price`
+pub const JUP_EXCHANGE_PRICE_PRECISION: u128 = 1_000_000_000_000;
+
+/// Zero-copy JupLend `Lending` account, the Borsh layout of `idls/jup_lend.json` with every
+/// integer unaligned. Fields are private, the vault only reads the ones it has accessors for.
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
 pub struct Lending {
-    pub mint: Pubkey,                         // usdc mint address
-    pub f_token_mint: Pubkey,                 // f-token mint address
-    pub lending_id: u16,                      // Unique ID for the lending market
-    pub decimals: u8,                         // Number of decimals
-    pub rewards_rate_model: Pubkey,           // PDA of the rewards rate model
-    pub liquidity_exchange_price: u64,        // Exchange price without rewards
-    pub token_exchange_price: u64,            // Exchange price with rewards (f-token to usdc)
-    pub last_update_timestamp: u64,           // Last time prices were updated
-    pub token_reserves_liquidity: Pubkey,     // Liquidity reserves account
-    pub supply_position_on_liquidity: Pubkey, // Supply position account
-    pub bump: u8,                             // PDA bump seed
+    mint: Pubkey,                         // usdc mint address
+    f_token_mint: Pubkey,                 // f-token mint address
+    lending_id: PodU16,                   // Unique ID for the lending market
+    decimals: u8,                         // Number of decimals
+    rewards_rate_model: Pubkey,           // PDA of the rewards rate model
+    liquidity_exchange_price: PodU64,     // Exchange price without rewards
+    token_exchange_price: PodU64,         // Exchange price with rewards (f-token to usdc)
+    last_update_timestamp: PodU64,        // Last time prices were updated
+    token_reserves_liquidity: Pubkey,     // Liquidity reserves account
+    supply_position_on_liquidity: Pubkey, // Supply position account
+    bump: u8,                             // PDA bump seed
+}
+
+const _: () = assert!(std::mem::size_of::<Lending>() == 188);
+
+impl ExternalAccount for Lending {
+    const OWNER: Pubkey = jup_lend::ID;
+    /// sha256("account:Lending")[0..8]
+    const DISCRIMINATOR: [u8; 8] = [135, 199, 82, 16, 249, 131, 182, 241];
+}
+
+impl Lending {
+    /// Mint of the asset supplied to the market
+    pub fn mint(&self) -> Pubkey {
+        self.mint
+    }
+
+    /// Mint of the f-tokens handed to suppliers
+    pub fn f_token_mint(&self) -> Pubkey {
+        self.f_token_mint
+    }
+
+    /// Rewards rate model the market reads its rewards rate from
+    pub fn rewards_rate_model(&self) -> Pubkey {
+        self.rewards_rate_model
+    }
+
+    /// Liquidity protocol reserves of the asset
+    pub fn token_reserves_liquidity(&self) -> Pubkey {
+        self.token_reserves_liquidity
+    }
+
+    /// The market's supply position in the liquidity protocol
+    pub fn supply_position_on_liquidity(&self) -> Pubkey {
+        self.supply_position_on_liquidity
+    }
+}
+
+/// Every JupLend conversion goes through here. `token_exchange_price` is USDC per f-token scaled by
+/// `JUP_EXCHANGE_PRICE_PRECISION`, the math is done in u128 and callers pick the rounding direction.
+impl Lending {
+    /// The token exchange price, rejected if zero so no conversion divides by it
+    fn exchange_price(&self) -> Result<u128> {
+        let price = self.token_exchange_price.get();
+        require!(price > 0, ErrorCode::InvalidExchangeRate);
+        Ok(price as u128)
+    }
+
+    /// USDC value of `f_token_amount` f-tokens at the current token exchange price
+    pub fn f_tokens_to_assets(&self, f_token_amount: u64, rounding: Rounding) -> Result<u64> {
+        mul_div(f_token_amount, self.exchange_price()?, JUP_EXCHANGE_PRICE_PRECISION, rounding)
+    }
+
+    /// Number of f-tokens worth `assets` USDC at the current token exchange price. Rounded up,
+    /// redeeming the result yields at least `assets`; rounded down, it never exceeds them.
+    pub fn assets_to_f_tokens(&self, assets: u64, rounding: Rounding) -> Result<u64> {
+        mul_div(assets, JUP_EXCHANGE_PRICE_PRECISION, self.exchange_price()?, rounding)
+    }
+
+    /// USDC per f-token, scaled by `EXCHANGE_RATE_SCALE`
+    pub fn exchange_rate(&self) -> Result<u128> {
+        self.exchange_price()?
+            .checked_mul(EXCHANGE_RATE_SCALE)
+            .map(|v| v / JUP_EXCHANGE_PRICE_PRECISION)
+            .ok_or(ErrorCode::MathOverflow.into())
+    }
+}
+
+/// `amount * numerator / denominator`, rounded as asked and checked to fit a u64
+fn mul_div(amount: u64, numerator: u128, denominator: u128, rounding: Rounding) -> Result<u64> {
+    let product = (amount as u128).checked_mul(numerator).ok_or(ErrorCode::MathOverflow)?;
+    let result = match rounding {
+        Rounding::Down => product / denominator,
+        Rounding::Up => product.div_ceil(denominator),
+    };
+    u64::try_from(result).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_utils::{AccountDump, Cases, USDC_MINT};
+    use anchor_lang::{AccountDeserialize, AccountSerialize, Discriminator};
+
+    /// Prices from just above zero to far above par, centered on the 1.0 - 2.0 range
+    fn price(cases: &mut Cases) -> u64 {
+        match cases.next() % 8 {
+            0 => 1 + cases.next() % 1_000,
+            1 => u64::MAX,
+            _ => JUP_EXCHANGE_PRICE_PRECISION as u64 + cases.next() % JUP_EXCHANGE_PRICE_PRECISION as u64,
+        }
+    }
+
+    const CASES: usize = 20_000;
+
+    /// A `Lending` account as JupLend's own IDL types serialize it
+    fn lending_data(token_exchange_price: u64) -> Vec<u8> {
+        let lending = jup_lend::accounts::Lending {
+            mint: Pubkey::new_unique(),
+            f_token_mint: Pubkey::new_unique(),
+            lending_id: 7,
+            decimals: 6,
+            rewards_rate_model: Pubkey::new_unique(),
+            liquidity_exchange_price: token_exchange_price / 2,
+            token_exchange_price,
+            last_update_timestamp: 1_700_000_000,
+            token_reserves_liquidity: Pubkey::new_unique(),
+            supply_position_on_liquidity: Pubkey::new_unique(),
+            bump: 254,
+        };
+        let mut data = Vec::new();
+        lending.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn lending(token_exchange_price: u64) -> Lending {
+        *Lending::from_data(&lending_data(token_exchange_price)).unwrap()
+    }
+
+    #[test]
+    fn rounding_brackets_the_exact_value() {
+        let mut cases = Cases(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..CASES {
+            let (amount, price) = (cases.amount(), price(&mut cases));
+            let lending = lending(price);
+            let exact = amount as u128 * price as u128 / JUP_EXCHANGE_PRICE_PRECISION;
+            match (lending.f_tokens_to_assets(amount, Rounding::Down), lending.f_tokens_to_assets(amount, Rounding::Up)) {
+                (Ok(down), Ok(up)) => {
+                    assert_eq!(down as u128, exact);
+                    assert!(up - down <= 1, "amount {amount} price {price}");
+                    assert_eq!(up == down, (amount as u128 * price as u128).is_multiple_of(JUP_EXCHANGE_PRICE_PRECISION));
+                }
+                // Only results that do not fit a u64 are rejected
+                (down, _) => {
+                    assert!(down.is_err());
+                    assert!(exact > u64::MAX as u128, "amount {amount} price {price}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_never_create_value() {
+        let mut cases = Cases(0xd1b5_4a32_d192_ed03);
+        for _ in 0..CASES {
+            let (amount, price) = (cases.amount(), price(&mut cases));
+            let lending = lending(price);
+
+            // f-tokens -> USDC -> f-tokens, both rounded down, never gives back more f-tokens
+            if let Ok(assets) = lending.f_tokens_to_assets(amount, Rounding::Down) {
+                let f_tokens = lending.assets_to_f_tokens(assets, Rounding::Down).unwrap();
+                assert!(f_tokens <= amount, "amount {amount} price {price}");
+            }
+
+            // Redeeming the f-tokens rounded up for some USDC pays out at least that USDC
+            if let Ok(f_tokens) = lending.assets_to_f_tokens(amount, Rounding::Up) {
+                if let Ok(assets) = lending.f_tokens_to_assets(f_tokens, Rounding::Down) {
+                    assert!(assets >= amount, "amount {amount} price {price}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn conversions_are_monotonic() {
+        let mut cases = Cases(0x94d0_49bb_1331_11eb);
+        for _ in 0..CASES {
+            let price = price(&mut cases);
+            let lending = lending(price);
+            let a = cases.amount() / 2;
+            let b = a + cases.next() % 1_000_000;
+            for rounding in [Rounding::Down, Rounding::Up] {
+                if let (Ok(low), Ok(high)) = (lending.f_tokens_to_assets(a, rounding), lending.f_tokens_to_assets(b, rounding)) {
+                    assert!(low <= high);
+                }
+                if let (Ok(low), Ok(high)) = (lending.assets_to_f_tokens(a, rounding), lending.assets_to_f_tokens(b, rounding)) {
+                    assert!(low <= high);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zero_exchange_price_is_rejected() {
+        let lending = lending(0);
+        for rounding in [Rounding::Down, Rounding::Up] {
+            assert_eq!(lending.f_tokens_to_assets(1_000_000, rounding).unwrap_err(), ErrorCode::InvalidExchangeRate.into());
+            assert_eq!(lending.assets_to_f_tokens(1_000_000, rounding).unwrap_err(), ErrorCode::InvalidExchangeRate.into());
+        }
+        assert_eq!(lending.exchange_rate().unwrap_err(), ErrorCode::InvalidExchangeRate.into());
+    }
+
+    #[test]
+    fn exchange_rate_keeps_the_full_precision() {
+        // 1.000000000001 USDC per f-token, which a 1e6 scale would flatten to 1
+        let lending = lending(JUP_EXCHANGE_PRICE_PRECISION as u64 + 1);
+        assert_eq!(lending.exchange_rate().unwrap(), EXCHANGE_RATE_SCALE + EXCHANGE_RATE_SCALE / JUP_EXCHANGE_PRICE_PRECISION);
+        assert_eq!(lending.f_tokens_to_assets(1_000_000_000_000, Rounding::Down).unwrap(), 1_000_000_000_001);
+        assert_eq!(lending.assets_to_f_tokens(1_000_000_000_001, Rounding::Down).unwrap(), 1_000_000_000_000);
+    }
+
+    #[test]
+    fn matches_the_jup_lend_account_layout() {
+        assert_eq!(Lending::DISCRIMINATOR, jup_lend::accounts::Lending::DISCRIMINATOR);
+
+        let mut cases = Cases(0xbf58_476d_1ce4_e5b9);
+        for _ in 0..64 {
+            let data = lending_data(price(&mut cases));
+            let theirs = jup_lend::accounts::Lending::try_deserialize(&mut &data[..]).unwrap();
+            let ours = Lending::from_data(&data).unwrap();
+            assert_eq!(ours.mint, theirs.mint);
+            assert_eq!(ours.f_token_mint, theirs.f_token_mint);
+            assert_eq!(ours.lending_id.get(), theirs.lending_id);
+            assert_eq!(ours.decimals, theirs.decimals);
+            assert_eq!(ours.rewards_rate_model, theirs.rewards_rate_model);
+            assert_eq!(ours.liquidity_exchange_price.get(), theirs.liquidity_exchange_price);
+            assert_eq!(ours.token_exchange_price.get(), theirs.token_exchange_price);
+            assert_eq!(ours.last_update_timestamp.get(), theirs.last_update_timestamp);
+            assert_eq!(ours.token_reserves_liquidity, theirs.token_reserves_liquidity);
+            assert_eq!(ours.supply_position_on_liquidity, theirs.supply_position_on_liquidity);
+            assert_eq!(ours.bump, theirs.bump);
+        }
+    }
+
+    #[test]
+    fn rejects_data_that_is_not_a_lending_account() {
+        let data = lending_data(JUP_EXCHANGE_PRICE_PRECISION as u64);
+        let longer = [&data[..], &[0]].concat();
+        assert_eq!(Lending::from_data(&data[..data.len() - 1]).unwrap_err(), ErrorCode::InvalidLendingAccount.into());
+        // Fields appended in a later jup_lend version are skipped
+        assert_eq!(Lending::from_data(&longer).unwrap().token_exchange_price.get(), JUP_EXCHANGE_PRICE_PRECISION as u64);
+
+        let mut other = data.clone();
+        other[..8].copy_from_slice(jup_lend::accounts::LendingAdmin::DISCRIMINATOR);
+        assert_eq!(Lending::from_data(&other).unwrap_err(), ErrorCode::InvalidLendingAccount.into());
+    }
+
+    #[test]
+    fn reads_the_usdc_lending_fixture() {
+        let mut dump = AccountDump::load("jup_usdc_lending.json");
+        let info = dump.info();
+        let lending = Lending::load(&info).unwrap();
+        assert_eq!(lending.mint(), USDC_MINT);
+        assert_ne!(lending.f_token_mint(), Pubkey::default());
+        assert_eq!(lending.decimals, 6);
+
+        // f-tokens only ever appreciate against USDC
+        let rate = lending.exchange_rate().unwrap();
+        assert!(rate > EXCHANGE_RATE_SCALE && rate < 2 * EXCHANGE_RATE_SCALE, "rate {rate}");
+        let assets = lending.f_tokens_to_assets(1_000_000, Rounding::Down).unwrap();
+        assert_eq!(assets as u128, 1_000_000 * rate / EXCHANGE_RATE_SCALE);
+    }
+
+    #[test]
+    #[ignore = "needs a mainnet capture of the fixture, see tests/fixtures/README.md"]
+    fn the_usdc_lending_capture_is_the_live_market() {
+        let mut dump = AccountDump::load("jup_usdc_lending.json");
+        dump.require_capture();
+        let info = dump.info();
+        let lending = Lending::load(&info).unwrap();
+        // jlUSDC, the f-token `getDepositContext` hands out for USDC
+        assert_eq!(lending.f_token_mint(), pubkey!("9BEcn9aPEmhSPbPQeFGjidRiEKki46fVQDyPpSQXPA2D"));
+        assert_eq!(lending.mint(), USDC_MINT);
+        assert_eq!(lending.decimals, 6);
+    }
+
+    #[test]
+    fn rejects_accounts_not_owned_by_jup_lend() {
+        let mut dump = AccountDump::load("jup_usdc_lending.json");
+        dump.owner = Pubkey::new_unique();
+        let info = dump.info();
+        assert_eq!(Lending::load(&info).unwrap_err(), ErrorCode::InvalidLendingAccount.into());
+    }
 }
@@ -1,33 +1,553 @@
 use anchor_lang::prelude::*;
+use bytemuck::{Pod, Zeroable};
 
-#[account]
-#[derive(Debug)]
-pub struct ReserveLiquidity {
-    pub mint_pubkey: Pubkey,
-    pub supply_vault: Pubkey,
-    pub fee_vault: Pubkey,
-    pub available_amount: u64,
-    pub borrowed_amount_sf: u128,
-    pub market_price_sf: u128,
-    pub market_price_last_updated_ts: u64,
-    pub mint_decimals: u8,
-}
-
-#[account]
-#[derive(Debug)]
-pub struct ReserveCollateral {
-    pub mint_pubkey: Pubkey,
-    pub mint_total_supply: u64,
-    pub supply_vault: Pubkey,
-}
-
-#[account]
-#[derive(Debug)]
+use crate::{EXCHANGE_RATE_SCALE, ExternalAccount, KAMINO_LENDING_PROGRAM_ID, PodU128, Rounding, error::ErrorCode};
+
+/// Fractional bits of klend's scaled fractions, the `_sf` fields
+pub const KAMINO_FRACTION_BITS: u32 = 60;
+
+/// Zero-copy Kamino Lend reserve, field for field the `Reserve` account of `idls/kamino_lending.json`.
+/// Fields are private, the vault only reads the ones it has accessors for.
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
 pub struct Reserve {
-    pub version: u64,
-    pub last_update: u64, // simplified
-    pub lending_market: Pubkey,
-    pub liquidity: ReserveLiquidity,
-    pub collateral: ReserveCollateral,
-    // other fields omitted for simplicity
-}
\ No newline at end of file
+    version: u64,
+    last_update: LastUpdate,
+    lending_market: Pubkey,
+    farm_collateral: Pubkey,
+    farm_debt: Pubkey,
+    liquidity: ReserveLiquidity,
+    reserve_liquidity_padding: [u64; 150],
+    collateral: ReserveCollateral,
+    reserve_collateral_padding: [u64; 150],
+    config: ReserveConfig,
+    config_padding: [u64; 116],
+    borrowed_amount_outside_elevation_group: u64,
+    borrowed_amounts_against_this_reserve_in_elevation_groups: [u64; 32],
+    padding: [u64; 207],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct LastUpdate {
+    slot: u64,
+    stale: u8,
+    price_status: u8,
+    placeholder: [u8; 6],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct ReserveLiquidity {
+    mint_pubkey: Pubkey,
+    supply_vault: Pubkey,
+    fee_vault: Pubkey,
+    available_amount: u64,
+    borrowed_amount_sf: PodU128,
+    market_price_sf: PodU128,
+    market_price_last_updated_ts: u64,
+    mint_decimals: u64,
+    deposit_limit_crossed_timestamp: u64,
+    borrow_limit_crossed_timestamp: u64,
+    cumulative_borrow_rate_bsf: BigFractionBytes,
+    accumulated_protocol_fees_sf: PodU128,
+    accumulated_referrer_fees_sf: PodU128,
+    pending_referrer_fees_sf: PodU128,
+    absolute_referral_rate_sf: PodU128,
+    token_program: Pubkey,
+    padding2: [u64; 51],
+    padding3: [PodU128; 32],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct BigFractionBytes {
+    value: [u64; 4],
+    padding: [u64; 2],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct ReserveCollateral {
+    mint_pubkey: Pubkey,
+    mint_total_supply: u64,
+    supply_vault: Pubkey,
+    padding1: [PodU128; 32],
+    padding2: [PodU128; 32],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct ReserveConfig {
+    status: u8,
+    asset_tier: u8,
+    host_fixed_interest_rate_bps: u16,
+    reserved1: [u8; 9],
+    protocol_order_execution_fee_pct: u8,
+    protocol_take_rate_pct: u8,
+    protocol_liquidation_fee_pct: u8,
+    loan_to_value_pct: u8,
+    liquidation_threshold_pct: u8,
+    min_liquidation_bonus_bps: u16,
+    max_liquidation_bonus_bps: u16,
+    bad_debt_liquidation_bonus_bps: u16,
+    deleveraging_margin_call_period_secs: u64,
+    deleveraging_threshold_decrease_bps_per_day: u64,
+    fees: ReserveFees,
+    borrow_rate_curve: BorrowRateCurve,
+    borrow_factor_pct: u64,
+    deposit_limit: u64,
+    borrow_limit: u64,
+    token_info: TokenInfo,
+    deposit_withdrawal_cap: WithdrawalCaps,
+    debt_withdrawal_cap: WithdrawalCaps,
+    elevation_groups: [u8; 20],
+    disable_usage_as_coll_outside_emode: u8,
+    utilization_limit_block_borrowing_above_pct: u8,
+    autodeleverage_enabled: u8,
+    proposer_authority_locked: u8,
+    borrow_limit_outside_elevation_group: u64,
+    borrow_limit_against_this_collateral_in_elevation_group: [u64; 32],
+    deleveraging_bonus_increase_bps_per_day: u64,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct ReserveFees {
+    origination_fee_sf: u64,
+    flash_loan_fee_sf: u64,
+    padding: [u8; 8],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct BorrowRateCurve {
+    points: [CurvePoint; 11],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct CurvePoint {
+    utilization_rate_bps: u32,
+    borrow_rate_bps: u32,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct TokenInfo {
+    name: [u8; 32],
+    heuristic: PriceHeuristic,
+    max_twap_divergence_bps: u64,
+    max_age_price_seconds: u64,
+    max_age_twap_seconds: u64,
+    scope_configuration: ScopeConfiguration,
+    switchboard_configuration: SwitchboardConfiguration,
+    pyth_configuration: PythConfiguration,
+    block_price_usage: u8,
+    reserved: [u8; 7],
+    padding: [u64; 19],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct PriceHeuristic {
+    lower: u64,
+    upper: u64,
+    exp: u64,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct ScopeConfiguration {
+    price_feed: Pubkey,
+    price_chain: [u16; 4],
+    twap_chain: [u16; 4],
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct SwitchboardConfiguration {
+    price_aggregator: Pubkey,
+    twap_aggregator: Pubkey,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct PythConfiguration {
+    price: Pubkey,
+}
+
+#[derive(Clone, Copy, Pod, Zeroable, Debug)]
+#[repr(C)]
+struct WithdrawalCaps {
+    config_capacity: i64,
+    current_total: i64,
+    last_interval_start_timestamp: u64,
+    config_interval_length_seconds: u64,
+}
+
+const _: () = assert!(std::mem::size_of::<Reserve>() == 8616);
+
+impl ExternalAccount for Reserve {
+    const OWNER: Pubkey = KAMINO_LENDING_PROGRAM_ID;
+    /// sha256("account:Reserve")[0..8]
+    const DISCRIMINATOR: [u8; 8] = [43, 242, 204, 202, 26, 247, 59, 127];
+}
+
+/// Collateral is priced against the reserve's total liquidity, which like klend counts what is
+/// lent out as well as what sits in the supply vault, minus the fees owed to the protocol and
+/// referrers: `available + borrowed_sf / 2^60 - fees_sf / 2^60`. An empty reserve, or one with
+/// no collateral minted, converts 1:1.
+impl Reserve {
+    /// Lending market the reserve belongs to
+    pub fn lending_market(&self) -> Pubkey {
+        self.lending_market
+    }
+
+    /// Token account holding the reserve's available liquidity
+    pub fn liquidity_supply_vault(&self) -> Pubkey {
+        self.liquidity.supply_vault
+    }
+
+    /// Mint of the liquidity the reserve lends out
+    pub fn liquidity_mint(&self) -> Pubkey {
+        self.liquidity.mint_pubkey
+    }
+
+    /// Mint of the collateral tokens handed to depositors
+    pub fn collateral_mint(&self) -> Pubkey {
+        self.collateral.mint_pubkey
+    }
+
+    /// Rejects a reserve whose interest and balances were last refreshed more than
+    /// `max_staleness` slots before `current_slot`. The `stale` flag is left to `check_refreshed`:
+    /// klend raises it after every deposit and redeem, the vault's own included, which keep the
+    /// balances exact until the next refresh.
+    pub fn check_fresh(&self, current_slot: u64, max_staleness: u64) -> Result<()> {
+        let elapsed = current_slot.saturating_sub(self.last_update.slot);
+        require!(elapsed <= max_staleness, ErrorCode::StaleReserve);
+        Ok(())
+    }
+
+    /// Rejects a reserve that was not refreshed in `current_slot`, or was deposited into or
+    /// redeemed from since its refresh
+    pub fn check_refreshed(&self, current_slot: u64) -> Result<()> {
+        require!(
+            self.last_update.slot == current_slot && self.last_update.stale == 0,
+            ErrorCode::StaleReserve
+        );
+        Ok(())
+    }
+
+    /// Liquidity backing the collateral mint, a scaled fraction
+    pub fn total_liquidity_sf(&self) -> Result<u128> {
+        let liquidity = &self.liquidity;
+        let fees = liquidity
+            .accumulated_protocol_fees_sf
+            .get()
+            .checked_add(liquidity.accumulated_referrer_fees_sf.get())
+            .and_then(|v| v.checked_add(liquidity.pending_referrer_fees_sf.get()))
+            .ok_or(ErrorCode::MathOverflow)?;
+        ((liquidity.available_amount as u128) << KAMINO_FRACTION_BITS)
+            .checked_add(liquidity.borrowed_amount_sf.get())
+            .and_then(|v| v.checked_sub(fees))
+            .ok_or(ErrorCode::MathOverflow.into())
+    }
+
+    /// `(total liquidity, collateral supply)`, or `None` while either is zero and the rate is 1:1
+    fn collateral_rate(&self) -> Result<Option<(u128, u128)>> {
+        let total_liquidity_sf = self.total_liquidity_sf()?;
+        let supply = self.collateral.mint_total_supply as u128;
+        if total_liquidity_sf == 0 || supply == 0 {
+            return Ok(None);
+        }
+        Ok(Some((total_liquidity_sf, supply)))
+    }
+
+    /// USDC redeemable for `collateral_amount` collateral tokens
+    pub fn collateral_to_liquidity(&self, collateral_amount: u64, rounding: Rounding) -> Result<u64> {
+        let Some((total_liquidity_sf, supply)) = self.collateral_rate()? else {
+            return Ok(collateral_amount);
+        };
+        let (liquidity, inexact) = mul_sf(collateral_amount as u128, total_liquidity_sf).ok_or(ErrorCode::MathOverflow)?;
+        let result = round(liquidity / supply, inexact || liquidity % supply != 0, rounding)?;
+        u64::try_from(result).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// Collateral tokens worth `liquidity_amount` USDC. Rounded up, redeeming the result yields
+    /// at least `liquidity_amount`; rounded down, it never exceeds it.
+    pub fn liquidity_to_collateral(&self, liquidity_amount: u64, rounding: Rounding) -> Result<u64> {
+        let Some((total_liquidity_sf, supply)) = self.collateral_rate()? else {
+            return Ok(liquidity_amount);
+        };
+        let numerator = (liquidity_amount as u128) * supply;
+        let (collateral, inexact) = shl_div(numerator, KAMINO_FRACTION_BITS, total_liquidity_sf).ok_or(ErrorCode::MathOverflow)?;
+        let result = round(collateral, inexact, rounding)?;
+        u64::try_from(result).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// USDC per collateral token, scaled by `EXCHANGE_RATE_SCALE`
+    pub fn exchange_rate(&self) -> Result<u128> {
+        let Some((total_liquidity_sf, supply)) = self.collateral_rate()? else {
+            return Ok(EXCHANGE_RATE_SCALE);
+        };
+        let (scaled, _) = mul_sf(EXCHANGE_RATE_SCALE, total_liquidity_sf).ok_or(ErrorCode::MathOverflow)?;
+        Ok(scaled / supply)
+    }
+}
+
+/// `amount * sf / 2^60` rounded down, and whether anything was rounded off. The scaled fraction
+/// is split into its integer and fractional parts so the product never needs more than 128 bits.
+fn mul_sf(amount: u128, sf: u128) -> Option<(u128, bool)> {
+    let mask = (1u128 << KAMINO_FRACTION_BITS) - 1;
+    let fraction = amount.checked_mul(sf & mask)?;
+    let result = amount.checked_mul(sf >> KAMINO_FRACTION_BITS)?.checked_add(fraction >> KAMINO_FRACTION_BITS)?;
+    Some((result, fraction & mask != 0))
+}
+
+/// `numerator * 2^shift / denominator` rounded down, and whether a remainder was left. Long
+/// division, shifting the remainder in as many bits at a time as fit.
+fn shl_div(numerator: u128, shift: u32, denominator: u128) -> Option<(u128, bool)> {
+    let mut quotient = numerator / denominator;
+    let mut remainder = numerator % denominator;
+    let mut bits_left = shift;
+    while bits_left > 0 {
+        let step = denominator.leading_zeros().min(bits_left);
+        if step == 0 {
+            return None;
+        }
+        quotient = quotient.checked_mul(1 << step)?;
+        remainder <<= step;
+        quotient = quotient.checked_add(remainder / denominator)?;
+        remainder %= denominator;
+        bits_left -= step;
+    }
+    Some((quotient, remainder != 0))
+}
+
+fn round(value: u128, inexact: bool, rounding: Rounding) -> Result<u128> {
+    match rounding {
+        Rounding::Up if inexact => value.checked_add(1).ok_or(ErrorCode::MathOverflow.into()),
+        _ => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_utils::{AccountDump, Cases, USDC_MINT};
+    use crate::MAX_RESERVE_STALENESS_SLOTS;
+    use anchor_lang::solana_program::hash::hash;
+    use std::mem::{offset_of, size_of};
+
+    const CASES: usize = 20_000;
+    const ONE_SF: u128 = 1 << KAMINO_FRACTION_BITS;
+
+    fn reserve(available: u64, borrowed_sf: u128, fees_sf: u128, collateral_supply: u64) -> Box<Reserve> {
+        let mut reserve = Box::new(Reserve::zeroed());
+        reserve.last_update.slot = 1_000;
+        reserve.liquidity.available_amount = available;
+        reserve.liquidity.borrowed_amount_sf = borrowed_sf.into();
+        reserve.liquidity.accumulated_protocol_fees_sf = (fees_sf / 2).into();
+        reserve.liquidity.accumulated_referrer_fees_sf = (fees_sf / 4).into();
+        reserve.liquidity.pending_referrer_fees_sf = (fees_sf - fees_sf / 2 - fees_sf / 4).into();
+        reserve.collateral.mint_total_supply = collateral_supply;
+        reserve
+    }
+
+    /// A reserve whose numbers keep every exact product inside a u128
+    fn small_reserve(cases: &mut Cases) -> Box<Reserve> {
+        let available = cases.next() % (1 << 31);
+        let borrowed_sf = (cases.next() as u128) << (cases.next() % 28);
+        let fees_sf = borrowed_sf / (2 + (cases.next() % 50) as u128);
+        reserve(available, borrowed_sf, fees_sf, 1 + cases.next() % (1 << 31))
+    }
+
+    #[test]
+    fn matches_the_kamino_reserve_layout() {
+        // Offsets from idls/kamino_lending.json
+        assert_eq!(size_of::<Reserve>(), 8616);
+        assert_eq!(offset_of!(Reserve, last_update), 8);
+        assert_eq!(offset_of!(Reserve, lending_market), 24);
+        assert_eq!(offset_of!(Reserve, liquidity), 120);
+        assert_eq!(offset_of!(Reserve, collateral), 2552);
+        assert_eq!(offset_of!(Reserve, config), 4848);
+        assert_eq!(offset_of!(Reserve, borrowed_amount_outside_elevation_group), 6696);
+        assert_eq!(offset_of!(Reserve, padding), 6960);
+
+        assert_eq!(size_of::<ReserveLiquidity>(), 1232);
+        assert_eq!(offset_of!(ReserveLiquidity, available_amount), 96);
+        assert_eq!(offset_of!(ReserveLiquidity, borrowed_amount_sf), 104);
+        assert_eq!(offset_of!(ReserveLiquidity, accumulated_protocol_fees_sf), 216);
+        assert_eq!(offset_of!(ReserveLiquidity, accumulated_referrer_fees_sf), 232);
+        assert_eq!(offset_of!(ReserveLiquidity, pending_referrer_fees_sf), 248);
+        assert_eq!(offset_of!(ReserveLiquidity, token_program), 280);
+        assert_eq!(size_of::<ReserveCollateral>(), 1096);
+        assert_eq!(offset_of!(ReserveCollateral, mint_total_supply), 32);
+        assert_eq!(size_of::<ReserveConfig>(), 920);
+        assert_eq!(size_of::<TokenInfo>(), 384);
+
+        assert_eq!(Reserve::DISCRIMINATOR, hash(b"account:Reserve").to_bytes()[..8]);
+    }
+
+    #[test]
+    fn reads_the_usdc_reserve_fixture() {
+        let mut dump = AccountDump::load("kamino_usdc_reserve.json");
+        let info = dump.info();
+        let reserve = Reserve::load(&info).unwrap();
+        assert_eq!(reserve.liquidity_mint(), USDC_MINT);
+        assert_eq!(reserve.lending_market, pubkey!("7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF"));
+        assert_ne!(reserve.collateral_mint(), Pubkey::default());
+        assert_eq!(reserve.liquidity.mint_decimals, 6);
+        reserve.check_fresh(reserve.last_update.slot, 0).unwrap();
+
+        // Most of the reserve is lent out, so pricing from `available_amount` alone would be far off
+        let liquidity = &reserve.liquidity;
+        let fees_sf = liquidity.accumulated_protocol_fees_sf.get()
+            + liquidity.accumulated_referrer_fees_sf.get()
+            + liquidity.pending_referrer_fees_sf.get();
+        let total_liquidity_sf = reserve.total_liquidity_sf().unwrap();
+        assert_eq!(total_liquidity_sf, ((liquidity.available_amount as u128) << KAMINO_FRACTION_BITS) + liquidity.borrowed_amount_sf.get() - fees_sf);
+        assert!(total_liquidity_sf >> KAMINO_FRACTION_BITS > 2 * liquidity.available_amount as u128);
+
+        // Collateral only ever appreciates against USDC
+        let rate = reserve.exchange_rate().unwrap();
+        assert!(rate > EXCHANGE_RATE_SCALE && rate < 2 * EXCHANGE_RATE_SCALE, "rate {rate}");
+        let assets = reserve.collateral_to_liquidity(1_000_000, Rounding::Down).unwrap();
+        assert_eq!(assets as u128, 1_000_000 * rate / EXCHANGE_RATE_SCALE);
+    }
+
+    #[test]
+    #[ignore = "needs a mainnet capture of the fixture, see tests/fixtures/README.md"]
+    fn the_usdc_reserve_capture_is_the_live_market() {
+        let mut dump = AccountDump::load("kamino_usdc_reserve.json");
+        dump.require_capture();
+        let slot = dump.slot.unwrap();
+        let info = dump.info();
+        let reserve = Reserve::load(&info).unwrap();
+        assert_eq!(reserve.lending_market, pubkey!("7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF"));
+        assert_eq!(reserve.liquidity_mint(), USDC_MINT);
+        assert_eq!(reserve.liquidity.mint_decimals, 6);
+        // A live reserve was last refreshed before it was read, and lends most of its USDC out
+        assert!(reserve.last_update.slot <= slot);
+        assert!(reserve.liquidity.borrowed_amount_sf.get() > 0);
+    }
+
+    #[test]
+    fn rejects_accounts_that_are_not_kamino_reserves() {
+        let mut dump = AccountDump::load("kamino_usdc_reserve.json");
+        dump.owner = Pubkey::new_unique();
+        assert_eq!(Reserve::load(&dump.info()).unwrap_err(), ErrorCode::InvalidLendingAccount.into());
+
+        let mut dump = AccountDump::load("kamino_usdc_reserve.json");
+        let data = dump.data();
+        assert_eq!(Reserve::from_data(&data[..data.len() - 8]).unwrap_err(), ErrorCode::InvalidLendingAccount.into());
+        data[0] ^= 1;
+        assert_eq!(Reserve::from_data(data).unwrap_err(), ErrorCode::InvalidLendingAccount.into());
+    }
+
+    #[test]
+    fn borrowed_liquidity_and_fees_move_the_collateral_value() {
+        // 1M idle, 3M lent out, 0.5M of fees: 3.5M USDC backing 2M collateral
+        let reserve = reserve(1_000_000, 3_000_000 * ONE_SF, 500_000 * ONE_SF, 2_000_000);
+        assert_eq!(reserve.collateral_to_liquidity(1_000_000, Rounding::Down).unwrap(), 1_750_000);
+        assert_eq!(reserve.liquidity_to_collateral(1_750_000, Rounding::Down).unwrap(), 1_000_000);
+        assert_eq!(reserve.exchange_rate().unwrap(), EXCHANGE_RATE_SCALE * 7 / 4);
+
+        // Fractions of a token of interest still count
+        let reserve = self::reserve(1_000_000, ONE_SF / 2, 0, 1_000_000);
+        assert_eq!(reserve.collateral_to_liquidity(2_000_000, Rounding::Down).unwrap(), 2_000_001);
+        assert_eq!(reserve.collateral_to_liquidity(1_000_000, Rounding::Down).unwrap(), 1_000_000);
+        assert_eq!(reserve.collateral_to_liquidity(1_000_000, Rounding::Up).unwrap(), 1_000_001);
+    }
+
+    #[test]
+    fn empty_reserves_convert_one_to_one() {
+        for reserve in [reserve(0, 0, 0, 0), reserve(1_000, 0, 0, 0), reserve(0, 0, 0, 1_000)] {
+            for rounding in [Rounding::Down, Rounding::Up] {
+                assert_eq!(reserve.collateral_to_liquidity(123, rounding).unwrap(), 123);
+                assert_eq!(reserve.liquidity_to_collateral(123, rounding).unwrap(), 123);
+            }
+            assert_eq!(reserve.exchange_rate().unwrap(), EXCHANGE_RATE_SCALE);
+        }
+    }
+
+    #[test]
+    fn fees_above_the_liquidity_are_rejected() {
+        let reserve = reserve(1, ONE_SF, 3 * ONE_SF, 1_000);
+        assert_eq!(reserve.exchange_rate().unwrap_err(), ErrorCode::MathOverflow.into());
+    }
+
+    #[test]
+    fn rounding_brackets_the_exact_value() {
+        let mut cases = Cases(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..CASES {
+            let reserve = small_reserve(&mut cases);
+            let total_liquidity_sf = reserve.total_liquidity_sf().unwrap();
+            let supply = reserve.collateral.mint_total_supply as u128;
+            if total_liquidity_sf == 0 {
+                continue;
+            }
+            let amount = cases.next() % (1 << 32);
+
+            let product = amount as u128 * total_liquidity_sf;
+            let denominator = supply << KAMINO_FRACTION_BITS;
+            let down = reserve.collateral_to_liquidity(amount, Rounding::Down).unwrap();
+            let up = reserve.collateral_to_liquidity(amount, Rounding::Up).unwrap();
+            assert_eq!(down as u128, product / denominator);
+            assert_eq!(up as u128, product.div_ceil(denominator));
+
+            let product = (amount as u128 * supply) << KAMINO_FRACTION_BITS;
+            let down = reserve.liquidity_to_collateral(amount, Rounding::Down).unwrap();
+            let up = reserve.liquidity_to_collateral(amount, Rounding::Up).unwrap();
+            assert_eq!(down as u128, product / total_liquidity_sf);
+            assert_eq!(up as u128, product.div_ceil(total_liquidity_sf));
+        }
+    }
+
+    #[test]
+    fn round_trips_never_create_value() {
+        let mut cases = Cases(0xd1b5_4a32_d192_ed03);
+        for _ in 0..CASES {
+            let reserve = reserve(cases.amount() / 4, (cases.amount() as u128) << (cases.next() % 61), 0, cases.amount().max(1));
+            let amount = cases.amount();
+
+            // Collateral -> USDC -> collateral, both rounded down, never gives back more collateral
+            if let Ok(liquidity) = reserve.collateral_to_liquidity(amount, Rounding::Down) {
+                let collateral = reserve.liquidity_to_collateral(liquidity, Rounding::Down).unwrap();
+                assert!(collateral <= amount);
+            }
+
+            // Redeeming the collateral rounded up for some USDC pays out at least that USDC
+            if let Ok(collateral) = reserve.liquidity_to_collateral(amount, Rounding::Up) {
+                if let Ok(liquidity) = reserve.collateral_to_liquidity(collateral, Rounding::Down) {
+                    assert!(liquidity >= amount);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn staleness_is_measured_from_the_last_refresh() {
+        let reserve = reserve(1_000, 0, 0, 1_000);
+        reserve.check_fresh(1_000, 0).unwrap();
+        assert_eq!(reserve.check_fresh(1_001, 0).unwrap_err(), ErrorCode::StaleReserve.into());
+        reserve.check_fresh(1_000 + MAX_RESERVE_STALENESS_SLOTS, MAX_RESERVE_STALENESS_SLOTS).unwrap();
+        assert_eq!(
+            reserve.check_fresh(1_001 + MAX_RESERVE_STALENESS_SLOTS, MAX_RESERVE_STALENESS_SLOTS).unwrap_err(),
+            ErrorCode::StaleReserve.into()
+        );
+    }
+
+    #[test]
+    fn moving_funds_needs_a_refresh_in_the_same_slot() {
+        let mut reserve = reserve(1_000, 0, 0, 1_000);
+        reserve.check_refreshed(1_000).unwrap();
+        assert_eq!(reserve.check_refreshed(1_001).unwrap_err(), ErrorCode::StaleReserve.into());
+
+        // klend marks the reserve stale after a deposit or redeem until it is refreshed again
+        reserve.last_update.stale = 1;
+        assert_eq!(reserve.check_refreshed(1_000).unwrap_err(), ErrorCode::StaleReserve.into());
+        reserve.check_fresh(1_000, 0).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+use crate::{UserPosition, Vault, error::ErrorCode};
+
+/// Vault from before the share mint, PDA of `[b"vault", authority]`. It shares the `Vault`
+/// discriminator but not its layout, so it is only ever read by hand with `LegacyVault::load`.
+/// `migrate_vault` moves its funds into a current vault and rewrites the account as a `MigratedVault`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct LegacyVault {
+    pub authority: Pubkey,
+    pub usdc_mint: Pubkey,
+    pub vault_usdc_ata: Pubkey,
+    /// Shares held by `LegacyUserPosition::shares`, never tokenized
+    pub total_shares: u64,
+    pub acc_per_share: u64,
+    pub total_underlying: u64,
+    pub jup_lend_balance: u64,
+    pub kamino_balance: u64,
+    pub last_jup_value: u64,
+    pub last_kamino_value: u64,
+    pub jup_allocation: u16,
+    pub kamino_allocation: u16,
+    pub last_update_ts: i64,
+    pub bump: u8,
+}
+
+/// User position from before the share mint, PDA of `[b"user_position", user]`. Shares the
+/// `UserPosition` discriminator, read with `LegacyUserPosition::load`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct LegacyUserPosition {
+    pub vault: Pubkey,
+    pub user: Pubkey,
+    pub shares: u64,
+    pub reward_debt: u128,
+    pub pending_rewards: u64,
+    pub last_updated: i64,
+    pub bump: u8,
+}
+
+impl LegacyVault {
+    /// Account size of a legacy vault, discriminator included
+    pub const LEN: usize = 8 + 32 * 3 + 8 * 7 + 2 * 2 + 8 + 1;
+
+    /// Reads a legacy vault, rejecting anything that is not a program account of exactly the
+    /// legacy size with the `Vault` discriminator
+    pub fn load(info: &AccountInfo) -> Result<Self> {
+        read_legacy(info, Vault::DISCRIMINATOR, Self::LEN)
+    }
+}
+
+impl LegacyUserPosition {
+    /// Account size of a legacy position, discriminator included
+    pub const LEN: usize = 8 + 32 * 2 + 8 + 16 + 8 + 8 + 1;
+
+    /// Reads a legacy position, see `LegacyVault::load`
+    pub fn load(info: &AccountInfo) -> Result<Self> {
+        read_legacy(info, UserPosition::DISCRIMINATOR, Self::LEN)
+    }
+}
+
+fn read_legacy<T: AnchorDeserialize>(info: &AccountInfo, discriminator: &[u8], len: usize) -> Result<T> {
+    require_keys_eq!(*info.owner, crate::ID, ErrorCode::InvalidLegacyAccount);
+    let data = info.try_borrow_data()?;
+    require!(data.len() == len && data[..8] == *discriminator, ErrorCode::InvalidLegacyAccount);
+    T::deserialize(&mut &data[8..]).map_err(|_| ErrorCode::InvalidLegacyAccount.into())
+}
+
+/// A legacy vault once `migrate_vault` moved its funds, rewritten in place at the legacy address.
+/// Holds the vault shares booked for the legacy holders until each of them claims theirs with
+/// `migrate_user_position`.
+#[account]
+#[derive(InitSpace)]
+pub struct MigratedVault {
+    /// Authority of the legacy vault, its PDA seed
+    pub authority: Pubkey,
+
+    /// Vault the funds moved to
+    pub vault: Pubkey,
+
+    /// Legacy shares whose holders have not migrated yet
+    pub legacy_shares: u64,
+
+    /// Shares of `vault` booked for them, counted in `Vault::total_shares` but not minted
+    pub vault_shares: u64,
+
+    /// `LegacyVault::acc_per_share` at migration, to settle the holders' legacy rewards
+    pub legacy_acc_per_share: u64,
+
+    pub bump: u8,
+}
+
+impl MigratedVault {
+    /// Takes the part of the booked vault shares owed for `legacy_shares`, rounded down.
+    /// The last holder gets whatever rounding left over.
+    pub fn take_shares(&mut self, legacy_shares: u64) -> Result<u64> {
+        require!(legacy_shares > 0, ErrorCode::NothingToMigrate);
+        require!(legacy_shares <= self.legacy_shares, ErrorCode::InvalidLegacyAccount);
+        let shares = if legacy_shares == self.legacy_shares {
+            self.vault_shares
+        } else {
+            (self.vault_shares as u128 * legacy_shares as u128 / self.legacy_shares as u128) as u64
+        };
+        self.legacy_shares -= legacy_shares;
+        self.vault_shares -= shares;
+        Ok(shares)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrated(legacy_shares: u64, vault_shares: u64) -> MigratedVault {
+        MigratedVault {
+            authority: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            legacy_shares,
+            vault_shares,
+            legacy_acc_per_share: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn legacy_sizes_match_the_old_layouts() {
+        let vault = LegacyVault {
+            authority: Pubkey::new_unique(),
+            usdc_mint: Pubkey::new_unique(),
+            vault_usdc_ata: Pubkey::new_unique(),
+            total_shares: 1,
+            acc_per_share: 2,
+            total_underlying: 3,
+            jup_lend_balance: 4,
+            kamino_balance: 5,
+            last_jup_value: 6,
+            last_kamino_value: 7,
+            jup_allocation: 5_000,
+            kamino_allocation: 5_000,
+            last_update_ts: 8,
+            bump: 254,
+        };
+        assert_eq!(8 + vault.try_to_vec().unwrap().len(), LegacyVault::LEN);
+
+        let position = LegacyUserPosition {
+            vault: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            shares: 1,
+            reward_debt: 2,
+            pending_rewards: 3,
+            last_updated: 4,
+            bump: 253,
+        };
+        assert_eq!(8 + position.try_to_vec().unwrap().len(), LegacyUserPosition::LEN);
+
+        // Today's accounts have other sizes, so they can never pass for legacy ones, and a
+        // migrated vault fits in the legacy account it replaces
+        const { assert!(8 + Vault::INIT_SPACE > LegacyVault::LEN) };
+        const { assert!(8 + MigratedVault::INIT_SPACE < LegacyVault::LEN) };
+        assert_ne!(8 + UserPosition::INIT_SPACE, LegacyUserPosition::LEN);
+    }
+
+    #[test]
+    fn only_legacy_sized_program_accounts_load() {
+        let position = LegacyUserPosition {
+            vault: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            shares: 42,
+            reward_debt: 0,
+            pending_rewards: 7,
+            last_updated: 0,
+            bump: 255,
+        };
+        let mut data = UserPosition::DISCRIMINATOR.to_vec();
+        data.extend(position.try_to_vec().unwrap());
+        let key = Pubkey::new_unique();
+        let load = |data: &mut [u8], owner: &Pubkey| {
+            let mut lamports = 0;
+            LegacyUserPosition::load(&AccountInfo::new(&key, false, false, &mut lamports, data, owner, false, 0))
+        };
+
+        assert_eq!(load(&mut data.clone(), &crate::ID).unwrap(), position);
+        assert!(load(&mut data.clone(), &Pubkey::new_unique()).is_err());
+        // A current position of the same user is shorter, a legacy vault has another discriminator
+        assert!(load(&mut data[..8 + UserPosition::INIT_SPACE], &crate::ID).is_err());
+        let mut vault_data = data.clone();
+        vault_data[..8].copy_from_slice(Vault::DISCRIMINATOR);
+        assert!(load(&mut vault_data, &crate::ID).is_err());
+    }
+
+    #[test]
+    fn holders_split_the_booked_shares_pro_rata() {
+        // 3 legacy shares worth 10 vault shares: 3 + 3, then the last holder gets the 4 left
+        let mut vault = migrated(3, 10);
+        assert_eq!(vault.take_shares(1).unwrap(), 3);
+        assert_eq!(vault.take_shares(1).unwrap(), 3);
+        assert_eq!(vault.take_shares(1).unwrap(), 4);
+        assert_eq!((vault.legacy_shares, vault.vault_shares), (0, 0));
+    }
+
+    #[test]
+    fn holders_cannot_take_more_than_is_left() {
+        let mut vault = migrated(5, 7);
+        assert_eq!(vault.take_shares(0).unwrap_err(), ErrorCode::NothingToMigrate.into());
+        assert_eq!(vault.take_shares(6).unwrap_err(), ErrorCode::InvalidLegacyAccount.into());
+        assert_eq!(vault.take_shares(5).unwrap(), 7);
+    }
+}
@@ -1,9 +1,21 @@
 pub mod vault;
+pub mod strategy;
 pub mod user_position;
+pub mod queued_action;
 pub mod jup_states;
 pub mod kamino_states;
+pub mod pod;
+pub mod external;
+pub mod legacy;
+#[cfg(test)]
+mod test_utils;
 
 pub use vault::*;
+pub use strategy::*;
 pub use user_position::*;
+pub use queued_action::*;
 pub use jup_states::*;
-pub use kamino_states::*;
\ No newline at end of file
+pub use kamino_states::*;
+pub use pod::*;
+pub use external::*;
+pub use legacy::*;
\ No newline at end of file
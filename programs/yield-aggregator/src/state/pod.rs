@@ -0,0 +1,28 @@
+use bytemuck::{Pod, Zeroable};
+
+/// Little-endian integers with an alignment of 1, for reading other programs' accounts in
+/// place. Borsh packs fields with no alignment at all, and klend keeps its u128s 8 byte
+/// aligned, so native integers would not line up with either layout.
+macro_rules! pod_int {
+    ($name:ident, $int:ty) => {
+        #[derive(Clone, Copy, Pod, Zeroable, Default, PartialEq, Eq, Debug)]
+        #[repr(transparent)]
+        pub struct $name([u8; std::mem::size_of::<$int>()]);
+
+        impl $name {
+            pub fn get(&self) -> $int {
+                <$int>::from_le_bytes(self.0)
+            }
+        }
+
+        impl From<$int> for $name {
+            fn from(value: $int) -> Self {
+                Self(value.to_le_bytes())
+            }
+        }
+    };
+}
+
+pod_int!(PodU16, u16);
+pod_int!(PodU64, u64);
+pod_int!(PodU128, u128);
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::{AdapterKind, FeeConfig, MAX_STRATEGIES, WeightBounds};
+
+/// A risk-sensitive vault change waiting out the timelock,
+/// PDA of `[b"queued_action", vault, id]`
+#[account]
+#[derive(InitSpace)]
+pub struct QueuedAction {
+    /// Vault the change applies to
+    pub vault: Pubkey,
+
+    /// `Vault::next_action_id` when the action was queued
+    pub id: u64,
+
+    /// Who queued the action, gets the rent back once it is executed or cancelled
+    pub proposer: Pubkey,
+
+    pub action: TimelockAction,
+
+    /// Earliest timestamp the action can be executed at
+    pub eta: i64,
+
+    pub bump: u8,
+}
+
+/// Changes that have to go through the timelock queue, so depositors get time to exit first
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, PartialEq, Eq, Debug)]
+pub enum TimelockAction {
+    /// Same as `Vault::set_allocation_bounds`
+    SetAllocationBounds {
+        #[max_len(MAX_STRATEGIES)]
+        bounds: Vec<WeightBounds>,
+        max_weight_change_bps: u16,
+        weight_change_cooldown: i64,
+    },
+    /// Same as `Vault::set_fees`
+    SetFees { fees: FeeConfig },
+    /// Registers a new strategy, executed with the strategy's valuation accounts as remaining
+    /// accounts, which must be the position token account and market queued here
+    AddStrategy {
+        kind: AdapterKind,
+        cap: u64,
+        position_token_account: Pubkey,
+        market: Pubkey,
+        program: Pubkey,
+    },
+    /// Hands the guardian role to `guardian`, `Pubkey::default()` revokes it
+    SetGuardian { guardian: Pubkey },
+    /// Changes the timelock delay itself
+    SetTimelockDelay { delay: i64 },
+    /// Points `compound` at another swap program, `Pubkey::default()` turns compounding off
+    SetSwapProgram { swap_program: Pubkey },
+}
@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::{ALLOCATION_SCALE, AdapterKind, error::ErrorCode};
+
+/// A lending market the vault allocates to, one entry of `Vault::strategies`
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Strategy {
+    /// Adapter used to talk to the market
+    pub kind: AdapterKind,
+
+    /// Vault's position token account in the market (Jup f-token ATA, Kamino collateral ATA...)
+    pub position_token_account: Pubkey,
+
+    /// Market state account the position is priced with (Jup `lending`, Kamino `reserve`...)
+    pub market: Pubkey,
+
+    /// Lending program the market belongs to, the only program the vault CPIs into for it
+    pub program: Pubkey,
+
+    /// Mint of the position tokens (Jup f-token mint, Kamino reserve collateral mint...)
+    pub position_mint: Pubkey,
+
+    /// Kamino lending market the reserve belongs to, unused for JupLend
+    pub lending_market: Pubkey,
+
+    /// Target share of the vault's investable value, in bps.
+    /// Weights of all strategies sum to `ALLOCATION_SCALE` once set.
+    pub target_weight_bps: u16,
+
+    /// Lowest target weight the strategy can be given while enabled, in bps
+    pub min_weight_bps: u16,
+
+    /// Highest target weight the strategy can be given, in bps
+    pub max_weight_bps: u16,
+
+    /// Most USDC the vault deploys into this strategy, `u64::MAX` for no cap
+    pub cap: u64,
+
+    /// Last recorded value (snapshot) of the position, in USDC.
+    /// Used to measure performance (gain/loss) since last update.
+    pub last_value: u64,
+
+    /// Disabled strategies take no new funds, they are drained by rebalances and withdrawals
+    /// and dropped from the registry once empty
+    pub enabled: bool,
+}
+
+/// Min and max target weight of one strategy, in bps
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WeightBounds {
+    pub min_bps: u16,
+    pub max_bps: u16,
+}
+
+impl Strategy {
+    /// Checks `accounts` start with the position token account and market the strategy was registered with
+    pub fn check_accounts(&self, accounts: &[AccountInfo]) -> Result<()> {
+        let accounts = accounts.get(..2).ok_or(ErrorCode::MissingAdapterAccounts)?;
+        require_keys_eq!(accounts[0].key(), self.position_token_account, ErrorCode::StrategyAccountMismatch);
+        require_keys_eq!(accounts[1].key(), self.market, ErrorCode::StrategyAccountMismatch);
+        Ok(())
+    }
+
+    /// Checks the position stays within the strategy's cap after funds went in
+    pub fn check_cap(&self, position_value: u64) -> Result<()> {
+        require!(position_value <= self.cap, ErrorCode::StrategyCapExceeded);
+        Ok(())
+    }
+
+    /// USDC this strategy should hold when the vault has `investable` USDC to split
+    pub fn target_value(&self, investable: u64) -> u64 {
+        if !self.enabled {
+            return 0;
+        }
+        let target = (investable as u128 * self.target_weight_bps as u128 / ALLOCATION_SCALE as u128) as u64;
+        target.min(self.cap)
+    }
+}
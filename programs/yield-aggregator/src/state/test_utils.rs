@@ -0,0 +1,88 @@
+use std::path::Path;
+
+use anchor_lang::prelude::*;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde_json::Value;
+
+use crate::EventEmitter;
+
+/// Mainnet USDC, the asset of the fixture markets
+pub const USDC_MINT: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+
+/// xorshift64*, enough to spread property test cases without pulling in a property testing crate
+pub struct Cases(pub u64);
+
+impl Cases {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Mostly realistic values, with the bounds thrown in
+    pub fn amount(&mut self) -> u64 {
+        match self.next() % 8 {
+            0 => 0,
+            1 => u64::MAX,
+            2 => self.next() % 1_000,
+            _ => self.next() % 1_000_000_000_000_000,
+        }
+    }
+}
+
+/// An account dump in the `solana account --output json` format, from `tests/fixtures`
+pub struct AccountDump {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    /// Slot `capture.sh` read the account at, `None` for the synthetic dumps
+    pub slot: Option<u64>,
+    /// u64 backed so the data is as aligned as the runtime's
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl AccountDump {
+    pub fn load(name: &str) -> Self {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+        let dump: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        let account = &dump["account"];
+        assert_eq!(account["data"][1], "base64");
+        let data = STANDARD.decode(account["data"][0].as_str().unwrap()).unwrap();
+
+        let mut words = vec![0u64; data.len().div_ceil(8)];
+        bytemuck::cast_slice_mut::<u64, u8>(&mut words)[..data.len()].copy_from_slice(&data);
+        Self {
+            key: dump["pubkey"].as_str().unwrap().parse().unwrap(),
+            owner: account["owner"].as_str().unwrap().parse().unwrap(),
+            lamports: account["lamports"].as_u64().unwrap(),
+            slot: dump["slot"].as_u64(),
+            words,
+            len: data.len(),
+        }
+    }
+
+    /// Fails tests that need the account as a program actually wrote it
+    pub fn require_capture(&self) {
+        assert!(self.slot.is_some(), "{} is a synthetic dump, replace it with `tests/fixtures/capture.sh`", self.key);
+    }
+
+    pub fn data(&mut self) -> &mut [u8] {
+        &mut bytemuck::cast_slice_mut(&mut self.words)[..self.len]
+    }
+
+    /// The dump as the runtime would hand it to the program
+    pub fn info(&mut self) -> AccountInfo<'_> {
+        let data = &mut bytemuck::cast_slice_mut(&mut self.words)[..self.len];
+        AccountInfo::new(&self.key, false, false, &mut self.lamports, data, &self.owner, false, 0)
+    }
+}
+
+/// Runs `f` with an event emitter. Off-chain the emitting self-CPI does nothing and succeeds.
+pub fn with_events<R>(f: impl FnOnce(&EventEmitter) -> R) -> R {
+    let (key, owner) = (Pubkey::new_unique(), crate::ID);
+    let (mut lamports, mut data) = (0, []);
+    let event_authority = AccountInfo::new(&key, true, false, &mut lamports, &mut data, &owner, false, 0);
+    f(&EventEmitter::new(&event_authority, 255))
+}
@@ -10,17 +10,6 @@ pub struct UserPosition {
     /// The user’s wallet address
     pub user: Pubkey,
 
-    /// The number of vault shares this user owns.
-    ///
-    /// Each "share" represents a proportional ownership of the vault’s total USDC value.
-    /// For example:
-    /// - If the vault has 10,000 USDC and total_shares = 10,000, then 1 share = 1 USDC.
-    /// - If yield increases vault value to 12,000 USDC but total_shares stays 10,000,
-    ///   then each share is now worth 1.2 USDC.
-    ///
-    /// This field is used to determine how much a user gets back on withdrawal.
-    pub shares: u64,
-
     /// The user’s yield checkpoint, equal to (shares * acc_per_share)
     /// at the time of their last deposit or withdrawal.
     ///
@@ -1,21 +1,60 @@
 use anchor_lang::prelude::*;
 
-// Global vault
+use crate::{ACC_PER_SHARE_SCALER, ALLOCATION_SCALE, Adapter, AdapterKind, EventEmitter, FeeAccrued, FeeKind, Freshness, LendingAdapter, LossEvent, MAX_DEPOSIT_FEE_BPS, MAX_MANAGEMENT_FEE_BPS, MAX_PERFORMANCE_FEE_BPS, MAX_STRATEGIES, MAX_WITHDRAW_FEE_BPS, SECONDS_PER_YEAR, SHARE_PRICE_SCALE, Strategy, VIRTUAL_ASSETS, VIRTUAL_SHARES, WeightBounds, error::ErrorCode};
+
+/// Signer seeds of a vault PDA, `[b"vault", usdc_mint, vault_index, bump]`
+#[macro_export]
+macro_rules! vault_signer_seeds {
+    ($vault:expr) => {
+        &[&[
+            b"vault".as_ref(),
+            $vault.usdc_mint.as_ref(),
+            &$vault.vault_index.to_le_bytes(),
+            &[$vault.bump],
+        ]]
+    };
+}
+
+// Global vault, PDA of `[b"vault", usdc_mint, vault_index]`
 #[account]
 #[derive(InitSpace)]
 pub struct Vault {
-    /// The admin authority that can configure or rebalance this vault
+    // Roles
+    /// The admin, configures the vault and manages the other roles. Holds every role itself.
     pub authority: Pubkey,
 
+    /// Admin proposed with `propose_authority`, becomes `authority` once it accepts.
+    /// `Pubkey::default()` when no transfer is pending.
+    pub pending_authority: Pubkey,
+
+    /// Hot key that runs rebalances and moves funds between the vault and its strategies
+    pub keeper: Pubkey,
+
+    /// Can halt the vault and unwind its strategies, but not resume it
+    pub guardian: Pubkey,
+
+    /// Sets the fees and where they are paid
+    pub fee_manager: Pubkey,
+
     /// The USDC mint accepted by this vault
     pub usdc_mint: Pubkey,
 
+    /// Tells apart vaults of the same mint, e.g. separate risk tiers
+    pub vault_index: u16,
+
     /// Vault's USDC ATA
     pub vault_usdc_ata: Pubkey,
 
+    /// SPL mint of the vault share token, PDA of `[b"share_mint", vault]`.
+    /// The vault PDA is the mint authority, shares are minted on deposit and burned on withdraw.
+    pub share_mint: Pubkey,
+
     /// Total shares issued across all users.
     /// Each user's share represents how much of the vault's total underlying they own.
     /// 1 share ~= 1 USDC initially, but as yield accrues, 1 share > 1 USDC.
+    ///
+    /// Equals the share mint supply plus the fee shares `claim_fees` has not minted yet and
+    /// the shares of migrated legacy positions `migrate_user_position` has not minted yet.
     pub total_shares: u64,
 
     /// Global accumulated yield per share (scaled by SCALER for precision).
@@ -23,33 +62,1157 @@ pub struct Vault {
     pub acc_per_share: u64,
 
     /// Total underlying USDC-equivalent value of this vault.
-    /// Includes allocations in JupLend, Kamino, and unallocated USDC sitting in the vault.
+    /// Includes the strategy positions and unallocated USDC sitting in the vault.
     pub total_underlying: u64,
 
-    
+    /// Highest share price the vault has been marked at, scaled by `SHARE_PRICE_SCALE`.
+    /// The performance fee is only charged on value above it, so holders never pay twice
+    /// for recovering a loss.
+    pub high_water_mark: u64,
+
     // Allocation Config
-    /// Amount of USDC currently deposited in JupLend
-    pub jup_lend_balance: u64,
+    /// Lending markets the vault allocates to. Instructions that price or move funds take the
+    /// accounts of every strategy, in this order, as remaining accounts.
+    #[max_len(MAX_STRATEGIES)]
+    pub strategies: Vec<Strategy>,
+
+    /// Most any strategy's target weight can move in a single update, in bps
+    pub max_weight_change_bps: u16,
+
+    /// Seconds that must pass between two updates that change the target weights
+    pub weight_change_cooldown: i64,
+
+    /// Timestamp of the last update that changed the target weights
+    pub last_weight_change_ts: i64,
+
+    /// Share of the vault's total value kept as idle USDC in the vault ATA, in bps.
+    /// Auto-allocating deposits and rebalances only deploy what exceeds this buffer.
+    pub idle_buffer_bps: u16,
+
+    // Deposit Limits
+    /// Most USDC the vault accepts in total, `u64::MAX` for no cap
+    pub deposit_cap: u64,
+
+    /// Most USDC a single user can hold in the vault, `u64::MAX` for no cap
+    pub user_deposit_cap: u64,
+
+    /// Smallest deposit the vault accepts
+    pub min_deposit: u64,
+
+    // Safety
+    /// What the vault currently allows, see `VaultStatus`
+    pub status: VaultStatus,
+
+    // Timelock
+    /// Seconds a queued risk-sensitive change waits before it can be executed
+    pub timelock_delay: i64,
+
+    /// Id of the next `QueuedAction`, part of its PDA seeds
+    pub next_action_id: u64,
+
+    /// Lowest id of a queued action that can still be executed. Actions queued before the last
+    /// change of admin or fee manager can only be cancelled.
+    pub first_valid_action_id: u64,
 
-    /// Amount of USDC currently deposited in KaminoLend
-    pub kamino_balance: u64,
+    // Fee Config
+    /// Owner of the vault shares taken as fees
+    pub fee_recipient: Pubkey,
 
-    /// Last recorded value (snapshot) of JupLend allocation.
-    /// Used to measure performance (gain/loss) since last update.
-    /// Values stored in terms of USDC
-    pub last_jup_value: u64,
+    /// Cut of the yield realized by harvest or rebalance, in bps
+    pub performance_fee_bps: u16,
 
-    /// Last recorded value (snapshot) of KaminoLend allocation.
-    /// Used to measure performance (gain/loss) since last update.
-    /// Values stored in terms of USDC
-    pub last_kamino_value: u64,
+    /// Annual fee on the vault's total value, streamed per second, in bps
+    pub management_fee_bps: u16,
 
-    /// Target allocation percentage 
-    pub jup_allocation: u16,    // e.g , 6000 = 60% -> For precision 61.34% = 6134
-    pub kamino_allocation: u16, // e.g , 4000 = 40%
+    /// Cut of the shares minted on deposit, in bps
+    pub deposit_fee_bps: u16,
+
+    /// Cut of the shares redeemed on withdraw, in bps
+    pub withdraw_fee_bps: u16,
+
+    /// Fee shares booked for `fee_recipient` that `claim_fees` has not minted yet.
+    /// Already counted in `total_shares`.
+    pub accrued_fee_shares: u64,
+
+    /// Timestamp up to which the management fee has been charged
+    pub last_fee_accrual_ts: i64,
+
+    // Compounding
+    /// Program `compound` sells reward tokens for USDC through, changed through the timelock.
+    /// `Pubkey::default()` until one is set, which leaves compounding off.
+    pub swap_program: Pubkey,
 
     /// Timestamp of the last yield update or rebalance action
     pub last_update_ts: i64,
 
     pub bump: u8,
 }
+
+/// Operating mode of a vault, each one more restrictive than the previous
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum VaultStatus {
+    /// Everything is allowed
+    Active,
+    /// No new deposits, funds still move between strategies
+    DepositsPaused,
+    /// No deposits and nothing deployed into strategies, users withdraw as usual
+    WithdrawOnly,
+    /// Strategies are off limits, users withdraw their pro-rata share of the idle USDC
+    Shutdown,
+}
+
+impl VaultStatus {
+    pub fn accepts_deposits(&self) -> bool {
+        *self == VaultStatus::Active
+    }
+
+    pub fn allows_allocations(&self) -> bool {
+        *self <= VaultStatus::DepositsPaused
+    }
+}
+
+/// The four vault fees, in bps
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FeeConfig {
+    pub performance_fee_bps: u16,
+    pub management_fee_bps: u16,
+    pub deposit_fee_bps: u16,
+    pub withdraw_fee_bps: u16,
+}
+
+/// Delegated permissions of a vault, the admin (`Vault::authority`) holds all of them
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    /// Rebalances and moves funds between the vault and its strategies
+    Keeper,
+    /// Halts the vault and unwinds its strategies
+    Guardian,
+    /// Sets the fees and the fee recipient
+    FeeManager,
+}
+
+impl Vault {
+    /// Share tokens in circulation can never exceed the shares accounted for by the vault.
+    /// The two only differ by fee shares that have not been claimed yet and by the shares
+    /// booked for legacy positions that have not been migrated yet.
+    pub fn check_share_supply(&self, share_supply: u64) -> Result<()> {
+        require!(share_supply <= self.total_shares, ErrorCode::ShareSupplyMismatch);
+        Ok(())
+    }
+
+    /// Whether `compound` may sell tokens of `mint` as rewards. USDC,
+    /// the share mint and the strategies' position tokens are the vault's own capital, not rewards.
+    pub fn is_reward_mint(&self, mint: &Pubkey) -> bool {
+        *mint != self.usdc_mint
+            && *mint != self.share_mint
+            && !self.strategies.iter().any(|strategy| strategy.position_mint == *mint)
+    }
+
+    /// USDC that should stay idle in the vault ATA when the vault is worth `total_value`
+    pub fn idle_buffer_target(&self, total_value: u64) -> u64 {
+        (total_value as u128 * self.idle_buffer_bps as u128 / ALLOCATION_SCALE as u128) as u64
+    }
+
+    /// Moves the snapshot of strategy `index` by the value an allocation added or removed,
+    /// so `harvest` only sees yield and not capital movements.
+    pub fn track_position(&mut self, index: usize, value_before: u64, value_after: u64) {
+        let strategy = &mut self.strategies[index];
+        strategy.last_value = apply_change(strategy.last_value, value_before, value_after);
+    }
+
+    /// Index of the `kind` strategy holding `position_token_account` in `market`
+    pub fn find_strategy(&self, kind: AdapterKind, position_token_account: &Pubkey, market: &Pubkey) -> Result<usize> {
+        self.strategies
+            .iter()
+            .position(|strategy| {
+                strategy.kind == kind
+                    && strategy.position_token_account == *position_token_account
+                    && strategy.market == *market
+            })
+            .ok_or(ErrorCode::StrategyNotFound.into())
+    }
+
+    /// Sets the target weight of every strategy, in registry order. Weights must sum to
+    /// `ALLOCATION_SCALE`, stay within each strategy's bounds and move by at most
+    /// `max_weight_change_bps`, and disabled strategies can't be given any. Passing the current
+    /// weights is always allowed, only an actual change starts the cooldown.
+    pub fn set_weights(&mut self, weights: &[u16], now: i64) -> Result<()> {
+        require!(weights.len() == self.strategies.len(), ErrorCode::InvalidAllocation);
+        let mut sum: u32 = 0;
+        let mut changed = false;
+        for (strategy, weight) in self.strategies.iter().zip(weights) {
+            if strategy.enabled {
+                require!(
+                    (strategy.min_weight_bps..=strategy.max_weight_bps).contains(weight),
+                    ErrorCode::AllocationOutOfBounds
+                );
+            } else {
+                require!(*weight == 0, ErrorCode::StrategyDisabled);
+            }
+            require!(
+                strategy.target_weight_bps.abs_diff(*weight) <= self.max_weight_change_bps,
+                ErrorCode::AllocationChangeTooLarge
+            );
+            changed |= strategy.target_weight_bps != *weight;
+            sum += *weight as u32;
+        }
+        require!(sum == ALLOCATION_SCALE as u32, ErrorCode::InvalidAllocation);
+
+        if !changed {
+            return Ok(());
+        }
+        let next_change_ts = self.last_weight_change_ts.saturating_add(self.weight_change_cooldown);
+        require!(now >= next_change_ts, ErrorCode::AllocationCooldownActive);
+
+        for (strategy, weight) in self.strategies.iter_mut().zip(weights) {
+            strategy.target_weight_bps = *weight;
+        }
+        self.last_weight_change_ts = now;
+        Ok(())
+    }
+
+    /// Sets the weight bounds of every strategy, in registry order, and the limits on how fast
+    /// weights can move. The bounds of the enabled strategies must leave room for weights
+    /// summing to `ALLOCATION_SCALE`.
+    pub fn set_allocation_bounds(&mut self, bounds: &[WeightBounds], max_weight_change_bps: u16, weight_change_cooldown: i64) -> Result<()> {
+        require!(bounds.len() == self.strategies.len(), ErrorCode::InvalidAllocationBounds);
+        require!(
+            max_weight_change_bps > 0 && max_weight_change_bps <= ALLOCATION_SCALE && weight_change_cooldown >= 0,
+            ErrorCode::InvalidAllocationBounds
+        );
+
+        let (mut min_sum, mut max_sum) = (0u32, 0u32);
+        for (strategy, bound) in self.strategies.iter().zip(bounds) {
+            require!(bound.min_bps <= bound.max_bps && bound.max_bps <= ALLOCATION_SCALE, ErrorCode::InvalidAllocationBounds);
+            if strategy.enabled {
+                min_sum += bound.min_bps as u32;
+                max_sum += bound.max_bps as u32;
+            }
+        }
+        require!(
+            min_sum <= ALLOCATION_SCALE as u32 && max_sum >= ALLOCATION_SCALE as u32,
+            ErrorCode::InvalidAllocationBounds
+        );
+
+        for (strategy, bound) in self.strategies.iter_mut().zip(bounds) {
+            strategy.min_weight_bps = bound.min_bps;
+            strategy.max_weight_bps = bound.max_bps;
+        }
+        self.max_weight_change_bps = max_weight_change_bps;
+        self.weight_change_cooldown = weight_change_cooldown;
+        Ok(())
+    }
+
+    /// Registers a `kind` market with no target weight, weights are handed out with `set_strategy_weight`.
+    /// `accounts` are the strategy's valuation accounts: the vault's position token account and the market,
+    /// which has to belong to `program`.
+    pub fn add_strategy(&mut self, vault: Pubkey, kind: AdapterKind, cap: u64, program: Pubkey, accounts: &[AccountInfo]) -> Result<()> {
+        require!(self.strategies.len() < MAX_STRATEGIES, ErrorCode::TooManyStrategies);
+        let accounts = accounts.get(..2).ok_or(ErrorCode::MissingAdapterAccounts)?;
+        let (position_token_account, market) = (accounts[0].key(), accounts[1].key());
+        require!(
+            !self.strategies.iter().any(|strategy| strategy.market == market
+                || strategy.position_token_account == position_token_account),
+            ErrorCode::StrategyAlreadyExists
+        );
+
+        // The market's program and position mint, and Kamino's lending market, are recorded for
+        // every later instruction to check the strategy's accounts against
+        let market_accounts = Adapter::market_accounts(kind, &accounts[1])?;
+        require_keys_eq!(market_accounts.program, program, ErrorCode::StrategyAccountMismatch);
+        require_keys_eq!(market_accounts.asset_mint, self.usdc_mint, ErrorCode::InvalidLendingAccount);
+        let strategy = Strategy {
+            kind,
+            position_token_account,
+            market,
+            program: market_accounts.program,
+            position_mint: market_accounts.position_mint,
+            lending_market: market_accounts.lending_market,
+            target_weight_bps: 0,
+            min_weight_bps: 0,
+            max_weight_bps: ALLOCATION_SCALE,
+            cap,
+            last_value: 0,
+            enabled: true,
+        };
+
+        // Loading the adapter checks the position is the vault's and holds the market's position token
+        Adapter::load(&strategy, vault, accounts, Freshness::Recent)?;
+        self.strategies.push(strategy);
+        Ok(())
+    }
+
+    /// Retires strategy `index`, which must have no target weight, given the USDC its position is
+    /// redeemable for. A position still worth something only disables the strategy, so deposits
+    /// skip it while rebalances and withdrawals drain it. Once it is worth nothing, dust or donated
+    /// position tokens included, the strategy is dropped and whatever its snapshot still held is
+    /// written off as a loss. Returns whether the strategy was dropped.
+    pub fn remove_strategy(&mut self, vault: Pubkey, index: usize, position_value: u64, now: i64, events: &EventEmitter) -> Result<bool> {
+        let strategy = self.strategies.get_mut(index).ok_or(ErrorCode::StrategyNotFound)?;
+        require!(strategy.target_weight_bps == 0, ErrorCode::StrategyNotEmpty);
+        if position_value > 0 {
+            require!(strategy.enabled, ErrorCode::StrategyNotEmpty);
+            strategy.enabled = false;
+            return Ok(false);
+        }
+
+        let share_price_before = self.share_price(self.total_underlying);
+        let strategy_values_before: Vec<u64> = self.strategies.iter().map(|strategy| strategy.last_value).collect();
+        let loss = self.strategies.remove(index).last_value;
+        if loss > 0 {
+            let mut strategy_values_after = strategy_values_before.clone();
+            strategy_values_after[index] = 0;
+            self.total_underlying = self.total_underlying.saturating_sub(loss);
+            events.emit(LossEvent {
+                vault,
+                loss,
+                strategy_values_before,
+                strategy_values_after,
+                share_price_before,
+                share_price_after: self.share_price(self.total_underlying),
+                high_water_mark: self.high_water_mark,
+                total_underlying: self.total_underlying,
+                total_shares: self.total_shares,
+                timestamp: now,
+            })?;
+        }
+        Ok(true)
+    }
+
+    /// Sets all four fees, each within its hard cap. The management fee owed so far is charged
+    /// at the old rate first, priced at the last booked vault value.
+    pub fn set_fees(&mut self, vault: Pubkey, fees: &FeeConfig, now: i64, events: &EventEmitter) -> Result<()> {
+        require!(fees.performance_fee_bps <= MAX_PERFORMANCE_FEE_BPS, ErrorCode::FeeTooHigh);
+        require!(fees.management_fee_bps <= MAX_MANAGEMENT_FEE_BPS, ErrorCode::FeeTooHigh);
+        require!(fees.deposit_fee_bps <= MAX_DEPOSIT_FEE_BPS, ErrorCode::FeeTooHigh);
+        require!(fees.withdraw_fee_bps <= MAX_WITHDRAW_FEE_BPS, ErrorCode::FeeTooHigh);
+
+        let pending = self.pending_fees(0, self.total_underlying, now)?;
+        self.book_fees(vault, &pending, now, events)?;
+
+        self.performance_fee_bps = fees.performance_fee_bps;
+        self.management_fee_bps = fees.management_fee_bps;
+        self.deposit_fee_bps = fees.deposit_fee_bps;
+        self.withdraw_fee_bps = fees.withdraw_fee_bps;
+        Ok(())
+    }
+
+    /// Key assigned to `role`, `Pubkey::default()` when it is unassigned
+    pub fn role_holder(&self, role: Role) -> Pubkey {
+        match role {
+            Role::Keeper => self.keeper,
+            Role::Guardian => self.guardian,
+            Role::FeeManager => self.fee_manager,
+        }
+    }
+
+    /// Whether `key` may act as `role`. The admin holds every role.
+    pub fn has_role(&self, key: Pubkey, role: Role) -> bool {
+        key == self.authority || (key == self.role_holder(role) && key != Pubkey::default())
+    }
+
+    /// Assigns `role` to `key`, revoking it from its previous holder. A new fee manager drops
+    /// every action queued so far, its predecessor may have queued some of them.
+    pub fn set_role(&mut self, role: Role, key: Pubkey) {
+        match role {
+            Role::Keeper => self.keeper = key,
+            Role::Guardian => self.guardian = key,
+            Role::FeeManager => {
+                if self.fee_manager != key {
+                    self.invalidate_queued_actions();
+                }
+                self.fee_manager = key;
+            }
+        }
+    }
+
+    /// Hands the vault to a new admin. Actions the previous one queued can no longer be executed.
+    pub fn set_authority(&mut self, authority: Pubkey) {
+        self.authority = authority;
+        self.pending_authority = Pubkey::default();
+        self.invalidate_queued_actions();
+    }
+
+    /// Leaves every action queued so far to be cancelled, they were queued under other roles
+    fn invalidate_queued_actions(&mut self) {
+        self.first_valid_action_id = self.next_action_id;
+    }
+
+    /// Whether queued action `id` can still be executed, see `first_valid_action_id`
+    pub fn is_action_valid(&self, id: u64) -> bool {
+        id >= self.first_valid_action_id
+    }
+
+    /// Moves the vault to `status`. The guardian can only make the vault more restrictive,
+    /// the admin can move it anywhere.
+    pub fn set_status(&mut self, signer: Pubkey, status: VaultStatus) -> Result<()> {
+        if signer != self.authority {
+            require!(self.has_role(signer, Role::Guardian), ErrorCode::Unauthorized);
+            require!(status >= self.status, ErrorCode::GuardianCannotResume);
+        }
+        self.status = status;
+        Ok(())
+    }
+
+    /// Sets the TVL cap, the per-user cap and the minimum deposit
+    pub fn set_deposit_limits(&mut self, deposit_cap: u64, user_deposit_cap: u64, min_deposit: u64) -> Result<()> {
+        require!(min_deposit <= deposit_cap && min_deposit <= user_deposit_cap, ErrorCode::InvalidDepositLimits);
+        self.deposit_cap = deposit_cap;
+        self.user_deposit_cap = user_deposit_cap;
+        self.min_deposit = min_deposit;
+        Ok(())
+    }
+
+    /// Checks a deposit of `amount` against the deposit limits, `user_assets` is what the user
+    /// already holds in a vault worth `total_assets`
+    pub fn check_deposit(&self, amount: u64, user_assets: u64, total_assets: u64) -> Result<()> {
+        require!(self.status.accepts_deposits(), ErrorCode::DepositsPaused);
+        require!(amount > 0, ErrorCode::ZeroAmount);
+        require!(amount >= self.min_deposit, ErrorCode::DepositTooSmall);
+        require!(amount <= headroom(self.deposit_cap, total_assets), ErrorCode::VaultCapExceeded);
+        require!(amount <= headroom(self.user_deposit_cap, user_assets), ErrorCode::UserCapExceeded);
+        Ok(())
+    }
+
+    /// Largest deposit a user holding `user_assets` can make in a vault worth `total_assets`,
+    /// 0 when what is left is below the minimum deposit
+    pub fn max_deposit(&self, user_assets: u64, total_assets: u64) -> u64 {
+        if !self.status.accepts_deposits() {
+            return 0;
+        }
+        let max = headroom(self.deposit_cap, total_assets).min(headroom(self.user_deposit_cap, user_assets));
+        if max < self.min_deposit.max(1) {
+            0
+        } else {
+            max
+        }
+    }
+
+    /// USDC each strategy should hold when the vault has `investable` USDC to split
+    pub fn strategy_targets(&self, investable: u64) -> Vec<u64> {
+        self.strategies.iter().map(|strategy| strategy.target_value(investable)).collect()
+    }
+
+    /// `total_shares` plus the vault's virtual shares, what every share conversion divides by
+    fn priced_shares(&self) -> u128 {
+        self.total_shares as u128 + VIRTUAL_SHARES as u128
+    }
+
+    /// Shares minted for `assets` when the vault holds `total_assets`, rounded down.
+    /// The first deposit into an empty vault mints 1 share per USDC.
+    pub fn convert_to_shares(&self, assets: u64, total_assets: u64) -> Result<u64> {
+        let shares = (assets as u128)
+            .checked_mul(self.priced_shares())
+            .and_then(|v| v.checked_div(priced_assets(total_assets)))
+            .ok_or(ErrorCode::MathOverflow)?;
+        u64::try_from(shares).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// USDC one share is worth when the vault holds `total_assets`, scaled by `SHARE_PRICE_SCALE`.
+    /// An empty vault is priced 1:1, like its first deposit.
+    pub fn share_price(&self, total_assets: u64) -> u64 {
+        (priced_assets(total_assets) * SHARE_PRICE_SCALE / self.priced_shares()).min(u64::MAX as u128) as u64
+    }
+
+    /// Assets `shares` are worth when the vault holds `total_assets`, rounded down.
+    /// Never more than `total_assets`, the virtual assets cannot be paid out.
+    pub fn convert_to_assets(&self, shares: u64, total_assets: u64) -> Result<u64> {
+        let assets = (shares as u128)
+            .checked_mul(priced_assets(total_assets))
+            .and_then(|v| v.checked_div(self.priced_shares()))
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(assets.min(total_assets as u128) as u64)
+    }
+
+    /// Shares backing `assets` when the vault holds `total_assets`, rounded up
+    /// so the vault never pays out more than it burns
+    pub fn assets_to_shares_rounded_up(&self, assets: u64, total_assets: u64) -> Result<u64> {
+        let shares = (assets as u128)
+            .checked_mul(self.priced_shares())
+            .ok_or(ErrorCode::MathOverflow)?
+            .div_ceil(priced_assets(total_assets));
+        u64::try_from(shares).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// Gain or loss of all strategy positions since the last snapshot, negative on a loss
+    pub fn unrealized_yield(&self, valuation: &VaultValuation) -> i128 {
+        self.strategies
+            .iter()
+            .zip(&valuation.positions)
+            .map(|(strategy, value)| *value as i128 - strategy.last_value as i128)
+            .sum()
+    }
+
+    /// Books the gain or loss of all strategy positions since the last snapshot, charges the fees owed
+    /// on it up to `now` and moves the snapshots to `valuation`. Returns the realized yield, negative
+    /// on a loss. `vault` is the address of this vault, for the fee and loss events.
+    ///
+    /// A loss is written off `total_underlying` without touching `total_shares`, so the share price
+    /// drops by the same ratio for every holder and whoever exits next cannot dodge it.
+    pub fn mark_to_market(&mut self, vault: Pubkey, valuation: &VaultValuation, now: i64, events: &EventEmitter) -> Result<i128> {
+        let realized_yield = self.unrealized_yield(valuation);
+        let fees = self.pending_fees(realized_yield, valuation.total()?, now)?;
+        let share_price_before = self.share_price(self.total_underlying);
+        let snapshots: Vec<u64> = self.strategies.iter().map(|strategy| strategy.last_value).collect();
+
+        if realized_yield >= 0 {
+            let yield_amount = u64::try_from(realized_yield).map_err(|_| ErrorCode::MathOverflow)?;
+            self.accrue_yield_after_fee(yield_amount, fees.performance_fee)?;
+        }
+        self.book_fees(vault, &fees, now, events)?;
+
+        for (strategy, value) in self.strategies.iter_mut().zip(&valuation.positions) {
+            strategy.last_value = *value;
+        }
+        self.last_update_ts = now;
+
+        if realized_yield < 0 {
+            // The snapshots are part of `total_underlying`, so the loss can only exceed it if the books
+            // already drifted
+            let loss = u64::try_from(-realized_yield).map_err(|_| ErrorCode::MathOverflow)?;
+            self.total_underlying = self.total_underlying.saturating_sub(loss);
+            events.emit(LossEvent {
+                vault,
+                loss,
+                strategy_values_before: snapshots,
+                strategy_values_after: valuation.positions.clone(),
+                share_price_before,
+                share_price_after: self.share_price(self.total_underlying),
+                high_water_mark: self.high_water_mark,
+                total_underlying: self.total_underlying,
+                total_shares: self.total_shares,
+                timestamp: now,
+            })?;
+        }
+        self.update_high_water_mark();
+
+        Ok(realized_yield)
+    }
+
+    /// Books `proceeds` of USDC the vault earned outside its strategy positions, such as sold
+    /// reward tokens, as realized yield and charges the fees owed on it. `total_assets` is the
+    /// vault value with the proceeds in. Returns the performance fee taken.
+    pub fn book_external_yield(&mut self, vault: Pubkey, proceeds: u64, total_assets: u64, now: i64, events: &EventEmitter) -> Result<u64> {
+        let fees = self.pending_fees(proceeds as i128, total_assets, now)?;
+        self.accrue_yield_after_fee(proceeds, fees.performance_fee)?;
+        self.book_fees(vault, &fees, now, events)?;
+        self.update_high_water_mark();
+        Ok(fees.performance_fee)
+    }
+
+    /// Holders only earn the yield left after the performance fee, the fee shares own the rest
+    fn accrue_yield_after_fee(&mut self, yield_amount: u64, performance_fee: u64) -> Result<()> {
+        self.accrue_yield(yield_amount - performance_fee)?;
+        self.total_underlying = self.total_underlying.checked_add(performance_fee).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Raises the high-water mark to the share price of the booked `total_underlying`. An emptied
+    /// vault starts over at the price its next first deposit gets.
+    pub fn update_high_water_mark(&mut self) {
+        self.high_water_mark = if self.total_shares == 0 {
+            SHARE_PRICE_SCALE as u64
+        } else {
+            self.high_water_mark.max(self.share_price(self.total_underlying))
+        };
+    }
+
+    /// Part of `realized_yield` that takes the booked `total_underlying`, with the yield added, above
+    /// the high-water mark. Gains that only win back an earlier loss are not charged a performance
+    /// fee. Measured on the same booked value `update_high_water_mark` prices the mark from, so
+    /// USDC sent to the vault outside its books is never charged as yield.
+    pub fn yield_above_high_water_mark(&self, realized_yield: i128) -> u64 {
+        if realized_yield <= 0 {
+            return 0;
+        }
+        let peak_assets = (self.high_water_mark as u128 * self.priced_shares()).div_ceil(SHARE_PRICE_SCALE);
+        let above_peak = (priced_assets(self.total_underlying) + realized_yield as u128).saturating_sub(peak_assets);
+        above_peak.min(realized_yield as u128) as u64
+    }
+
+    /// Management fee owed since `last_fee_accrual_ts` and performance fee owed on `realized_yield`,
+    /// with the shares paying for them when the vault is worth `total_assets`
+    pub fn pending_fees(&self, realized_yield: i128, total_assets: u64, now: i64) -> Result<PendingFees> {
+        let elapsed = now.saturating_sub(self.last_fee_accrual_ts).max(0) as u128;
+        let management_fee = (total_assets as u128)
+            .checked_mul(self.management_fee_bps as u128)
+            .and_then(|v| v.checked_mul(elapsed))
+            .ok_or(ErrorCode::MathOverflow)?
+            / (ALLOCATION_SCALE as u128 * SECONDS_PER_YEAR as u128);
+        let management_fee = u64::try_from(management_fee).map_err(|_| ErrorCode::MathOverflow)?;
+
+        let chargeable_yield = self.yield_above_high_water_mark(realized_yield);
+        let performance_fee = (chargeable_yield as u128 * self.performance_fee_bps as u128 / ALLOCATION_SCALE as u128) as u64;
+
+        let total_fee = management_fee.checked_add(performance_fee).ok_or(ErrorCode::MathOverflow)?;
+        let fee_shares = self.fee_to_shares(total_fee, total_assets)?;
+        let management_shares = if total_fee == 0 {
+            0
+        } else {
+            (fee_shares as u128 * management_fee as u128 / total_fee as u128) as u64
+        };
+
+        Ok(PendingFees {
+            management_fee,
+            management_shares,
+            performance_fee,
+            performance_shares: fee_shares - management_shares,
+        })
+    }
+
+    /// Books the management and performance fee shares and restarts the management fee clock
+    pub fn book_fees(&mut self, vault: Pubkey, fees: &PendingFees, now: i64, events: &EventEmitter) -> Result<()> {
+        self.book_fee_shares(vault, FeeKind::Management, fees.management_fee, fees.management_shares, now, events)?;
+        self.book_fee_shares(vault, FeeKind::Performance, fees.performance_fee, fees.performance_shares, now, events)?;
+        self.last_fee_accrual_ts = now;
+        Ok(())
+    }
+
+    /// Shares that are worth `fee` once issued, so the existing holders pay exactly `fee` through dilution
+    fn fee_to_shares(&self, fee: u64, total_assets: u64) -> Result<u64> {
+        if fee == 0 || self.total_shares == 0 || fee >= total_assets {
+            return Ok(0);
+        }
+        let shares = (fee as u128)
+            .checked_mul(self.priced_shares())
+            .and_then(|v| v.checked_div(priced_assets(total_assets) - fee as u128))
+            .ok_or(ErrorCode::MathOverflow)?;
+        u64::try_from(shares).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// Issues `fee_shares` to the fee recipient. They count towards `total_shares` right away
+    /// and are minted as share tokens by `claim_fees`, so no USDC leaves the vault.
+    pub fn book_fee_shares(&mut self, vault: Pubkey, kind: FeeKind, fee_assets: u64, fee_shares: u64, now: i64, events: &EventEmitter) -> Result<()> {
+        if fee_shares == 0 {
+            return Ok(());
+        }
+        self.total_shares = self.total_shares.checked_add(fee_shares).ok_or(ErrorCode::MathOverflow)?;
+        self.accrued_fee_shares = self.accrued_fee_shares.checked_add(fee_shares).ok_or(ErrorCode::MathOverflow)?;
+
+        events.emit(FeeAccrued {
+            vault,
+            kind,
+            fee_assets,
+            fee_shares,
+            fee_recipient: self.fee_recipient,
+            timestamp: now,
+        })
+    }
+
+    /// Deposit fee taken from `shares` minted for a deposit, rounded up
+    pub fn deposit_fee_shares(&self, shares: u64) -> u64 {
+        bps_rounded_up(shares, self.deposit_fee_bps)
+    }
+
+    /// Withdraw fee taken from `shares` redeemed, rounded up
+    pub fn withdraw_fee_shares(&self, shares: u64) -> u64 {
+        bps_rounded_up(shares, self.withdraw_fee_bps)
+    }
+
+    /// Shares to redeem so that `net_shares` are left once the withdraw fee is taken, rounded up
+    pub fn shares_with_withdraw_fee(&self, net_shares: u64) -> Result<u64> {
+        let keep_bps = (ALLOCATION_SCALE - self.withdraw_fee_bps) as u128;
+        let shares = (net_shares as u128)
+            .checked_mul(ALLOCATION_SCALE as u128)
+            .and_then(|v| v.checked_add(keep_bps - 1))
+            .ok_or(ErrorCode::MathOverflow)?
+            / keep_bps;
+        u64::try_from(shares).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// Books `yield_amount` of realized yield: grows `total_underlying` and spreads it over all shares
+    pub fn accrue_yield(&mut self, yield_amount: u64) -> Result<()> {
+        if yield_amount > 0 && self.total_shares > 0 {
+            let acc_increment = (yield_amount as u128)
+                .checked_mul(ACC_PER_SHARE_SCALER)
+                .and_then(|v| v.checked_div(self.total_shares as u128))
+                .ok_or(ErrorCode::MathOverflow)?;
+            let acc_per_share = (self.acc_per_share as u128)
+                .checked_add(acc_increment)
+                .ok_or(ErrorCode::MathOverflow)?;
+            self.acc_per_share = u64::try_from(acc_per_share).map_err(|_| ErrorCode::MathOverflow)?;
+        }
+        self.total_underlying = self.total_underlying.checked_add(yield_amount).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+fn bps_rounded_up(amount: u64, bps: u16) -> u64 {
+    ((amount as u128 * bps as u128).div_ceil(ALLOCATION_SCALE as u128)) as u64
+}
+
+/// `total_assets` plus the virtual assets every vault is priced with
+fn priced_assets(total_assets: u64) -> u128 {
+    total_assets as u128 + VIRTUAL_ASSETS as u128
+}
+
+/// Room left under `cap` once `used` is taken, `u64::MAX` caps are unlimited
+fn headroom(cap: u64, used: u64) -> u64 {
+    if cap == u64::MAX {
+        u64::MAX
+    } else {
+        cap.saturating_sub(used)
+    }
+}
+
+fn apply_change(value: u64, before: u64, after: u64) -> u64 {
+    if after >= before {
+        value.saturating_add(after - before)
+    } else {
+        value.saturating_sub(before - after)
+    }
+}
+
+/// Fees owed by the vault holders, see `Vault::pending_fees`
+pub struct PendingFees {
+    pub management_fee: u64,
+    pub management_shares: u64,
+    pub performance_fee: u64,
+    pub performance_shares: u64,
+}
+
+/// Live USDC value of everything the vault holds, priced with the protocols' own exchange rates
+pub struct VaultValuation {
+    pub idle: u64,
+    /// Value of each strategy position, in registry order
+    pub positions: Vec<u64>,
+}
+
+impl VaultValuation {
+    /// Prices the vault's positions through the lending adapters, which already checked
+    /// the position accounts belong to the vault and match their strategy
+    pub fn load<'info, A: LendingAdapter<'info>>(idle: u64, adapters: &[A]) -> Result<Self> {
+        Ok(Self {
+            idle,
+            positions: adapters.iter().map(|adapter| adapter.position_value()).collect::<Result<_>>()?,
+        })
+    }
+
+    pub fn total(&self) -> Result<u64> {
+        self.positions
+            .iter()
+            .try_fold(self.idle, |total, value| total.checked_add(*value))
+            .ok_or(ErrorCode::MathOverflow.into())
+    }
+
+    /// Decides where a withdrawal of `amount` USDC is sourced from:
+    /// idle USDC first, then the strategies over their target weight (most over-weight first),
+    /// and only then a proportional split by target weight.
+    pub fn plan_withdrawal(&self, vault: &Vault, amount: u64) -> Result<WithdrawalPlan> {
+        let total = self.total()?;
+        require!(amount <= total, ErrorCode::InsufficientLiquidity);
+
+        let from_idle = amount.min(self.idle);
+        let mut remaining = amount - from_idle;
+        let mut from_strategies = vec![0u64; self.positions.len()];
+
+        if remaining > 0 {
+            // Weights are measured against what the vault holds once this withdrawal is done
+            let total_after = total - amount;
+            let investable = total_after.saturating_sub(vault.idle_buffer_target(total_after));
+            let excess: Vec<u64> = vault
+                .strategy_targets(investable)
+                .iter()
+                .zip(&self.positions)
+                .map(|(target, value)| value.saturating_sub(*target))
+                .collect();
+
+            // Stable sort, ties keep registry order
+            let mut order: Vec<usize> = (0..excess.len()).collect();
+            order.sort_by_key(|&i| std::cmp::Reverse(excess[i]));
+            for i in order {
+                let take = remaining.min(excess[i]);
+                from_strategies[i] += take;
+                remaining -= take;
+            }
+        }
+
+        if remaining > 0 {
+            let shortfall = remaining;
+            for (i, strategy) in vault.strategies.iter().enumerate() {
+                let share = (shortfall as u128 * strategy.target_weight_bps as u128 / ALLOCATION_SCALE as u128) as u64;
+                let take = share.min(remaining).min(self.positions[i] - from_strategies[i]);
+                from_strategies[i] += take;
+                remaining -= take;
+            }
+
+            // Whatever one strategy could not cover comes from the others
+            for (i, value) in self.positions.iter().enumerate() {
+                let take = remaining.min(value - from_strategies[i]);
+                from_strategies[i] += take;
+                remaining -= take;
+            }
+        }
+
+        require!(remaining == 0, ErrorCode::InsufficientLiquidity);
+
+        Ok(WithdrawalPlan { from_idle, from_strategies })
+    }
+}
+
+/// USDC to take from each source for a withdrawal, see `VaultValuation::plan_withdrawal`
+pub struct WithdrawalPlan {
+    pub from_idle: u64,
+    /// USDC to redeem from each strategy, in registry order
+    pub from_strategies: Vec<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_utils::{Cases, with_events};
+
+    fn strategy(target_weight_bps: u16) -> Strategy {
+        Strategy {
+            kind: AdapterKind::JupLend,
+            position_token_account: Pubkey::new_unique(),
+            market: Pubkey::new_unique(),
+            program: Pubkey::new_unique(),
+            position_mint: Pubkey::new_unique(),
+            lending_market: Pubkey::default(),
+            target_weight_bps,
+            min_weight_bps: 0,
+            max_weight_bps: ALLOCATION_SCALE,
+            cap: u64::MAX,
+            last_value: 0,
+            enabled: true,
+        }
+    }
+
+    /// An empty vault with no fees or limits, one strategy per weight
+    fn vault(weights: &[u16]) -> Vault {
+        Vault {
+            authority: Pubkey::new_unique(),
+            pending_authority: Pubkey::default(),
+            keeper: Pubkey::default(),
+            guardian: Pubkey::default(),
+            fee_manager: Pubkey::default(),
+            usdc_mint: Pubkey::new_unique(),
+            vault_index: 0,
+            vault_usdc_ata: Pubkey::new_unique(),
+            share_mint: Pubkey::new_unique(),
+            total_shares: 0,
+            acc_per_share: 0,
+            total_underlying: 0,
+            high_water_mark: SHARE_PRICE_SCALE as u64,
+            strategies: weights.iter().map(|weight| strategy(*weight)).collect(),
+            max_weight_change_bps: ALLOCATION_SCALE,
+            weight_change_cooldown: 0,
+            last_weight_change_ts: 0,
+            idle_buffer_bps: 0,
+            deposit_cap: u64::MAX,
+            user_deposit_cap: u64::MAX,
+            min_deposit: 0,
+            status: VaultStatus::Active,
+            timelock_delay: 0,
+            next_action_id: 0,
+            first_valid_action_id: 0,
+            fee_recipient: Pubkey::new_unique(),
+            performance_fee_bps: 0,
+            management_fee_bps: 0,
+            deposit_fee_bps: 0,
+            withdraw_fee_bps: 0,
+            accrued_fee_shares: 0,
+            last_fee_accrual_ts: 0,
+            swap_program: Pubkey::default(),
+            last_update_ts: 0,
+            bump: 255,
+        }
+    }
+
+    fn bounds(bounds: &[(u16, u16)]) -> Vec<WeightBounds> {
+        bounds.iter().map(|&(min_bps, max_bps)| WeightBounds { min_bps, max_bps }).collect()
+    }
+
+    fn weights(vault: &Vault) -> Vec<u16> {
+        vault.strategies.iter().map(|strategy| strategy.target_weight_bps).collect()
+    }
+
+    #[test]
+    fn weights_must_sum_to_the_scale_within_bounds() {
+        let mut vault = vault(&[5_000, 5_000]);
+        vault.set_weights(&[6_000, 4_000], 0).unwrap();
+        assert_eq!(weights(&vault), [6_000, 4_000]);
+
+        assert_eq!(vault.set_weights(&[6_000, 3_000], 0).unwrap_err(), ErrorCode::InvalidAllocation.into());
+        assert_eq!(vault.set_weights(&[10_000], 0).unwrap_err(), ErrorCode::InvalidAllocation.into());
+
+        vault.strategies[0].max_weight_bps = 7_000;
+        assert_eq!(vault.set_weights(&[8_000, 2_000], 0).unwrap_err(), ErrorCode::AllocationOutOfBounds.into());
+        vault.strategies[1].min_weight_bps = 4_500;
+        assert_eq!(vault.set_weights(&[7_000, 3_000], 0).unwrap_err(), ErrorCode::AllocationOutOfBounds.into());
+
+        // Disabled strategies can only be given nothing, whatever their bounds
+        vault.strategies[1].enabled = false;
+        vault.strategies[0].max_weight_bps = ALLOCATION_SCALE;
+        assert_eq!(vault.set_weights(&[6_000, 4_000], 0).unwrap_err(), ErrorCode::StrategyDisabled.into());
+        vault.set_weights(&[10_000, 0], 0).unwrap();
+        assert_eq!(weights(&vault), [10_000, 0]);
+    }
+
+    #[test]
+    fn weights_move_by_at_most_the_max_change_once_per_cooldown() {
+        let mut vault = vault(&[5_000, 5_000]);
+        vault.max_weight_change_bps = 2_000;
+        vault.weight_change_cooldown = 3_600;
+
+        assert_eq!(vault.set_weights(&[7_001, 2_999], 3_600).unwrap_err(), ErrorCode::AllocationChangeTooLarge.into());
+        vault.set_weights(&[7_000, 3_000], 3_600).unwrap();
+        assert_eq!(vault.last_weight_change_ts, 3_600);
+
+        assert_eq!(vault.set_weights(&[6_000, 4_000], 7_199).unwrap_err(), ErrorCode::AllocationCooldownActive.into());
+        // Passing the current weights is not a change and doesn't restart the cooldown
+        vault.set_weights(&[7_000, 3_000], 7_199).unwrap();
+        assert_eq!(vault.last_weight_change_ts, 3_600);
+        vault.set_weights(&[6_000, 4_000], 7_200).unwrap();
+        assert_eq!(weights(&vault), [6_000, 4_000]);
+    }
+
+    #[test]
+    fn allocation_bounds_must_leave_room_for_a_full_allocation() {
+        let mut vault = vault(&[5_000, 5_000]);
+        let invalid = ErrorCode::InvalidAllocationBounds.into();
+
+        assert_eq!(vault.set_allocation_bounds(&bounds(&[(0, 10_000)]), 1_000, 0).unwrap_err(), invalid);
+        assert_eq!(vault.set_allocation_bounds(&bounds(&[(6_000, 5_000), (0, 10_000)]), 1_000, 0).unwrap_err(), invalid);
+        assert_eq!(vault.set_allocation_bounds(&bounds(&[(0, 10_001), (0, 10_000)]), 1_000, 0).unwrap_err(), invalid);
+        assert_eq!(vault.set_allocation_bounds(&bounds(&[(6_000, 10_000), (5_000, 10_000)]), 1_000, 0).unwrap_err(), invalid);
+        assert_eq!(vault.set_allocation_bounds(&bounds(&[(0, 4_000), (0, 5_000)]), 1_000, 0).unwrap_err(), invalid);
+        assert_eq!(vault.set_allocation_bounds(&bounds(&[(0, 10_000), (0, 10_000)]), 0, 0).unwrap_err(), invalid);
+        assert_eq!(vault.set_allocation_bounds(&bounds(&[(0, 10_000), (0, 10_000)]), 10_001, 0).unwrap_err(), invalid);
+        assert_eq!(vault.set_allocation_bounds(&bounds(&[(0, 10_000), (0, 10_000)]), 1_000, -1).unwrap_err(), invalid);
+
+        vault.set_allocation_bounds(&bounds(&[(2_000, 6_000), (4_000, 8_000)]), 1_000, 60).unwrap();
+        assert_eq!((vault.strategies[0].min_weight_bps, vault.strategies[0].max_weight_bps), (2_000, 6_000));
+        assert_eq!((vault.strategies[1].min_weight_bps, vault.strategies[1].max_weight_bps), (4_000, 8_000));
+        assert_eq!((vault.max_weight_change_bps, vault.weight_change_cooldown), (1_000, 60));
+
+        // Disabled strategies take no weight, so their bounds don't count towards the full allocation
+        vault.strategies[1].enabled = false;
+        assert_eq!(vault.set_allocation_bounds(&bounds(&[(0, 6_000), (0, 10_000)]), 1_000, 60).unwrap_err(), invalid);
+        vault.set_allocation_bounds(&bounds(&[(0, 10_000), (9_000, 10_000)]), 1_000, 60).unwrap();
+    }
+
+    #[test]
+    fn an_empty_vault_prices_shares_one_to_one() {
+        let vault = vault(&[]);
+        assert_eq!(vault.convert_to_shares(1_000_000, 0).unwrap(), 1_000_000);
+        assert_eq!(vault.assets_to_shares_rounded_up(1_000_000, 0).unwrap(), 1_000_000);
+        assert_eq!(vault.share_price(0), SHARE_PRICE_SCALE as u64);
+        // The virtual assets are never paid out
+        assert_eq!(vault.convert_to_assets(1_000_000, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn conversions_round_in_the_vaults_favor() {
+        // 100 shares backed by 110 USDC, priced with the virtual 1 share and 1 USDC
+        let mut vault = vault(&[]);
+        vault.total_shares = 100_000_000;
+        let total_assets = 110_000_000;
+
+        // 11 * 101 / 111 = 10.009..., 10 * 111 / 101 = 10.99...
+        assert_eq!(vault.convert_to_shares(11, total_assets).unwrap(), 10);
+        assert_eq!(vault.assets_to_shares_rounded_up(11, total_assets).unwrap(), 11);
+        assert_eq!(vault.convert_to_assets(10, total_assets).unwrap(), 10);
+        assert_eq!(vault.convert_to_shares(0, total_assets).unwrap(), 0);
+
+        let mut cases = Cases(0x5eed_0005);
+        for _ in 0..20_000 {
+            vault.total_shares = cases.amount() / 2;
+            let total_assets = cases.amount() / 2;
+            let assets = cases.amount() % (total_assets.max(1));
+            let (Ok(shares), Ok(shares_up)) = (
+                vault.convert_to_shares(assets, total_assets),
+                vault.assets_to_shares_rounded_up(assets, total_assets),
+            ) else {
+                continue;
+            };
+            assert!(shares <= shares_up && shares_up - shares <= 1);
+            // Depositing and redeeming right away never gains anything
+            assert!(vault.convert_to_assets(shares, total_assets).unwrap() <= assets);
+            assert!(vault.convert_to_assets(vault.total_shares, total_assets).unwrap() <= total_assets);
+        }
+    }
+
+    #[test]
+    fn conversions_report_overflow() {
+        let mut vault = vault(&[]);
+        vault.total_shares = u64::MAX / 2;
+        assert_eq!(vault.convert_to_shares(u64::MAX, 0).unwrap_err(), ErrorCode::MathOverflow.into());
+        assert_eq!(vault.assets_to_shares_rounded_up(u64::MAX, 0).unwrap_err(), ErrorCode::MathOverflow.into());
+        // Assets are capped by what the vault holds instead
+        assert_eq!(vault.convert_to_assets(u64::MAX, 5).unwrap(), 5);
+    }
+
+    fn valuation(idle: u64, positions: &[u64]) -> VaultValuation {
+        VaultValuation { idle, positions: positions.to_vec() }
+    }
+
+    #[test]
+    fn withdrawals_come_from_idle_first() {
+        let vault = vault(&[5_000, 5_000]);
+        let plan = valuation(10, &[60, 30]).plan_withdrawal(&vault, 5).unwrap();
+        assert_eq!((plan.from_idle, plan.from_strategies), (5, vec![0, 0]));
+
+        let plan = valuation(10, &[60, 30]).plan_withdrawal(&vault, 0).unwrap();
+        assert_eq!((plan.from_idle, plan.from_strategies), (0, vec![0, 0]));
+    }
+
+    #[test]
+    fn withdrawals_then_come_from_the_most_over_weight_strategy() {
+        let mut vault = vault(&[5_000, 5_000]);
+        // 70 left after the withdrawal, targets of 35 each: only the first strategy is over
+        let plan = valuation(10, &[60, 30]).plan_withdrawal(&vault, 30).unwrap();
+        assert_eq!((plan.from_idle, plan.from_strategies), (10, vec![20, 0]));
+
+        // 40 left, targets of 20: 40 over in the first, 10 in the second
+        let plan = valuation(10, &[60, 30]).plan_withdrawal(&vault, 60).unwrap();
+        assert_eq!((plan.from_idle, plan.from_strategies), (10, vec![40, 10]));
+
+        // 90 left, both 5 over their target of 45
+        let plan = valuation(0, &[50, 50]).plan_withdrawal(&vault, 10).unwrap();
+        assert_eq!(plan.from_strategies, vec![5, 5]);
+
+        // A 20% idle buffer: 96 left, targets of 38, both 12 over and ties keep registry order
+        vault.idle_buffer_bps = 2_000;
+        let plan = valuation(0, &[50, 50]).plan_withdrawal(&vault, 4).unwrap();
+        assert_eq!(plan.from_strategies, vec![4, 0]);
+
+        // A 50% buffer and a disabled strategy, which has no target: 60 left, targets of 0 and 15,
+        // so the disabled strategy is 50 over and the other 15
+        vault.idle_buffer_bps = 5_000;
+        vault.strategies[0].enabled = false;
+        let plan = valuation(0, &[50, 30]).plan_withdrawal(&vault, 20).unwrap();
+        assert_eq!(plan.from_strategies, vec![20, 0]);
+    }
+
+    #[test]
+    fn withdrawals_can_empty_the_vault_but_not_more() {
+        let vault = vault(&[5_000, 5_000]);
+        let plan = valuation(10, &[60, 30]).plan_withdrawal(&vault, 100).unwrap();
+        assert_eq!((plan.from_idle, plan.from_strategies), (10, vec![60, 30]));
+
+        assert_eq!(
+            valuation(10, &[60, 30]).plan_withdrawal(&vault, 101).err(),
+            Some(ErrorCode::InsufficientLiquidity.into())
+        );
+    }
+
+    #[test]
+    fn strategies_with_value_left_are_only_disabled() {
+        let mut vault = vault(&[10_000, 0]);
+        let key = Pubkey::new_unique();
+        with_events(|events| {
+            assert_eq!(vault.remove_strategy(key, 0, 0, 0, events).err(), Some(ErrorCode::StrategyNotEmpty.into()));
+            assert_eq!(vault.remove_strategy(key, 2, 0, 0, events).err(), Some(ErrorCode::StrategyNotFound.into()));
+
+            assert!(!vault.remove_strategy(key, 1, 1, 0, events).unwrap());
+            assert!(!vault.strategies[1].enabled);
+            // Still draining, and nothing left to disable
+            assert_eq!(vault.remove_strategy(key, 1, 1, 0, events).err(), Some(ErrorCode::StrategyNotEmpty.into()));
+        });
+        assert_eq!(vault.strategies.len(), 2);
+    }
+
+    #[test]
+    fn removing_a_worthless_strategy_writes_its_snapshot_off() {
+        let mut vault = vault(&[10_000, 0]);
+        vault.total_shares = 1_000;
+        vault.total_underlying = 1_000;
+        vault.strategies[0].last_value = 700;
+        // Dust the last redemption could not get out, held by an already disabled strategy
+        vault.strategies[1].last_value = 300;
+        vault.strategies[1].enabled = false;
+
+        let removed = with_events(|events| vault.remove_strategy(Pubkey::new_unique(), 1, 0, 0, events)).unwrap();
+        assert!(removed);
+        assert_eq!(vault.strategies.len(), 1);
+        assert_eq!(vault.strategies[0].last_value, 700);
+        assert_eq!(vault.total_underlying, 700);
+        assert!(vault.share_price(vault.total_underlying) < SHARE_PRICE_SCALE as u64);
+    }
+
+    #[test]
+    fn new_admins_and_fee_managers_drop_the_queued_actions() {
+        let mut vault = vault(&[10_000]);
+        vault.next_action_id = 3;
+        assert!(vault.is_action_valid(0));
+
+        // Keepers, guardians and reassigning the same fee manager leave the queue alone
+        vault.set_role(Role::Keeper, Pubkey::new_unique());
+        vault.set_role(Role::Guardian, Pubkey::new_unique());
+        vault.set_role(Role::FeeManager, Pubkey::default());
+        assert!(vault.is_action_valid(0));
+
+        vault.set_role(Role::FeeManager, Pubkey::new_unique());
+        assert!(!vault.is_action_valid(2));
+        assert!(vault.is_action_valid(3));
+
+        vault.next_action_id = 5;
+        vault.set_authority(Pubkey::new_unique());
+        assert!(!vault.is_action_valid(4));
+        assert!(vault.is_action_valid(5));
+        assert_eq!(vault.pending_authority, Pubkey::default());
+    }
+
+    /// 1,000 USDC held by 1,000 shares, `position` of it in the single strategy
+    fn funded(position: u64) -> Vault {
+        let mut vault = vault(&[10_000]);
+        vault.total_shares = 1_000_000_000;
+        vault.total_underlying = 1_000_000_000;
+        vault.strategies[0].last_value = position;
+        vault
+    }
+
+    #[test]
+    fn losses_lower_the_share_price_of_every_holder() {
+        let mut vault = funded(600_000_000);
+        let realized = with_events(|events| vault.mark_to_market(Pubkey::new_unique(), &valuation(400_000_000, &[300_000_000]), 0, events)).unwrap();
+        assert_eq!(realized, -300_000_000);
+
+        // No share is burned, each one is worth 30% less and the mark stays where the loss started
+        assert_eq!((vault.total_shares, vault.total_underlying), (1_000_000_000, 700_000_000));
+        assert_eq!(vault.strategies[0].last_value, 300_000_000);
+        assert_eq!(vault.share_price(vault.total_underlying) * 10 / SHARE_PRICE_SCALE as u64, 7);
+        assert_eq!(vault.high_water_mark, SHARE_PRICE_SCALE as u64);
+        let half = vault.convert_to_assets(500_000_000, vault.total_underlying).unwrap();
+        assert!((349_000_000..=351_000_000).contains(&half));
+    }
+
+    #[test]
+    fn performance_fees_only_apply_above_the_high_water_mark() {
+        let mut vault = funded(600_000_000);
+        vault.performance_fee_bps = 2_000;
+        let key = Pubkey::new_unique();
+        with_events(|events| {
+            vault.mark_to_market(key, &valuation(400_000_000, &[300_000_000]), 0, events).unwrap();
+            // Winning the loss back is not charged
+            vault.mark_to_market(key, &valuation(400_000_000, &[600_000_000]), 1, events).unwrap();
+            assert_eq!(vault.accrued_fee_shares, 0);
+            assert_eq!(vault.high_water_mark, SHARE_PRICE_SCALE as u64);
+
+            // 100 USDC above the mark pays 20 USDC of fee shares
+            vault.mark_to_market(key, &valuation(400_000_000, &[700_000_000]), 2, events).unwrap();
+        });
+        assert_eq!(vault.total_underlying, 1_100_000_000);
+        let fee = vault.convert_to_assets(vault.accrued_fee_shares, vault.total_underlying).unwrap();
+        assert!((19_999_990..=20_000_000).contains(&fee));
+        assert!(vault.high_water_mark > SHARE_PRICE_SCALE as u64);
+    }
+
+    #[test]
+    fn the_high_water_mark_ignores_usdc_outside_the_books() {
+        let mut vault = funded(600_000_000);
+        vault.performance_fee_bps = 2_000;
+        let key = Pubkey::new_unique();
+        with_events(|events| {
+            vault.mark_to_market(key, &valuation(400_000_000, &[300_000_000]), 0, events).unwrap();
+            // 300 USDC sent straight to the vault ATA would put the live value back above the mark,
+            // the booked value is still 200 USDC under it after the strategy earns 100
+            vault.mark_to_market(key, &valuation(700_000_000, &[400_000_000]), 1, events).unwrap();
+        });
+        assert_eq!(vault.total_underlying, 800_000_000);
+        assert_eq!(vault.accrued_fee_shares, 0);
+        assert_eq!(vault.yield_above_high_water_mark(300_000_000), 100_000_000);
+    }
+}
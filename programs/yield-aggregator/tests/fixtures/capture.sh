@@ -0,0 +1,24 @@
+#!/usr/bin/env bash
+# Dumps a mainnet account into the fixture format, with the slot it was read at.
+#
+#   ./capture.sh <file> <address> [rpc url]
+#
+# Writes `solana account --output json` plus a top-level `slot` from the RPC response context.
+set -euo pipefail
+
+file=$1
+address=$2
+rpc=${3:-https://api.mainnet-beta.solana.com}
+
+curl -sf "$rpc" -H 'Content-Type: application/json' -d "{
+  \"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"getAccountInfo\",
+  \"params\": [\"$address\", { \"encoding\": \"base64\", \"commitment\": \"finalized\" }]
+}" | jq --arg pubkey "$address" '
+  if .result.value == null then error("account \($pubkey) not found") else . end
+  | {
+      pubkey: $pubkey,
+      slot: .result.context.slot,
+      account: (.result.value | {data, executable, lamports, owner, rentEpoch, space})
+    }' > "$file"
+
+echo "$file: $address at slot $(jq .slot "$file")"
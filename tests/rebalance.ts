@@ -2,6 +2,7 @@ import * as anchor from "@coral-xyz/anchor";
 import { BN } from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { YieldAggregator } from "../target/types/yield_aggregator";
+import { MockSwap } from "../target/types/mock_swap";
 import {
   getAssociatedTokenAddress,
   TOKEN_PROGRAM_ID,
@@ -10,16 +11,35 @@ import {
   getAssociatedTokenAddressSync,
   getAccount,
   Mint,
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  transfer,
 } from "@solana/spl-token";
 import {
     Connection,
 } from "@solana/web3.js";
-import { airdropTo, confirmTx, setUSDCViaCheatcode, convertJupFTokenToUsdcAmount, convertKaminoTokenToUsdcAmount } from "../client_utility/helper-fns";
+import { airdropTo, confirmTx, setUSDCViaCheatcode, vaultIndexSeed, convertJupFTokenToUsdcAmount, convertKaminoTokenToUsdcAmount, programDataAddress } from "../client_utility/helper-fns";
 import { assert, expect } from "chai";
 import { getDepositReserveLiquidityAccounts, initRpc } from "../client_utility/generate-kamino-accounts";
 import { DEFAULT_RECENT_SLOT_DURATION_MS, KaminoMarket } from "@kamino-finance/klend-sdk";
 import { Address } from "@solana/kit";
 import { invokeRebalance } from "../client_utility/invokeRebalance";
+import { harvest } from "../client_utility/instructionCalls/harvest";
+import { depositAndAllocate } from "../client_utility/instructionCalls/depositAndAllocate";
+import { getCpiEvents } from "../client_utility/instructionCalls/events";
+import { VIRTUAL_ASSETS, VIRTUAL_SHARES } from "../client_utility/constants";
+import { withdrawAssets, withdrawShares } from "../client_utility/instructionCalls/withdraw";
+import { claimFees, setFees } from "../client_utility/instructionCalls/fees";
+import { addStrategy, getStrategyAccounts, refreshReserveInstructions, setStrategyWeight } from "../client_utility/instructionCalls/strategies";
+import { emergencyUnwind, setVaultStatus } from "../client_utility/instructionCalls/vaultStatus";
+import { revokeRole, setRole } from "../client_utility/instructionCalls/roles";
+import { acceptAuthority, cancelAction, executeAction, proposeAuthority, queueAction, runTimelocked, waitOutTimelock } from "../client_utility/instructionCalls/timelock";
+import { rebalance } from "../client_utility/instructionCalls/rebalance";
+import { convertToAssets, getVaultViewAccounts, maxDeposit, maxIn, maxWithdraw, minOut, previewDeposit, previewWithdraw } from "../client_utility/instructionCalls/vaultViews";
+import { compound } from "../client_utility/instructionCalls/compound";
+import { claimJupRewards } from "../client_utility/instructionCalls/claimRewards";
+import { initializeMockSwapPool, mockSwapAccounts } from "../client_utility/instructionCalls/mockSwap";
 
 const USDC_MINT_ADDRESS = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"; // Mainnet
 const KLEND_PROGRAM_ID = new anchor.web3.PublicKey("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD") as any;
@@ -39,6 +59,8 @@ describe("Rebalacing tests", () => {
   anchor.setProvider(provider);
 
   const program = anchor.workspace.yieldAggregator as Program<YieldAggregator>;
+  // Vault PDAs only depend on the mint and index, keep clear of the vaults in yield-aggregator.ts
+  const VAULT_INDEX = 2;
 
   let admin: anchor.web3.Keypair;
   let usdcMint: anchor.web3.PublicKey;
@@ -52,6 +74,10 @@ describe("Rebalacing tests", () => {
   let user: anchor.web3.Keypair;
   let userUsdcAta: anchor.web3.PublicKey;
   let userPositionPda: anchor.web3.PublicKey;
+  let shareMint: anchor.web3.PublicKey;
+  let jupLending: anchor.web3.PublicKey;
+  let kaminoReserve: anchor.web3.PublicKey;
+  let userShareAta: anchor.web3.PublicKey;
 
   before(async () => {
     admin = anchor.web3.Keypair.generate();
@@ -77,6 +103,7 @@ describe("Rebalacing tests", () => {
       connection,
     });
     jupFTokenMint = depositContext.fTokenMint; // Static : 9BEcn9aPEmhSPbPQeFGjidRiEKki46fVQDyPpSQXPA2D
+    jupLending = depositContext.lending;
     const jupFTokenDetails = await getMint(
       connection,
       jupFTokenMint,
@@ -91,9 +118,10 @@ describe("Rebalacing tests", () => {
     const reserve = market.getReserveByMint(usdcMint.toBase58() as Address);
     const ixAccounts = await getDepositReserveLiquidityAccounts(admin.publicKey, reserve.address, kaminoMainMarket.toBase58() as Address, usdcMint.toBase58() as Address);
     kaminoCollateralMint = ixAccounts.reserveCollateralMint;
+    kaminoReserve = new anchor.web3.PublicKey(reserve.address);
 
     [vaultPda] = anchor.web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("vault"), admin.publicKey.toBuffer()],
+      [Buffer.from("vault"), usdcMint.toBuffer(), vaultIndexSeed(VAULT_INDEX)],
       program.programId
     );
 
@@ -162,10 +190,16 @@ describe("Rebalacing tests", () => {
     );
 
     [userPositionPda] = anchor.web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("user_position"), user.publicKey.toBuffer()],
+      [Buffer.from("user_position"), vaultPda.toBuffer(), user.publicKey.toBuffer()],
       program.programId
     );
 
+    [shareMint] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("share_mint"), vaultPda.toBuffer()],
+      program.programId
+    );
+    userShareAta = getAssociatedTokenAddressSync(shareMint, user.publicKey, false);
+
     expect(userUSDTAtaDetails.amount).eq(
       BigInt(userUSDTAmount * 10 ** usdcMintDetails.decimals)
     );
@@ -173,14 +207,14 @@ describe("Rebalacing tests", () => {
 
   it("Initialize vault by Admin", async () => {
     const tx = await program.methods
-      .initializeVault() // 50% = 5000
+      .initializeVault(VAULT_INDEX)
       .accounts({
         admin: admin.publicKey,
+        creator: provider.wallet.publicKey,
+        programData: programDataAddress(program.programId),
         usdcMint: usdcMint,
-        jupFTokenMint: jupFTokenMint,
-        kaminoCollateralMint: kaminoCollateralMint,
         tokenProgram: TOKEN_PROGRAM_ID,
-      })
+      } as any)
       .signers([admin])
       .rpc();
     console.log("Vault initialized", tx);
@@ -190,18 +224,31 @@ describe("Rebalacing tests", () => {
     assert.equal(vaultAccount.authority.toString(), admin.publicKey.toString());
     assert.equal(vaultAccount.usdcMint.toString(), usdcMint.toString());
     assert.equal(vaultAccount.vaultUsdcAta.toString(), vaultUsdcAta.toString());
+    assert.equal(vaultAccount.shareMint.toString(), shareMint.toString());
     assert(vaultAccount.totalShares.eq(new anchor.BN(0)));
     assert(vaultAccount.accPerShare.eq(new anchor.BN(0)));
     assert(vaultAccount.totalUnderlying.eq(new anchor.BN(0)));
-    assert(vaultAccount.jupLendBalance.eq(new anchor.BN(0)));
-    assert(vaultAccount.kaminoBalance.eq(new anchor.BN(0)));
-    assert(vaultAccount.lastJupValue.eq(new anchor.BN(0)));
-    assert(vaultAccount.lastKaminoValue.eq(new anchor.BN(0)));
-    assert.equal(vaultAccount.jupAllocation, 5000);
-    assert.equal(vaultAccount.kaminoAllocation, 5000);
+    assert.equal(vaultAccount.strategies.length, 0);
     assert(vaultAccount.lastUpdateTs.gt(new anchor.BN(0)));
   });
 
+  it("Register JupLend and Kamino as the vault strategies", async () => {
+    const noCap = new anchor.BN("18446744073709551615");
+    await addStrategy(program, { jupLend: {} }, noCap, { admin, vaultPda, positionMint: jupFTokenMint, market: jupLending });
+    await addStrategy(program, { kamino: {} }, noCap, { admin, vaultPda, positionMint: kaminoCollateralMint, market: kaminoReserve });
+    await setStrategyWeight(program, [5000, 5000], { keeper: admin, vaultPda });
+
+    const vaultAccount = await program.account.vault.fetch(vaultPda);
+    assert.equal(vaultAccount.strategies[0].positionTokenAccount.toString(), vaultFTokenAta.toString());
+    assert.equal(vaultAccount.strategies[1].positionTokenAccount.toString(), vaultKaminoTokenAta.toString());
+
+    // CPI accounts are checked against what the markets pointed to at registration
+    assert.equal(vaultAccount.strategies[0].positionMint.toString(), jupFTokenMint.toString());
+    assert.equal(vaultAccount.strategies[1].positionMint.toString(), kaminoCollateralMint.toString());
+    assert.equal(vaultAccount.strategies[1].program.toString(), "KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD");
+    assert.equal(vaultAccount.strategies[1].lendingMarket.toString(), "7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF");
+  });
+
   it("Deposit USDC into vault", async () => {
     const firstDepositAmount = 50 * 10 ** usdcMintDetails.decimals; // 50 USDC
     const secondDepositAmount = 50 * 10 ** usdcMintDetails.decimals; // 50 USDC
@@ -212,13 +259,15 @@ describe("Rebalacing tests", () => {
 
     // First deposit (initialize user position)
     let tx = await program.methods
-      .deposit(new anchor.BN(firstDepositAmount))
+      .deposit(new anchor.BN(firstDepositAmount), new anchor.BN(0))
       .accounts({
         user: user.publicKey,
         vault: vaultPda,
         usdcMint: usdcMint,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
       .signers([user])
       .rpc();
 
@@ -234,27 +283,31 @@ describe("Rebalacing tests", () => {
     // Verify vault USDC balance increased by first amount
     expect(vaultUsdcBalanceAfterFirst.amount).to.equal(initialVaultUsdcBalance.amount + BigInt(firstDepositAmount));
 
-    // Verify user position after first deposit
+    // Verify user position after first deposit, shares are minted as tokens
     let userPosition = await program.account.userPosition.fetch(userPositionPda);
-    expect(userPosition.shares.toNumber()).to.equal(firstDepositAmount);
+    let userShareBalance = await getAccount(provider.connection, userShareAta, "confirmed");
+    expect(userShareBalance.amount).to.equal(BigInt(firstDepositAmount));
     expect(userPosition.rewardDebt.toNumber()).to.equal(0);
     expect(userPosition.vault.equals(vaultPda)).to.be.true;
 
     // Verify vault state after first deposit
     let vault = await program.account.vault.fetch(vaultPda);
     expect(vault.totalShares.toNumber()).to.equal(firstDepositAmount);
+    expect((await getMint(provider.connection, shareMint, "confirmed")).supply).to.equal(BigInt(firstDepositAmount));
     expect(vault.totalUnderlying.toNumber()).to.equal(firstDepositAmount);
     expect(vault.accPerShare.toNumber()).to.equal(0);
 
     // Second deposit (existing user position)
     tx = await program.methods
-      .deposit(new anchor.BN(secondDepositAmount))
+      .deposit(new anchor.BN(secondDepositAmount), new anchor.BN(0))
       .accounts({
         user: user.publicKey,
         vault: vaultPda,
         usdcMint: usdcMint,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
       .signers([user])
       .rpc();
 
@@ -272,13 +325,15 @@ describe("Rebalacing tests", () => {
 
     // Verify user position after second deposit
     userPosition = await program.account.userPosition.fetch(userPositionPda);
-    expect(userPosition.shares.toNumber()).to.equal(firstDepositAmount + secondDepositAmount);
+    userShareBalance = await getAccount(provider.connection, userShareAta, "confirmed");
+    expect(userShareBalance.amount).to.equal(BigInt(firstDepositAmount + secondDepositAmount));
     expect(userPosition.rewardDebt.toNumber()).to.equal(0); // Still 0 since accPerShare is 0
     expect(userPosition.vault.equals(vaultPda)).to.be.true;
 
     // Verify vault state after second deposit
     vault = await program.account.vault.fetch(vaultPda);
     expect(vault.totalShares.toNumber()).to.equal(firstDepositAmount + secondDepositAmount);
+    expect((await getMint(provider.connection, shareMint, "confirmed")).supply).to.equal(BigInt(firstDepositAmount + secondDepositAmount));
     expect(vault.totalUnderlying.toNumber()).to.equal(firstDepositAmount + secondDepositAmount);
     expect(vault.accPerShare.toNumber()).to.equal(0);
   });
@@ -286,27 +341,25 @@ describe("Rebalacing tests", () => {
   it("Rebalancing USDC to jup and kamino for the first time", async () => {
     // access vault data
     await invokeRebalance(program, provider, {
-      admin : admin, 
+      keeper : admin, 
       usdcMint : usdcMint, 
       jupFTokenMint : jupFTokenMint, 
       vaultPda : vaultPda, 
       vaultUsdcAta : vaultUsdcAta, 
-      vaultFTokenAta : vaultFTokenAta, 
-      vaultKaminoTokenAta : vaultKaminoTokenAta, 
     })
     
     // get vault states
     const vaultState = await program.account.vault.fetch(vaultPda, "confirmed");
     // Expect allocation to be 50-50
-    expect(vaultState.jupAllocation).eq(5000);
-    expect(vaultState.kaminoAllocation).eq(5000);
+    expect(vaultState.strategies[0].targetWeightBps).eq(5000);
+    expect(vaultState.strategies[1].targetWeightBps).eq(5000);
 
     // Expect price distributed to be 50-50
     const vaultJupAtaDetails = await getAccount(provider.connection, vaultFTokenAta, "confirmed");
     const vaultKaminoAtaDetails = await getAccount(provider.connection, vaultKaminoTokenAta, "confirmed");
 
     const jupAmountInUSDC = await convertJupFTokenToUsdcAmount(jupFTokenMint, new BN(vaultJupAtaDetails.amount), provider.connection);
-    const kaminoAmountInUSDC = await convertKaminoTokenToUsdcAmount(new BN(vaultKaminoAtaDetails.amount), {usdcMint, admin : admin.publicKey, vaultPda});
+    const kaminoAmountInUSDC = await convertKaminoTokenToUsdcAmount(new BN(vaultKaminoAtaDetails.amount), {usdcMint, vaultPda});
     
     expect(jupAmountInUSDC.sub(new BN(50_000_000)).abs().lte(new BN(100))).to.be.true
     expect(kaminoAmountInUSDC.sub(new BN(50_000_000)).abs().lte(new BN(100))).to.be.true
@@ -315,6 +368,11 @@ describe("Rebalacing tests", () => {
 
     const vaultUSDCAtaDetails = await getAccount(provider.connection, vaultUsdcAta, "confirmed");
     expect(new BN(vaultUSDCAtaDetails.amount).sub(new BN(100_000_000)).abs().lte(new BN(100)));
+
+    // Accounting is derived on-chain from the live balances
+    expect(vaultState.strategies[0].lastValue.sub(jupAmountInUSDC).abs().lte(new BN(100))).to.be.true;
+    expect(vaultState.strategies[1].lastValue.sub(kaminoAmountInUSDC).abs().lte(new BN(100))).to.be.true;
+    expect(vaultState.totalUnderlying.sub(new BN(100_000_000)).abs().lte(new BN(200))).to.be.true;
   });
 
   it("Rebalancing for the second time after user deposits some more USDC to check for lazy balancing", async () => {
@@ -324,26 +382,26 @@ describe("Rebalacing tests", () => {
 
     // User makes their second deposit
     let tx = await program.methods
-      .deposit(new anchor.BN(100 * 10 ** usdcMintDetails.decimals))
+      .deposit(new anchor.BN(100 * 10 ** usdcMintDetails.decimals), new anchor.BN(0))
       .accounts({
         user: user.publicKey,
         vault: vaultPda,
         usdcMint: usdcMint,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
       .signers([user])
       .rpc();
     
     console.log("User depositing their USDC to the protocol : ", tx);
 
     await invokeRebalance(program, provider, {
-      admin : admin, 
+      keeper : admin, 
       usdcMint : usdcMint, 
       jupFTokenMint : jupFTokenMint, 
       vaultPda : vaultPda, 
       vaultUsdcAta : vaultUsdcAta, 
-      vaultFTokenAta : vaultFTokenAta, 
-      vaultKaminoTokenAta : vaultKaminoTokenAta, 
     });
 
     const vaultUSDCAtaDetails = await getAccount(provider.connection, vaultUsdcAta, "confirmed");
@@ -353,73 +411,600 @@ describe("Rebalacing tests", () => {
     const vaultKaminoAtaDetails = await getAccount(provider.connection, vaultKaminoTokenAta, "confirmed");
 
     const jupAmountInUSDC = await convertJupFTokenToUsdcAmount(jupFTokenMint, new BN(vaultJupAtaDetails.amount), provider.connection);
-    const kaminoAmountInUSDC = await convertKaminoTokenToUsdcAmount(new BN(vaultKaminoAtaDetails.amount), {usdcMint, admin : admin.publicKey, vaultPda});
+    const kaminoAmountInUSDC = await convertKaminoTokenToUsdcAmount(new BN(vaultKaminoAtaDetails.amount), {usdcMint, vaultPda});
 
     expect(jupAmountInUSDC.sub(new BN(100_000_000)).abs().lte(new BN(500))).to.be.true
     expect(kaminoAmountInUSDC.sub(new BN(100_000_000)).abs().lte(new BN(500))).to.be.true
   })
 
-  // TODO : This test will instead be calling invokeClientWithdraw.ts function 
-  // Will be removing the withdraw.rs instruction
-  // it("Withdraw USDC from vault", async () => {
-  //   // Get Jup withdraw context
-  //   const { getWithdrawContext } = await import("@jup-ag/lend/earn");
-  //   const jupWithdrawContext = await getWithdrawContext({
-  //     asset: usdcMint,
-  //     connection: provider.connection,
-  //     signer: admin.publicKey,
-  //   });
-
-  //   // Get Kamino accounts
-  //   const kaminoMainMarket = new anchor.web3.PublicKey(
-  //     "7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF"
-  //   );
-  //   const rpc = initRpc("http://localhost:8899");
-  //   const market = await KaminoMarket.load(
-  //     rpc as any,
-  //     kaminoMainMarket.toBase58() as Address,
-  //     DEFAULT_RECENT_SLOT_DURATION_MS
-  //   );
-  //   const reserve = market.getReserveByMint(usdcMint.toBase58() as Address);
-  //   const ixAccounts = await getDepositReserveLiquidityAccounts(
-  //     admin.publicKey,
-  //     reserve.address,
-  //     kaminoMainMarket.toBase58() as Address,
-  //     usdcMint.toBase58() as Address
-  //   );
-
-  //   const tx = await program.methods
-  //     .withdraw(new anchor.BN(10000000)) // 10 USDC
-  //     .accounts({
-  //       user: user.publicKey,
-  //       admin: admin.publicKey,
-  //       usdcMint: usdcMint,
-  //       fTokenMint: jupWithdrawContext.fTokenMint,
-  //       lendingAdmin: jupWithdrawContext.lendingAdmin,
-  //       lending: jupWithdrawContext.lending,
-  //       supplyTokenReservesLiquidity:
-  //         jupWithdrawContext.supplyTokenReservesLiquidity,
-  //       lendingSupplyPositionOnLiquidity:
-  //         jupWithdrawContext.lendingSupplyPositionOnLiquidity,
-  //       rateModel: jupWithdrawContext.rateModel,
-  //       vault: jupWithdrawContext.vault,
-  //       claimAccount: jupWithdrawContext.claimAccount,
-  //       liquidity: jupWithdrawContext.liquidity,
-  //       liquidityProgram: jupWithdrawContext.liquidityProgram,
-  //       rewardsRateModel: jupWithdrawContext.rewardsRateModel,
-  //       reserve: ixAccounts.reserve,
-  //       lendingMarket: ixAccounts.lendingMarket,
-  //       lendingMarketAuthority: ixAccounts.lendingMarketAuthority,
-  //       reserveLiquiditySupply: ixAccounts.reserveLiquiditySupply,
-  //       reserveCollateralMint: ixAccounts.reserveCollateralMint,
-  //       collateralTokenProgram: ixAccounts.collateralTokenProgram,
-  //       liquidityTokenProgram: ixAccounts.liquidityTokenProgram,
-  //       instructionSysvarAccount: ixAccounts.instructionSysvarAccount,
-  //       klendProgram: KLEND_PROGRAM_ID,
-  //     })
-  //     .signers([user])
-  //     .rpc({skipPreflight : true});
-
-  //   console.log("Withdraw transaction:", tx);
-  // });
+  it("Harvesting marks positions to market without admin keys", async () => {
+    const vaultBefore = await program.account.vault.fetch(vaultPda, "confirmed");
+
+    // user is not the vault authority, harvest is permissionless
+    await harvest(program, provider, { caller: user, vaultPda, usdcMint });
+
+    const vaultAfter = await program.account.vault.fetch(vaultPda, "confirmed");
+
+    const vaultJupAtaDetails = await getAccount(provider.connection, vaultFTokenAta, "confirmed");
+    const vaultKaminoAtaDetails = await getAccount(provider.connection, vaultKaminoTokenAta, "confirmed");
+    const jupAmountInUSDC = await convertJupFTokenToUsdcAmount(jupFTokenMint, new BN(vaultJupAtaDetails.amount), provider.connection);
+    const kaminoAmountInUSDC = await convertKaminoTokenToUsdcAmount(new BN(vaultKaminoAtaDetails.amount), {usdcMint, vaultPda});
+
+    expect(vaultAfter.strategies[0].lastValue.sub(jupAmountInUSDC).abs().lte(new BN(100))).to.be.true;
+    expect(vaultAfter.strategies[1].lastValue.sub(kaminoAmountInUSDC).abs().lte(new BN(100))).to.be.true;
+    // Interest only accrues, so the share price can't go down
+    expect(vaultAfter.accPerShare.gte(vaultBefore.accPerShare)).to.be.true;
+    expect(vaultAfter.totalUnderlying.gte(vaultBefore.totalUnderlying.sub(new BN(100)))).to.be.true;
+    expect(vaultAfter.lastUpdateTs.gte(vaultBefore.lastUpdateTs)).to.be.true;
+
+    // The high-water mark follows the booked share price up and never comes back down
+    const sharePrice = vaultAfter.totalUnderlying.mul(new BN(10).pow(new BN(12))).div(vaultAfter.totalShares);
+    expect(vaultAfter.highWaterMark.gte(vaultBefore.highWaterMark)).to.be.true;
+    expect(vaultAfter.highWaterMark.gte(sharePrice)).to.be.true;
+  })
+
+  it("Claiming JupLend rewards accrues them into the exchange price and books them as yield", async () => {
+    const vaultBefore = await program.account.vault.fetch(vaultPda, "confirmed");
+
+    // update_rate is permissionless on JupLend, so is the claim
+    const tx = await claimJupRewards(program, provider, { caller: user, vaultPda, usdcMint });
+
+    const vaultAfter = await program.account.vault.fetch(vaultPda, "confirmed");
+    const harvestEvent = (await getCpiEvents(program, provider, tx)).find((event) => event.name === "harvestEvent")?.data as any;
+    expect(harvestEvent.realizedYield.gten(0)).to.be.true;
+    expect(harvestEvent.accPerShare.eq(vaultAfter.accPerShare)).to.be.true;
+    expect(vaultAfter.accPerShare.gte(vaultBefore.accPerShare)).to.be.true;
+
+    const vaultJupAtaDetails = await getAccount(provider.connection, vaultFTokenAta, "confirmed");
+    const jupAmountInUSDC = await convertJupFTokenToUsdcAmount(jupFTokenMint, new BN(vaultJupAtaDetails.amount), provider.connection);
+    expect(vaultAfter.strategies[0].lastValue.sub(jupAmountInUSDC).abs().lte(new BN(100))).to.be.true;
+  })
+
+  it("Deposit and allocate splits USDC by target weights and keeps the idle buffer", async () => {
+    // keep 10% of the vault idle
+    await program.methods
+      .setIdleBuffer(1000)
+      .accounts({ admin: admin.publicKey, mainVault: vaultPda })
+      .signers([admin])
+      .rpc();
+
+    const vaultBefore = await program.account.vault.fetch(vaultPda, "confirmed");
+    const idleBefore = new BN((await getAccount(provider.connection, vaultUsdcAta, "confirmed")).amount);
+    const totalBefore = vaultBefore.strategies[0].lastValue.add(vaultBefore.strategies[1].lastValue).add(idleBefore);
+
+    const depositAmount = new BN(100_000_000);
+    const view = await getVaultViewAccounts(program, provider, vaultPda);
+    const minSharesOut = minOut(await previewDeposit(program, view, depositAmount), 100);
+    const tx = await depositAndAllocate(program, provider, depositAmount, { user, vaultPda, usdcMint }, minSharesOut);
+
+    const vaultAfter = await program.account.vault.fetch(vaultPda, "confirmed");
+    expect(vaultAfter.idleBufferBps).eq(1000);
+
+    // Every state change is emitted through event CPI, with the vault books after the change
+    const events = await getCpiEvents(program, provider, tx);
+    const depositEvent = events.find((event) => event.name === "depositEvent")?.data as any;
+    expect(depositEvent.amount.eq(depositAmount)).to.be.true;
+    expect(depositEvent.totalShares.eq(vaultAfter.totalShares)).to.be.true;
+    expect(depositEvent.sharePriceAfter.sub(depositEvent.sharePriceBefore).abs().lten(1)).to.be.true;
+    const allocations = events.filter((event) => event.name === "allocationEvent").map((event) => event.data as any);
+    expect(allocations.length).eq(2);
+    allocations.forEach((allocation, i) => {
+      expect(allocation.market.equals(vaultAfter.strategies[i].market)).to.be.true;
+      expect(allocation.positionValueAfter.eq(vaultAfter.strategies[i].lastValue)).to.be.true;
+      // Allocating moves funds without repricing the vault, up to the markets' rounding
+      expect(allocation.sharePriceBefore.sub(depositEvent.sharePriceAfter).abs().lten(1)).to.be.true;
+      expect(allocation.sharePriceAfter.sub(allocation.sharePriceBefore).abs().lten(1)).to.be.true;
+    });
+
+    // Buffer is sized on the vault value after the deposit, the rest of the deposit is deployed 50-50
+    const buffer = totalBefore.add(depositAmount).divn(10);
+    const deployed = BN.min(depositAmount, idleBefore.add(depositAmount).sub(buffer));
+    const idleAfter = new BN((await getAccount(provider.connection, vaultUsdcAta, "confirmed")).amount);
+    expect(idleAfter.sub(idleBefore.add(depositAmount).sub(deployed)).abs().lte(new BN(500))).to.be.true;
+
+    // Allocated value is tracked in the snapshots so harvest doesn't count it as yield
+    const jupAdded = vaultAfter.strategies[0].lastValue.sub(vaultBefore.strategies[0].lastValue);
+    const kaminoAdded = vaultAfter.strategies[1].lastValue.sub(vaultBefore.strategies[1].lastValue);
+    expect(jupAdded.sub(deployed.divn(2)).abs().lte(new BN(500))).to.be.true;
+    expect(kaminoAdded.sub(deployed.divn(2)).abs().lte(new BN(500))).to.be.true;
+  })
+
+  it("Withdraw is paid from the idle buffer before touching the protocols", async () => {
+    const idleBefore = new BN((await getAccount(provider.connection, vaultUsdcAta, "confirmed")).amount);
+    const vaultBefore = await program.account.vault.fetch(vaultPda, "confirmed");
+    const userUsdcBefore = new BN((await getAccount(provider.connection, userUsdcAta, "confirmed")).amount);
+
+    const amount = new BN(10_000_000); // 10 USDC, less than the idle buffer
+    expect(idleBefore.gte(amount)).to.be.true;
+    const view = await getVaultViewAccounts(program, provider, vaultPda);
+    const maxSharesIn = maxIn(await previewWithdraw(program, view, amount), 100);
+    const { withdrawEvent } = await withdrawAssets(program, provider, amount, { user, usdcMint, vaultPda }, maxSharesIn);
+
+    expect(withdrawEvent.amount.eq(amount)).to.be.true;
+    expect(withdrawEvent.fromIdle.eq(amount)).to.be.true;
+    expect(withdrawEvent.fromStrategies[0].toNumber()).eq(0);
+    expect(withdrawEvent.fromStrategies[1].toNumber()).eq(0);
+
+    const vaultAfter = await program.account.vault.fetch(vaultPda, "confirmed");
+    expect(vaultAfter.strategies[0].lastValue.eq(vaultBefore.strategies[0].lastValue)).to.be.true;
+    expect(vaultAfter.strategies[1].lastValue.eq(vaultBefore.strategies[1].lastValue)).to.be.true;
+    expect(vaultAfter.totalUnderlying.eq(vaultBefore.totalUnderlying.sub(amount))).to.be.true;
+
+    const userUsdcAfter = new BN((await getAccount(provider.connection, userUsdcAta, "confirmed")).amount);
+    expect(userUsdcAfter.sub(userUsdcBefore).eq(amount)).to.be.true;
+  });
+
+  it("Withdraw larger than the idle USDC redeems the shortfall from the protocols", async () => {
+    const idleBefore = new BN((await getAccount(provider.connection, vaultUsdcAta, "confirmed")).amount);
+    const amount = idleBefore.add(new BN(20_000_000));
+
+    const view = await getVaultViewAccounts(program, provider, vaultPda);
+    const maxSharesIn = maxIn(await previewWithdraw(program, view, amount), 100);
+    const { withdrawEvent } = await withdrawAssets(program, provider, amount, { user, usdcMint, vaultPda }, maxSharesIn);
+
+    expect(withdrawEvent.amount.eq(amount)).to.be.true;
+    // Redemptions round up, so the protocols cover at least the shortfall
+    const fromStrategies = withdrawEvent.fromStrategies.reduce((sum, value) => sum.add(value), new BN(0));
+    expect(fromStrategies.gte(new BN(20_000_000))).to.be.true;
+    expect(withdrawEvent.fromIdle.add(fromStrategies).gte(amount)).to.be.true;
+  });
+
+  it("Vault views match what deposit and withdraw actually do", async () => {
+    const view = await getVaultViewAccounts(program, provider, vaultPda);
+    const shareBalance = async () => new BN((await getAccount(provider.connection, userShareAta, "confirmed")).amount);
+
+    expect((await maxDeposit(program, view, user.publicKey, shareMint)).eq(new BN("18446744073709551615"))).to.be.true;
+
+    // preview_deposit == shares minted by deposit
+    const depositAmount = new BN(10_000_000);
+    const expectedShares = await previewDeposit(program, view, depositAmount);
+    const sharesBefore = await shareBalance();
+    await program.methods
+      .deposit(depositAmount, new anchor.BN(0))
+      .accounts({
+        user: user.publicKey,
+        vault: vaultPda,
+        usdcMint: usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+    expect((await shareBalance()).sub(sharesBefore).eq(expectedShares)).to.be.true;
+
+    // preview_withdraw == shares burned by withdraw_assets
+    const withdrawAmount = new BN(5_000_000);
+    const expectedBurn = await previewWithdraw(program, view, withdrawAmount);
+    const { withdrawEvent: assetsEvent } = await withdrawAssets(program, provider, withdrawAmount, { user, usdcMint, vaultPda }, expectedBurn);
+    expect(assetsEvent.shares.eq(expectedBurn)).to.be.true;
+    expect(assetsEvent.amount.eq(withdrawAmount)).to.be.true;
+
+    // convert_to_assets == USDC paid by withdraw_shares
+    const sharesToBurn = new BN(3_000_000);
+    const expectedAssets = await convertToAssets(program, view, sharesToBurn);
+    const { withdrawEvent: sharesEvent } = await withdrawShares(program, provider, sharesToBurn, { user, usdcMint, vaultPda }, expectedAssets);
+    expect(sharesEvent.amount.eq(expectedAssets)).to.be.true;
+
+    // max_withdraw is what all of the user's shares are worth
+    const allShares = await shareBalance();
+    const maxAssets = await maxWithdraw(program, view, user.publicKey, shareMint);
+    expect(maxAssets.eq(await convertToAssets(program, view, allShares))).to.be.true;
+  });
+
+  it("Deposits and withdrawals revert once they fall outside the caller's slippage bound", async () => {
+    const view = await getVaultViewAccounts(program, provider, vaultPda);
+    const expectSlippage = async (call: Promise<unknown>) => {
+      try {
+        await call;
+        assert.fail("slippage bound was not enforced");
+      } catch (err) {
+        expect(err.error?.errorCode?.code).eq("SlippageExceeded");
+      }
+    };
+
+    const depositAmount = new BN(10_000_000);
+    const deposit = async (minSharesOut: BN) =>
+      program.methods
+        .deposit(depositAmount, minSharesOut)
+        .accounts({ user: user.publicKey, vault: vaultPda, usdcMint: usdcMint, tokenProgram: TOKEN_PROGRAM_ID })
+        .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+        .preInstructions(await refreshReserveInstructions(program, vaultPda))
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    const expectedShares = await previewDeposit(program, view, depositAmount);
+    await expectSlippage(deposit(expectedShares.muln(2)));
+    await deposit(minOut(expectedShares, 100));
+
+    const withdrawAmount = new BN(5_000_000);
+    const expectedBurn = await previewWithdraw(program, view, withdrawAmount);
+    await expectSlippage(withdrawAssets(program, provider, withdrawAmount, { user, usdcMint, vaultPda }, expectedBurn.divn(2)));
+
+    const shares = new BN(3_000_000);
+    const expectedAssets = await convertToAssets(program, view, shares);
+    await expectSlippage(withdrawShares(program, provider, shares, { user, usdcMint, vaultPda }, expectedAssets.muln(2)));
+    const { withdrawEvent } = await withdrawShares(program, provider, shares, { user, usdcMint, vaultPda }, minOut(expectedAssets, 100));
+    expect(withdrawEvent.amount.gte(minOut(expectedAssets, 100))).to.be.true;
+  });
+
+  it("Withdrawing all shares burns them and closes the user position", async () => {
+    const userShares = new BN((await getAccount(provider.connection, userShareAta, "confirmed")).amount);
+    const vaultBefore = await program.account.vault.fetch(vaultPda, "confirmed");
+
+    const view = await getVaultViewAccounts(program, provider, vaultPda);
+    const minAssetsOut = minOut(await convertToAssets(program, view, userShares), 100);
+    const { withdrawEvent } = await withdrawShares(program, provider, userShares, { user, usdcMint, vaultPda }, minAssetsOut);
+    expect(withdrawEvent.shares.eq(userShares)).to.be.true;
+
+    const vaultAfter = await program.account.vault.fetch(vaultPda, "confirmed");
+    expect(vaultAfter.totalShares.eq(vaultBefore.totalShares.sub(userShares))).to.be.true;
+    expect((await getAccount(provider.connection, userShareAta, "confirmed")).amount).eq(BigInt(0));
+    expect((await getMint(provider.connection, shareMint, "confirmed")).supply).eq(BigInt(vaultAfter.totalShares.toString()));
+
+    // Position is closed once the user holds no shares
+    expect(await provider.connection.getAccountInfo(userPositionPda, "confirmed")).to.be.null;
+  });
+
+  it("Fees are taken as shares booked for the fee recipient and minted on claim", async () => {
+    // Caps are enforced
+    try {
+      await setFees(program, { performanceFeeBps: 3001, managementFeeBps: 0, depositFeeBps: 0, withdrawFeeBps: 0 }, { feeManager: admin, vaultPda });
+      assert.fail("performance fee above the cap was accepted");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("FeeTooHigh");
+    }
+
+    await setFees(program, { performanceFeeBps: 1000, managementFeeBps: 200, depositFeeBps: 50, withdrawFeeBps: 50 }, { feeManager: admin, vaultPda });
+    let vault = await program.account.vault.fetch(vaultPda, "confirmed");
+    expect(vault.feeRecipient.equals(admin.publicKey)).to.be.true;
+    expect(vault.performanceFeeBps).eq(1000);
+    expect(vault.managementFeeBps).eq(200);
+    expect(vault.depositFeeBps).eq(50);
+    expect(vault.withdrawFeeBps).eq(50);
+
+    const view = await getVaultViewAccounts(program, provider, vaultPda);
+    const depositAmount = new BN(10_000_000);
+    const expectedShares = await previewDeposit(program, view, depositAmount);
+    const accruedBefore = vault.accruedFeeShares;
+
+    await program.methods
+      .deposit(depositAmount, new anchor.BN(0))
+      .accounts({
+        user: user.publicKey,
+        vault: vaultPda,
+        usdcMint: usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+
+    // The user gets the deposit net of the 0.5% fee, the fee shares are booked, not minted
+    const userShares = new BN((await getAccount(provider.connection, userShareAta, "confirmed")).amount);
+    expect(userShares.sub(expectedShares).abs().lten(1)).to.be.true;
+    vault = await program.account.vault.fetch(vaultPda, "confirmed");
+    const depositFeeShares = vault.accruedFeeShares.sub(accruedBefore);
+    expect(depositFeeShares.gte(userShares.muln(50).divn(9950))).to.be.true;
+
+    // Withdraw fee shares are handed to the fee recipient instead of being burned. The 0.5% fee is
+    // within the 1% tolerance off `convertToAssets`, which prices the shares without it.
+    const minAssetsOut = minOut(await convertToAssets(program, view, userShares), 100);
+    const { withdrawEvent } = await withdrawShares(program, provider, userShares, { user, usdcMint, vaultPda }, minAssetsOut);
+    expect(withdrawEvent.shares.eq(userShares)).to.be.true;
+    vault = await program.account.vault.fetch(vaultPda, "confirmed");
+    expect(vault.accruedFeeShares.sub(accruedBefore).sub(depositFeeShares).gte(userShares.muln(50).divn(10000))).to.be.true;
+
+    // Stop charging so claiming settles every fee share
+    await setFees(program, { performanceFeeBps: 0, managementFeeBps: 0, depositFeeBps: 0, withdrawFeeBps: 0 }, { feeManager: admin, vaultPda });
+    vault = await program.account.vault.fetch(vaultPda, "confirmed");
+    const toClaim = vault.accruedFeeShares;
+
+    await claimFees(program, { payer: admin, vaultPda, feeRecipient: admin.publicKey });
+    const adminShareAta = getAssociatedTokenAddressSync(shareMint, admin.publicKey, false);
+    expect((await getAccount(provider.connection, adminShareAta, "confirmed")).amount).eq(BigInt(toClaim.toString()));
+
+    vault = await program.account.vault.fetch(vaultPda, "confirmed");
+    expect(vault.accruedFeeShares.toNumber()).eq(0);
+    expect((await getMint(provider.connection, shareMint, "confirmed")).supply).eq(BigInt(vault.totalShares.toString()));
+
+    // The fee recipient never deposited, withdrawing opens its position on the fly and closes it again
+    await setUSDCViaCheatcode(admin.publicKey.toBase58(), 0, usdcMintDetails);
+    const claimedAssets = await convertToAssets(program, view, toClaim);
+    const { withdrawEvent: feeWithdrawEvent } = await withdrawShares(program, provider, toClaim, { user: admin, usdcMint, vaultPda }, minOut(claimedAssets, 100));
+    expect(feeWithdrawEvent.shares.eq(toClaim)).to.be.true;
+    expect((await getAccount(provider.connection, adminShareAta, "confirmed")).amount).eq(BigInt(0));
+    const [adminPositionPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("user_position"), vaultPda.toBuffer(), admin.publicKey.toBuffer()],
+      program.programId
+    );
+    expect(await provider.connection.getAccountInfo(adminPositionPda, "confirmed")).to.be.null;
+  });
+
+  it("Keeper can only move funds, its role can be revoked", async () => {
+    const keeper = anchor.web3.Keypair.generate();
+    await setRole(program, { keeper: {} }, { admin, vaultPda, member: keeper.publicKey });
+
+    // Rebalancing with the current weights is a keeper job
+    const weights = (await program.account.vault.fetch(vaultPda, "confirmed")).strategies.map((strategy) => strategy.targetWeightBps);
+    await setStrategyWeight(program, weights, { keeper, vaultPda });
+
+    // Fees and vault config stay out of reach
+    try {
+      await setFees(program, { performanceFeeBps: 0, managementFeeBps: 0, depositFeeBps: 0, withdrawFeeBps: 0 }, { feeManager: keeper, vaultPda });
+      assert.fail("keeper changed the fees");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("Unauthorized");
+    }
+
+    await revokeRole(program, { keeper: {} }, { admin, vaultPda });
+    try {
+      await setStrategyWeight(program, weights, { keeper, vaultPda });
+      assert.fail("revoked keeper changed the weights");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("Unauthorized");
+    }
+    expect((await program.account.vault.fetch(vaultPda, "confirmed")).keeper.equals(anchor.web3.PublicKey.default)).to.be.true;
+  });
+
+  it("Compound sells reward tokens through the swap program and books the USDC as yield", async () => {
+    const swapProgram = anchor.workspace.mockSwap as Program<MockSwap>;
+
+    // Incentive tokens paid to the vault land in its ATA of the reward mint
+    const rewardMint = await createMint(provider.connection, admin, admin.publicKey, null, 6);
+    const vaultRewardAta = await getOrCreateAssociatedTokenAccount(provider.connection, admin, rewardMint, vaultPda, true);
+    await mintTo(provider.connection, admin, rewardMint, vaultRewardAta.address, admin, 50_000_000);
+
+    // Pool pays 0.5 USDC per reward token
+    const pool = await initializeMockSwapPool(swapProgram, new BN(500_000_000), { payer: admin, inputMint: rewardMint, outputMint: usdcMint });
+    await setUSDCViaCheatcode(admin.publicKey.toBase58(), 100, usdcMintDetails);
+    const adminUsdcAta = getAssociatedTokenAddressSync(usdcMint, admin.publicKey, false);
+    await transfer(provider.connection, admin, adminUsdcAta, pool.outputAta, admin, 100_000_000);
+    const swapAccounts = mockSwapAccounts(swapProgram, pool);
+
+    const amountIn = new BN(20_000_000);
+    const expectedOut = new BN(10_000_000);
+    const expectError = async (call: Promise<unknown>, code: string) => {
+      try {
+        await call;
+        assert.fail(`expected ${code}`);
+      } catch (err) {
+        expect(err.error?.errorCode?.code).eq(code);
+      }
+    };
+
+    // Compounding stays off until a swap program goes through the timelock
+    await expectError(compound(program, provider, amountIn, expectedOut, { keeper: admin, vaultPda, usdcMint, rewardMint }, swapAccounts), "SwapNotConfigured");
+    await runTimelocked(program, { setSwapProgram: { swapProgram: swapProgram.programId } }, { proposer: admin, vaultPda });
+    expect((await program.account.vault.fetch(vaultPda, "confirmed")).swapProgram.equals(swapProgram.programId)).to.be.true;
+
+    // The vault's own capital is not for sale, and the swap has to pay at least the minimum out
+    await expectError(compound(program, provider, amountIn, expectedOut, { keeper: admin, vaultPda, usdcMint, rewardMint: jupFTokenMint }, swapAccounts), "InvalidRewardMint");
+    await expectError(compound(program, provider, amountIn, expectedOut.addn(1), { keeper: admin, vaultPda, usdcMint, rewardMint }, swapAccounts), "SlippageExceeded");
+
+    const vaultBefore = await program.account.vault.fetch(vaultPda, "confirmed");
+    const tx = await compound(program, provider, amountIn, expectedOut, { keeper: admin, vaultPda, usdcMint, rewardMint }, swapAccounts);
+    const vaultAfter = await program.account.vault.fetch(vaultPda, "confirmed");
+
+    expect((await getAccount(provider.connection, vaultRewardAta.address, "confirmed")).amount).eq(BigInt(30_000_000));
+
+    const events = await getCpiEvents(program, provider, tx);
+    const compoundEvent = events.find((event) => event.name === "compoundEvent")?.data as any;
+    expect(compoundEvent.rewardMint.equals(rewardMint)).to.be.true;
+    expect(compoundEvent.rewardAmount.eq(amountIn)).to.be.true;
+    expect(compoundEvent.usdcReceived.eq(expectedOut)).to.be.true;
+    expect(compoundEvent.accPerShare.eq(vaultAfter.accPerShare)).to.be.true;
+
+    // The proceeds are yield: holders get them through acc_per_share, less any performance fee
+    expect(vaultAfter.accPerShare.gt(vaultBefore.accPerShare)).to.be.true;
+    expect(vaultAfter.totalUnderlying.sub(vaultBefore.totalUnderlying).gte(expectedOut)).to.be.true;
+    expect(compoundEvent.sharePriceAfter.gt(compoundEvent.sharePriceBefore)).to.be.true;
+
+    // and are redeployed by the target weights, tracked in the snapshots so harvest doesn't count them twice
+    const allocations = events.filter((event) => event.name === "allocationEvent").map((event) => event.data as any);
+    const allocated = allocations.reduce((sum, allocation) => sum.add(allocation.usdcAmount), new BN(0));
+    expect(allocated.lte(expectedOut)).to.be.true;
+    allocations.forEach((allocation) => {
+      const strategy = vaultAfter.strategies.find((strategy) => strategy.market.equals(allocation.market));
+      expect(allocation.positionValueAfter.eq(strategy.lastValue)).to.be.true;
+    });
+  });
+
+  it("Guardian halts the vault, unwinds the strategies and holders exit pro-rata from idle", async () => {
+    const guardian = anchor.web3.Keypair.generate();
+    await runTimelocked(program, { setGuardian: { guardian: guardian.publicKey } }, { proposer: admin, vaultPda });
+
+    const depositAmount = new BN(20_000_000);
+    const deposit = async () => program.methods
+      .deposit(depositAmount, new anchor.BN(0))
+      .accounts({
+        user: user.publicKey,
+        vault: vaultPda,
+        usdcMint: usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
+      .signers([user])
+      .rpc({ commitment: "confirmed" });
+    await deposit();
+
+    // Deposits are rejected once paused
+    await setVaultStatus(program, { depositsPaused: {} }, { signer: guardian, vaultPda });
+    try {
+      await deposit();
+      assert.fail("deposit went through while paused");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("DepositsPaused");
+    }
+
+    // The guardian can only tighten the status
+    try {
+      await setVaultStatus(program, { active: {} }, { signer: guardian, vaultPda });
+      assert.fail("guardian resumed the vault");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("GuardianCannotResume");
+    }
+
+    // Nothing is redeployed once the vault is withdraw-only
+    await setVaultStatus(program, { withdrawOnly: {} }, { signer: guardian, vaultPda });
+    const vault = await program.account.vault.fetch(vaultPda, "confirmed");
+    try {
+      await rebalance(program, provider, vault.strategies.map((strategy) => strategy.targetWeightBps), { keeper: admin, usdcMint, vaultPda });
+      assert.fail("rebalance went through while withdraw-only");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("AllocationsPaused");
+    }
+
+    // Every position comes back to the vault ATA
+    await emergencyUnwind(program, provider, { signer: guardian, usdcMint, vaultPda });
+    let unwound = await program.account.vault.fetch(vaultPda, "confirmed");
+    const idle = new BN((await getAccount(provider.connection, vaultUsdcAta, "confirmed")).amount);
+    expect(unwound.totalUnderlying.eq(idle)).to.be.true;
+    for (const strategy of unwound.strategies) {
+      expect(strategy.lastValue.toNumber()).eq(0);
+    }
+
+    // In shutdown holders get their pro-rata share of the idle USDC
+    await setVaultStatus(program, { shutdown: {} }, { signer: guardian, vaultPda });
+    const view = await getVaultViewAccounts(program, provider, vaultPda);
+    let userShares = new BN((await getAccount(provider.connection, userShareAta, "confirmed")).amount);
+    unwound = await program.account.vault.fetch(vaultPda, "confirmed");
+    const expected = userShares.mul(idle.addn(VIRTUAL_ASSETS)).div(unwound.totalShares.addn(VIRTUAL_SHARES));
+    const maxAssets = await maxWithdraw(program, view, user.publicKey, shareMint);
+    expect(maxAssets.sub(expected).abs().lten(1)).to.be.true;
+
+    // The views price withdrawals the way shutdown pays them, against the idle USDC
+    const withdrawAmount = new BN(1_000_000);
+    const expectedBurn = await previewWithdraw(program, view, withdrawAmount);
+    const { withdrawEvent: assetsEvent } = await withdrawAssets(program, provider, withdrawAmount, { user, usdcMint, vaultPda }, expectedBurn);
+    expect(assetsEvent.shares.eq(expectedBurn)).to.be.true;
+
+    userShares = new BN((await getAccount(provider.connection, userShareAta, "confirmed")).amount);
+    const remainingAssets = await maxWithdraw(program, view, user.publicKey, shareMint);
+    const { withdrawEvent } = await withdrawShares(program, provider, userShares, { user, usdcMint, vaultPda }, remainingAssets);
+    expect(withdrawEvent.amount.eq(remainingAssets)).to.be.true;
+    expect(withdrawEvent.fromStrategies.every((value) => value.isZero())).to.be.true;
+
+    // Only the admin brings the vault back
+    await setVaultStatus(program, { active: {} }, { signer: admin, vaultPda });
+    expect((await program.account.vault.fetch(vaultPda, "confirmed")).status).to.have.property("active");
+  });
+
+  it("Authority moves in two steps and sensitive changes wait out the timelock", async () => {
+    // Nobody but the proposed key can take over
+    const newAdmin = anchor.web3.Keypair.generate();
+    await proposeAuthority(program, { admin, vaultPda, newAuthority: newAdmin.publicKey });
+    try {
+      await acceptAuthority(program, { newAuthority: user, vaultPda });
+      assert.fail("a key that was not proposed took over");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("Unauthorized");
+    }
+    await acceptAuthority(program, { newAuthority: newAdmin, vaultPda });
+    let vault = await program.account.vault.fetch(vaultPda, "confirmed");
+    expect(vault.authority.equals(newAdmin.publicKey)).to.be.true;
+    expect(vault.pendingAuthority.equals(anchor.web3.PublicKey.default)).to.be.true;
+
+    // Hand it back, the vault address does not depend on the admin
+    await proposeAuthority(program, { admin: newAdmin, vaultPda, newAuthority: admin.publicKey });
+    await acceptAuthority(program, { newAuthority: admin, vaultPda });
+
+    // The guardian cannot be swapped outside the queue
+    try {
+      await setRole(program, { guardian: {} }, { admin, vaultPda, member: newAdmin.publicKey });
+      assert.fail("guardian changed without the timelock");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("TimelockRequired");
+    }
+
+    // The delay cannot go below the minimum
+    try {
+      await queueAction(program, { setTimelockDelay: { delay: new BN(3600) } }, { proposer: admin, vaultPda });
+      assert.fail("a delay below the minimum was queued");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("InvalidTimelockDelay");
+    }
+
+    // Queued changes cannot be executed before the delay has passed
+    const delay = 2 * 24 * 60 * 60;
+    await runTimelocked(program, { setTimelockDelay: { delay: new BN(delay) } }, { proposer: admin, vaultPda });
+    const fees = { performanceFeeBps: 2000, managementFeeBps: 0, depositFeeBps: 0, withdrawFeeBps: 0 };
+    const { queuedAction } = await queueAction(program, { setFees: { fees } }, { proposer: admin, vaultPda });
+    const queued = await program.account.queuedAction.fetch(queuedAction, "confirmed");
+    const now = await provider.connection.getBlockTime(await provider.connection.getSlot("confirmed"));
+    expect(queued.eta.toNumber() - now).to.be.within(delay - 100, delay);
+    try {
+      await executeAction(program, queuedAction, { executor: user, vaultPda });
+      assert.fail("queued action executed before its delay");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("TimelockNotExpired");
+    }
+
+    // The admin holds the guardian role and can drop it, depositors never see the new fees
+    await cancelAction(program, queuedAction, { signer: admin, vaultPda });
+    expect(await provider.connection.getAccountInfo(queuedAction, "confirmed")).to.be.null;
+    vault = await program.account.vault.fetch(vaultPda, "confirmed");
+    expect(vault.performanceFeeBps).eq(0);
+    expect(vault.timelockDelay.toNumber()).eq(delay);
+
+    // A queued strategy is executed with the accounts it was queued with, not the executor's
+    const [queuedStrategy, otherStrategy] = vault.strategies;
+    const { queuedAction: addAction } = await queueAction(
+      program,
+      {
+        addStrategy: {
+          kind: queuedStrategy.kind as any,
+          cap: new BN("18446744073709551615"),
+          positionTokenAccount: queuedStrategy.positionTokenAccount,
+          market: queuedStrategy.market,
+          program: queuedStrategy.program,
+        },
+      },
+      { proposer: admin, vaultPda }
+    );
+    await waitOutTimelock(program, addAction);
+    try {
+      await executeAction(program, addAction, { executor: user, vaultPda }, [
+        { pubkey: otherStrategy.positionTokenAccount, isSigner: false, isWritable: false },
+        { pubkey: otherStrategy.market, isSigner: false, isWritable: false },
+      ]);
+      assert.fail("a strategy was added with accounts other than the queued ones");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("StrategyAccountMismatch");
+    }
+    await cancelAction(program, addAction, { signer: admin, vaultPda });
+
+    // The guardian cannot cancel its own replacement, and the admin can drop it without waiting
+    const guardian = anchor.web3.Keypair.generate();
+    await runTimelocked(program, { setGuardian: { guardian: guardian.publicKey } }, { proposer: admin, vaultPda });
+    const { queuedAction: replaceGuardian } = await queueAction(program, { setGuardian: { guardian: user.publicKey } }, { proposer: admin, vaultPda });
+    try {
+      await cancelAction(program, replaceGuardian, { signer: guardian, vaultPda });
+      assert.fail("the guardian cancelled its own replacement");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("GuardianCannotCancel");
+    }
+    await revokeRole(program, { guardian: {} }, { admin, vaultPda });
+    vault = await program.account.vault.fetch(vaultPda, "confirmed");
+    expect(vault.guardian.equals(anchor.web3.PublicKey.default)).to.be.true;
+    await cancelAction(program, replaceGuardian, { signer: admin, vaultPda });
+
+    // Changes queued by a fee manager are dropped along with it
+    const feeManager = anchor.web3.Keypair.generate();
+    await confirmTx(await airdropTo(feeManager.publicKey, 1, provider.connection), provider.connection);
+    await setRole(program, { feeManager: {} }, { admin, vaultPda, member: feeManager.publicKey });
+    const { queuedAction: feeAction } = await queueAction(program, { setFees: { fees } }, { proposer: feeManager, vaultPda });
+    await revokeRole(program, { feeManager: {} }, { admin, vaultPda });
+    await waitOutTimelock(program, feeAction);
+    try {
+      await executeAction(program, feeAction, { executor: user, vaultPda });
+      assert.fail("a change queued by a former fee manager was executed");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("ActionInvalidated");
+    }
+    await cancelAction(program, feeAction, { signer: admin, vaultPda });
+    expect((await program.account.vault.fetch(vaultPda, "confirmed")).performanceFeeBps).eq(0);
+  });
 })
+
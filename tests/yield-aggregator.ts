@@ -8,16 +8,21 @@ import {
   getMint,
   getAssociatedTokenAddressSync,
   getAccount,
+  createTransferInstruction,
   Mint,
 } from "@solana/spl-token";
 import {
     Connection,
 } from "@solana/web3.js";
-import { airdropTo, confirmTx, setUSDCViaCheatcode } from "../client_utility/helper-fns";
+import { airdropTo, confirmTx, setUSDCViaCheatcode, vaultIndexSeed, programDataAddress } from "../client_utility/helper-fns";
 import { assert, expect } from "chai";
 import { getDepositReserveLiquidityAccounts, initRpc } from "../client_utility/generate-kamino-accounts";
 import { DEFAULT_RECENT_SLOT_DURATION_MS, KaminoMarket } from "@kamino-finance/klend-sdk";
 import { Address } from "@solana/kit";
+import { NO_DEPOSIT_LIMITS, setDepositLimits } from "../client_utility/instructionCalls/depositLimits";
+import { convertToAssets, getVaultViewAccounts, maxDeposit } from "../client_utility/instructionCalls/vaultViews";
+import { addStrategy, getStrategyAccounts, refreshReserveInstructions, removeStrategy, setAllocationBounds, setStrategyWeight } from "../client_utility/instructionCalls/strategies";
+import { encodeLegacyPosition, encodeLegacyVault, legacyPositionAddress, legacyVaultAddress, migrateUserPosition, migrateVault, setProgramAccountViaCheatcode } from "../client_utility/instructionCalls/migration";
 
 const USDC_MINT_ADDRESS = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"; // Mainnet
 const KLEND_PROGRAM_ID = new anchor.web3.PublicKey("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD") as any;
@@ -36,6 +41,7 @@ describe("Yield aggregator instruction tests", () => {
   anchor.setProvider(provider);
 
   const program = anchor.workspace.yieldAggregator as Program<YieldAggregator>;
+  const VAULT_INDEX = 0;
 
   let admin: anchor.web3.Keypair;
   let usdcMint: anchor.web3.PublicKey;
@@ -45,9 +51,14 @@ describe("Yield aggregator instruction tests", () => {
   let vaultUsdcAta: anchor.web3.PublicKey;
   let vaultFTokenAta: anchor.web3.PublicKey;
   let kaminoCollateralMint: anchor.web3.PublicKey;
+  let vaultKaminoTokenAta: anchor.web3.PublicKey;
   let user: anchor.web3.Keypair;
   let userUsdcAta: anchor.web3.PublicKey;
   let userPositionPda: anchor.web3.PublicKey;
+  let shareMint: anchor.web3.PublicKey;
+  let jupLending: anchor.web3.PublicKey;
+  let kaminoReserve: anchor.web3.PublicKey;
+  let userShareAta: anchor.web3.PublicKey;
 
   before(async () => {
     admin = anchor.web3.Keypair.generate();
@@ -73,6 +84,7 @@ describe("Yield aggregator instruction tests", () => {
       connection,
     });
     jupFTokenMint = depositContext.fTokenMint; // Static : 9BEcn9aPEmhSPbPQeFGjidRiEKki46fVQDyPpSQXPA2D
+    jupLending = depositContext.lending;
     const jupFTokenDetails = await getMint(
       connection,
       jupFTokenMint,
@@ -87,9 +99,10 @@ describe("Yield aggregator instruction tests", () => {
     const reserve = market.getReserveByMint(usdcMint.toBase58() as Address);
     const ixAccounts = await getDepositReserveLiquidityAccounts(admin.publicKey, reserve.address, kaminoMainMarket.toBase58() as Address, usdcMint.toBase58() as Address);
     kaminoCollateralMint = ixAccounts.reserveCollateralMint;
+    kaminoReserve = new anchor.web3.PublicKey(reserve.address);
 
     [vaultPda] = anchor.web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("vault"), admin.publicKey.toBuffer()],
+      [Buffer.from("vault"), usdcMint.toBuffer(), vaultIndexSeed(VAULT_INDEX)],
       program.programId
     );
 
@@ -105,6 +118,12 @@ describe("Yield aggregator instruction tests", () => {
       true,
       TOKEN_PROGRAM_ID
     );
+    vaultKaminoTokenAta = await getAssociatedTokenAddress(
+      kaminoCollateralMint,
+      vaultPda,
+      true,
+      TOKEN_PROGRAM_ID
+    );
 
     // Setup user
     user = anchor.web3.Keypair.generate();
@@ -152,9 +171,15 @@ describe("Yield aggregator instruction tests", () => {
     );
 
     [userPositionPda] = anchor.web3.PublicKey.findProgramAddressSync(
-      [Buffer.from("user_position"), user.publicKey.toBuffer()],
+      [Buffer.from("user_position"), vaultPda.toBuffer(), user.publicKey.toBuffer()],
+      program.programId
+    );
+
+    [shareMint] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("share_mint"), vaultPda.toBuffer()],
       program.programId
     );
+    userShareAta = getAssociatedTokenAddressSync(shareMint, user.publicKey, false);
 
     expect(userUSDTAtaDetails.amount).eq(
       BigInt(userUSDTAmount * 10 ** usdcMintDetails.decimals)
@@ -163,14 +188,14 @@ describe("Yield aggregator instruction tests", () => {
 
   it("Initialize vault by Admin", async () => {
     const tx = await program.methods
-      .initializeVault() // 50% = 5000
+      .initializeVault(VAULT_INDEX)
       .accounts({
         admin: admin.publicKey,
+        creator: provider.wallet.publicKey,
+        programData: programDataAddress(program.programId),
         usdcMint: usdcMint,
-        jupFTokenMint: jupFTokenMint,
-        kaminoCollateralMint: kaminoCollateralMint,
         tokenProgram: TOKEN_PROGRAM_ID,
-      })
+      } as any)
       .signers([admin])
       .rpc();
     console.log("Vault initialized", tx);
@@ -180,16 +205,206 @@ describe("Yield aggregator instruction tests", () => {
     assert.equal(vaultAccount.authority.toString(), admin.publicKey.toString());
     assert.equal(vaultAccount.usdcMint.toString(), usdcMint.toString());
     assert.equal(vaultAccount.vaultUsdcAta.toString(), vaultUsdcAta.toString());
+    assert.equal(vaultAccount.shareMint.toString(), shareMint.toString());
     assert(vaultAccount.totalShares.eq(new anchor.BN(0)));
     assert(vaultAccount.accPerShare.eq(new anchor.BN(0)));
     assert(vaultAccount.totalUnderlying.eq(new anchor.BN(0)));
-    assert(vaultAccount.jupLendBalance.eq(new anchor.BN(0)));
-    assert(vaultAccount.kaminoBalance.eq(new anchor.BN(0)));
-    assert(vaultAccount.lastJupValue.eq(new anchor.BN(0)));
-    assert(vaultAccount.lastKaminoValue.eq(new anchor.BN(0)));
-    assert.equal(vaultAccount.jupAllocation, 5000);
-    assert.equal(vaultAccount.kaminoAllocation, 5000);
+    assert.equal(vaultAccount.strategies.length, 0);
     assert(vaultAccount.lastUpdateTs.gt(new anchor.BN(0)));
+    assert.equal(vaultAccount.vaultIndex, VAULT_INDEX);
+  });
+
+  it("Admin registers the JupLend and Kamino strategies", async () => {
+    await addStrategy(program, { jupLend: {} }, new anchor.BN("18446744073709551615"), {
+      admin,
+      vaultPda,
+      positionMint: jupFTokenMint,
+      market: jupLending,
+    });
+    await addStrategy(program, { kamino: {} }, new anchor.BN("18446744073709551615"), {
+      admin,
+      vaultPda,
+      positionMint: kaminoCollateralMint,
+      market: kaminoReserve,
+    });
+
+    // The same market can't be registered twice
+    try {
+      await addStrategy(program, { jupLend: {} }, new anchor.BN(0), {
+        admin,
+        vaultPda,
+        positionMint: jupFTokenMint,
+        market: jupLending,
+      });
+      assert.fail("a duplicate strategy should be rejected");
+    } catch (err) {
+      expect(err.toString()).to.include("StrategyAlreadyExists");
+    }
+
+    // Weights must cover the whole vault
+    try {
+      await setStrategyWeight(program, [5000, 4000], { keeper: admin, vaultPda });
+      assert.fail("weights not summing to 10000 should be rejected");
+    } catch (err) {
+      expect(err.toString()).to.include("InvalidAllocation");
+    }
+    await setStrategyWeight(program, [5000, 5000], { keeper: admin, vaultPda });
+
+    const vaultAccount = await program.account.vault.fetch(vaultPda);
+    assert.equal(vaultAccount.strategies.length, 2);
+    assert("jupLend" in vaultAccount.strategies[0].kind);
+    assert.equal(vaultAccount.strategies[0].positionTokenAccount.toString(), vaultFTokenAta.toString());
+    assert.equal(vaultAccount.strategies[0].market.toString(), jupLending.toString());
+    assert("kamino" in vaultAccount.strategies[1].kind);
+    assert.equal(vaultAccount.strategies[1].positionTokenAccount.toString(), vaultKaminoTokenAta.toString());
+    assert.equal(vaultAccount.strategies[1].market.toString(), kaminoReserve.toString());
+    for (const strategy of vaultAccount.strategies) {
+      assert.equal(strategy.targetWeightBps, 5000);
+      assert(strategy.lastValue.eq(new anchor.BN(0)));
+      assert(strategy.enabled);
+    }
+  });
+
+  it("Weight updates respect the allocation bounds, max change and cooldown", async () => {
+    const expectError = async (call: Promise<unknown>, code: string) => {
+      try {
+        await call;
+        assert.fail(`expected ${code}`);
+      } catch (err) {
+        expect(err.toString()).to.include(code);
+      }
+    };
+
+    // Bounds must leave room for weights summing to 10000
+    await expectError(
+      setAllocationBounds(program, [{ minBps: 6000, maxBps: 8000 }, { minBps: 6000, maxBps: 8000 }], 10000, new anchor.BN(0), { admin, vaultPda }),
+      "InvalidAllocationBounds"
+    );
+
+    await setAllocationBounds(program, [{ minBps: 2000, maxBps: 8000 }, { minBps: 2000, maxBps: 8000 }], 1000, new anchor.BN(0), { admin, vaultPda });
+    await expectError(setStrategyWeight(program, [9000, 1000], { keeper: admin, vaultPda }), "AllocationOutOfBounds");
+    await expectError(setStrategyWeight(program, [7000, 3000], { keeper: admin, vaultPda }), "AllocationChangeTooLarge");
+    await setStrategyWeight(program, [6000, 4000], { keeper: admin, vaultPda });
+
+    // A cooldown blocks the next change, resubmitting the current weights is still fine
+    await setAllocationBounds(program, [{ minBps: 2000, maxBps: 8000 }, { minBps: 2000, maxBps: 8000 }], 1000, new anchor.BN(3600), { admin, vaultPda });
+    await expectError(setStrategyWeight(program, [5000, 5000], { keeper: admin, vaultPda }), "AllocationCooldownActive");
+    await setStrategyWeight(program, [6000, 4000], { keeper: admin, vaultPda });
+
+    // Lift the limits again for the rest of the suite
+    await setAllocationBounds(program, [{ minBps: 0, maxBps: 10000 }, { minBps: 0, maxBps: 10000 }], 10000, new anchor.BN(0), { admin, vaultPda });
+    await setStrategyWeight(program, [5000, 5000], { keeper: admin, vaultPda });
+
+    const vaultAccount = await program.account.vault.fetch(vaultPda);
+    assert.equal(vaultAccount.maxWeightChangeBps, 10000);
+    assert(vaultAccount.weightChangeCooldown.eq(new anchor.BN(0)));
+    assert.equal(vaultAccount.strategies[0].targetWeightBps, 5000);
+    assert.equal(vaultAccount.strategies[1].maxWeightBps, 10000);
+  });
+
+  it("Admin can open a second vault for the same mint under another index", async () => {
+    const [secondVaultPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), usdcMint.toBuffer(), vaultIndexSeed(VAULT_INDEX + 1)],
+      program.programId
+    );
+
+    await program.methods
+      .initializeVault(VAULT_INDEX + 1)
+      .accounts({
+        admin: admin.publicKey,
+        creator: provider.wallet.publicKey,
+        programData: programDataAddress(program.programId),
+        usdcMint: usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      } as any)
+      .signers([admin])
+      .rpc();
+
+    const secondVault = await program.account.vault.fetch(secondVaultPda);
+    assert.equal(secondVault.vaultIndex, VAULT_INDEX + 1);
+    assert.equal(secondVault.timelockDelay.toNumber(), 24 * 60 * 60);
+    assert.equal(secondVault.vaultUsdcAta.toString(), getAssociatedTokenAddressSync(usdcMint, secondVaultPda, true).toString());
+    assert.notEqual(secondVault.shareMint.toString(), shareMint.toString());
+
+    // the first vault is untouched
+    const firstVault = await program.account.vault.fetch(vaultPda);
+    assert(firstVault.totalShares.eq(new anchor.BN(0)));
+  });
+
+  it("Only the upgrade authority can open a vault", async () => {
+    // Anyone else would squat the (mint, index) slot with a vault they control
+    try {
+      await program.methods
+        .initializeVault(VAULT_INDEX + 2)
+        .accounts({
+          admin: user.publicKey,
+          creator: user.publicKey,
+          programData: programDataAddress(program.programId),
+          usdcMint: usdcMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        } as any)
+        .signers([user])
+        .rpc();
+      assert.fail("a vault was opened without the upgrade authority");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("Unauthorized");
+    }
+  });
+
+  it("A donation to a near-empty vault cannot steal from the next depositor", async () => {
+    const [emptyVaultPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), usdcMint.toBuffer(), vaultIndexSeed(VAULT_INDEX + 1)],
+      program.programId
+    );
+    const emptyVaultUsdcAta = getAssociatedTokenAddressSync(usdcMint, emptyVaultPda, true);
+    const [emptyShareMint] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("share_mint"), emptyVaultPda.toBuffer()],
+      program.programId
+    );
+
+    const attacker = anchor.web3.Keypair.generate();
+    await confirmTx(await airdropTo(attacker.publicKey, 10, provider.connection), provider.connection);
+    await setUSDCViaCheatcode(attacker.publicKey.toBase58(), 1000, usdcMintDetails);
+    const attackerUsdcAta = getAssociatedTokenAddressSync(usdcMint, attacker.publicKey);
+    const victim = anchor.web3.Keypair.generate();
+    await confirmTx(await airdropTo(victim.publicKey, 10, provider.connection), provider.connection);
+    await setUSDCViaCheatcode(victim.publicKey.toBase58(), 500, usdcMintDetails);
+
+    const deposit = (depositor: anchor.web3.Keypair, amount: anchor.BN) =>
+      program.methods
+        .deposit(amount, new anchor.BN(0))
+        .accounts({ user: depositor.publicKey, vault: emptyVaultPda, usdcMint, tokenProgram: TOKEN_PROGRAM_ID })
+        .signers([depositor])
+        .rpc({ commitment: "confirmed" });
+    const shareBalance = async (owner: anchor.web3.PublicKey) =>
+      new anchor.BN((await getAccount(provider.connection, getAssociatedTokenAddressSync(emptyShareMint, owner), "confirmed")).amount);
+
+    // The attacker takes the first share, then donates 999 USDC straight to the vault ATA
+    await deposit(attacker, new anchor.BN(1));
+    const donation = new anchor.BN(999 * 10 ** usdcMintDetails.decimals);
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        createTransferInstruction(attackerUsdcAta, emptyVaultUsdcAta, attacker.publicKey, BigInt(donation.toString()))
+      ),
+      [attacker]
+    );
+
+    // Rounding a deposit down to nothing now takes a donation a million times its size
+    try {
+      await deposit(victim, new anchor.BN(100));
+      assert.fail("a deposit worth less than a share minted nothing");
+    } catch (err) {
+      expect(err.error?.errorCode?.code).eq("ZeroShares");
+    }
+
+    // The victim's deposit keeps its value, the donation mostly went to the virtual shares
+    const victimAmount = new anchor.BN(500 * 10 ** usdcMintDetails.decimals);
+    await deposit(victim, victimAmount);
+    const view = await getVaultViewAccounts(program, provider, emptyVaultPda);
+    const victimValue = await convertToAssets(program, view, await shareBalance(victim.publicKey));
+    expect(victimAmount.sub(victimValue).lten(1000)).to.be.true;
+    const attackerValue = await convertToAssets(program, view, await shareBalance(attacker.publicKey));
+    expect(attackerValue.lt(donation.divn(1000))).to.be.true;
   });
 
   it("Deposit USDC into vault", async () => {
@@ -202,13 +417,15 @@ describe("Yield aggregator instruction tests", () => {
 
     // First deposit (initialize user position)
     let tx = await program.methods
-      .deposit(new anchor.BN(firstDepositAmount))
+      .deposit(new anchor.BN(firstDepositAmount), new anchor.BN(0))
       .accounts({
         user: user.publicKey,
         vault: vaultPda,
         usdcMint: usdcMint,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
       .signers([user])
       .rpc();
 
@@ -224,27 +441,31 @@ describe("Yield aggregator instruction tests", () => {
     // Verify vault USDC balance increased by first amount
     expect(vaultUsdcBalanceAfterFirst.amount).to.equal(initialVaultUsdcBalance.amount + BigInt(firstDepositAmount));
 
-    // Verify user position after first deposit
+    // Verify user position after first deposit, shares are minted as tokens
     let userPosition = await program.account.userPosition.fetch(userPositionPda);
-    expect(userPosition.shares.toNumber()).to.equal(firstDepositAmount);
+    let userShareBalance = await getAccount(provider.connection, userShareAta, "confirmed");
+    expect(userShareBalance.amount).to.equal(BigInt(firstDepositAmount));
     expect(userPosition.rewardDebt.toNumber()).to.equal(0);
     expect(userPosition.vault.equals(vaultPda)).to.be.true;
 
     // Verify vault state after first deposit
     let vault = await program.account.vault.fetch(vaultPda);
     expect(vault.totalShares.toNumber()).to.equal(firstDepositAmount);
+    expect((await getMint(provider.connection, shareMint, "confirmed")).supply).to.equal(BigInt(firstDepositAmount));
     expect(vault.totalUnderlying.toNumber()).to.equal(firstDepositAmount);
     expect(vault.accPerShare.toNumber()).to.equal(0);
 
     // Second deposit (existing user position)
     tx = await program.methods
-      .deposit(new anchor.BN(secondDepositAmount))
+      .deposit(new anchor.BN(secondDepositAmount), new anchor.BN(0))
       .accounts({
         user: user.publicKey,
         vault: vaultPda,
         usdcMint: usdcMint,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
       .signers([user])
       .rpc();
 
@@ -262,17 +483,68 @@ describe("Yield aggregator instruction tests", () => {
 
     // Verify user position after second deposit
     userPosition = await program.account.userPosition.fetch(userPositionPda);
-    expect(userPosition.shares.toNumber()).to.equal(firstDepositAmount + secondDepositAmount);
+    userShareBalance = await getAccount(provider.connection, userShareAta, "confirmed");
+    expect(userShareBalance.amount).to.equal(BigInt(firstDepositAmount + secondDepositAmount));
     expect(userPosition.rewardDebt.toNumber()).to.equal(0); // Still 0 since accPerShare is 0
     expect(userPosition.vault.equals(vaultPda)).to.be.true;
 
     // Verify vault state after second deposit
     vault = await program.account.vault.fetch(vaultPda);
     expect(vault.totalShares.toNumber()).to.equal(firstDepositAmount + secondDepositAmount);
+    expect((await getMint(provider.connection, shareMint, "confirmed")).supply).to.equal(BigInt(firstDepositAmount + secondDepositAmount));
     expect(vault.totalUnderlying.toNumber()).to.equal(firstDepositAmount + secondDepositAmount);
     expect(vault.accPerShare.toNumber()).to.equal(0);
   });
 
+  it("Deposits are checked against the minimum, the TVL cap and the per-user cap", async () => {
+    const usdc = (amount: number) => new anchor.BN(amount * 10 ** usdcMintDetails.decimals);
+    const deposit = async (amount: anchor.BN) =>
+      program.methods
+        .deposit(amount, new anchor.BN(0))
+        .accounts({
+          user: user.publicKey,
+          vault: vaultPda,
+          usdcMint: usdcMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+        .preInstructions(await refreshReserveInstructions(program, vaultPda))
+        .signers([user])
+        .rpc();
+    const expectError = async (call: Promise<unknown>, code: string) => {
+      try {
+        await call;
+        assert.fail(`expected ${code}`);
+      } catch (err) {
+        expect(err.toString()).to.include(code);
+      }
+    };
+
+    await expectError(
+      setDepositLimits(program, { depositCap: usdc(150), userDepositCap: usdc(1), minDeposit: usdc(5) }, { admin, vaultPda }),
+      "InvalidDepositLimits"
+    );
+
+    // The user already holds 100 USDC in a 100 USDC vault
+    await setDepositLimits(program, { depositCap: usdc(150), userDepositCap: usdc(120), minDeposit: usdc(5) }, { admin, vaultPda });
+    await expectError(deposit(new anchor.BN(0)), "ZeroAmount");
+    await expectError(deposit(usdc(1)), "DepositTooSmall");
+    await expectError(deposit(usdc(30)), "UserCapExceeded");
+
+    const view = await getVaultViewAccounts(program, provider, vaultPda);
+    expect((await maxDeposit(program, view, user.publicKey, shareMint)).eq(usdc(20))).to.be.true;
+
+    // Lower the TVL cap below the user cap
+    await setDepositLimits(program, { depositCap: usdc(110), userDepositCap: usdc(120), minDeposit: usdc(5) }, { admin, vaultPda });
+    await expectError(deposit(usdc(15)), "VaultCapExceeded");
+    expect((await maxDeposit(program, view, user.publicKey, shareMint)).eq(usdc(10))).to.be.true;
+
+    await setDepositLimits(program, NO_DEPOSIT_LIMITS, { admin, vaultPda });
+    const vaultAccount = await program.account.vault.fetch(vaultPda);
+    assert(vaultAccount.totalUnderlying.eq(usdc(100)));
+    assert(vaultAccount.minDeposit.eq(new anchor.BN(0)));
+  });
+
   it("Depositing USDC from vault_usdc_ata to Jup", async () => {
     // Get Jup accounts
     const { getDepositContext } = await import("@jup-ag/lend/earn");
@@ -298,8 +570,9 @@ describe("Yield aggregator instruction tests", () => {
     const tx = await program.methods
       .jupDeposit(new anchor.BN(depositAmount))
       .accounts({
-        admin: admin.publicKey,
+        keeper: admin.publicKey,
         usdcMint: usdcMint,
+        mainVault: vaultPda,
         fTokenMint: jupDepositContext.fTokenMint,
         lendingAdmin: jupDepositContext.lendingAdmin,
         lending: jupDepositContext.lending,
@@ -311,6 +584,8 @@ describe("Yield aggregator instruction tests", () => {
         liquidityProgram: jupDepositContext.liquidityProgram,
         rewardsRateModel: jupDepositContext.rewardsRateModel,
       })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
       .signers([admin])
       .rpc({skipPreflight: true});
 
@@ -344,8 +619,9 @@ describe("Yield aggregator instruction tests", () => {
     const tx = await program.methods
       .kaminoDeposit(depositAmount)
       .accounts({
-        admin: admin.publicKey,
+        keeper: admin.publicKey,
         usdcMint: usdcMint,
+        mainVault: vaultPda,
         reserve: ixAccounts.reserve,
         lendingMarket: ixAccounts.lendingMarket,
         lendingMarketAuthority: ixAccounts.lendingMarketAuthority,
@@ -356,6 +632,8 @@ describe("Yield aggregator instruction tests", () => {
         instructionSysvarAccount: ixAccounts.instructionSysvarAccount,
         klendProgram: KLEND_PROGRAM_ID,
       })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
       .signers([admin])
       .rpc({skipPreflight: true});
 
@@ -395,8 +673,9 @@ describe("Yield aggregator instruction tests", () => {
     const tx = await program.methods
       .kaminoWithdraw(withdrawAmount)
       .accounts({
-        admin: admin.publicKey,
+        keeper: admin.publicKey,
         usdcMint: usdcMint,
+        mainVault: vaultPda,
         reserve: ixAccounts.reserve,
         lendingMarket: ixAccounts.lendingMarket,
         lendingMarketAuthority: ixAccounts.lendingMarketAuthority,
@@ -407,6 +686,8 @@ describe("Yield aggregator instruction tests", () => {
         instructionSysvarAccount: ixAccounts.instructionSysvarAccount,
         klendProgram: KLEND_PROGRAM_ID,
       })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
       .signers([admin])
       .rpc({skipPreflight: true});
 
@@ -422,6 +703,154 @@ describe("Yield aggregator instruction tests", () => {
     expect(collateralAtaAfter.amount).to.equal(BigInt(0)); // Should have burned all collateral
   });
 
+  it("Admin removes the emptied Kamino strategy", async () => {
+    // A strategy keeps its weight until the admin hands it to the others
+    try {
+      await removeStrategy(program, 1, { admin, vaultPda });
+      assert.fail("a strategy with a target weight should not be removed");
+    } catch (err) {
+      expect(err.toString()).to.include("StrategyNotEmpty");
+    }
+
+    await setStrategyWeight(program, [10000, 0], { keeper: admin, vaultPda });
+    await removeStrategy(program, 1, { admin, vaultPda });
+
+    const vaultAccount = await program.account.vault.fetch(vaultPda);
+    assert.equal(vaultAccount.strategies.length, 1);
+    assert("jupLend" in vaultAccount.strategies[0].kind);
+    assert.equal(vaultAccount.strategies[0].targetWeightBps, 10000);
+  });
+
+  it("A holder that got shares by transfer opens its position on its first deposit", async () => {
+    const holder = anchor.web3.Keypair.generate();
+    await confirmTx(await airdropTo(holder.publicKey, 10, provider.connection), provider.connection);
+    await setUSDCViaCheatcode(holder.publicKey.toBase58(), 10, usdcMintDetails);
+
+    const holderShareAta = getAssociatedTokenAddressSync(shareMint, holder.publicKey, false);
+    const received = BigInt(5 * 10 ** usdcMintDetails.decimals);
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        createAssociatedTokenAccountInstruction(user.publicKey, holderShareAta, holder.publicKey, shareMint),
+        createTransferInstruction(userShareAta, holderShareAta, user.publicKey, received)
+      ),
+      [user]
+    );
+
+    await program.methods
+      .deposit(new anchor.BN(10 ** usdcMintDetails.decimals), new anchor.BN(0))
+      .accounts({
+        user: holder.publicKey,
+        vault: vaultPda,
+        usdcMint: usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(await getStrategyAccounts(program, provider, vaultPda, "valuation"))
+      .preInstructions(await refreshReserveInstructions(program, vaultPda))
+      .signers([holder])
+      .rpc({ commitment: "confirmed" });
+
+    // The position is filled in even though the holder already had shares, and earns nothing
+    // for the time before it existed
+    const [holderPositionPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("user_position"), vaultPda.toBuffer(), holder.publicKey.toBuffer()],
+      program.programId
+    );
+    const position = await program.account.userPosition.fetch(holderPositionPda, "confirmed");
+    expect(position.user.equals(holder.publicKey)).to.be.true;
+    expect(position.vault.equals(vaultPda)).to.be.true;
+    expect(position.pendingRewards.toNumber()).to.equal(0);
+
+    const shares = new anchor.BN((await getAccount(provider.connection, holderShareAta, "confirmed")).amount.toString());
+    expect(shares.gt(new anchor.BN(received.toString()))).to.be.true;
+    const vault = await program.account.vault.fetch(vaultPda, "confirmed");
+    expect(position.rewardDebt.eq(shares.mul(vault.accPerShare))).to.be.true;
+  });
+
+  it("A legacy vault and its positions migrate onto the share mint", async () => {
+    const usdc = (amount: number) => new anchor.BN(amount * 10 ** usdcMintDetails.decimals);
+    const legacyVault = legacyVaultAddress(program.programId, admin.publicKey);
+    const holders = [anchor.web3.Keypair.generate(), anchor.web3.Keypair.generate()];
+    const legacyShares = [usdc(20), usdc(10)];
+    for (const holder of holders) {
+      await confirmTx(await airdropTo(holder.publicKey, 10, provider.connection), provider.connection);
+    }
+
+    // Accounts as the program wrote them before the share mint
+    await setProgramAccountViaCheatcode(connection, program, legacyVault, encodeLegacyVault(program, {
+      authority: admin.publicKey,
+      usdcMint,
+      totalShares: usdc(30),
+      totalUnderlying: usdc(30),
+    }));
+    await setUSDCViaCheatcode(legacyVault.toBase58(), 30, usdcMintDetails);
+    for (const [i, holder] of holders.entries()) {
+      await setProgramAccountViaCheatcode(connection, program, legacyPositionAddress(program.programId, holder.publicKey), encodeLegacyPosition(program, {
+        vault: legacyVault,
+        user: holder.publicKey,
+        shares: legacyShares[i],
+        pendingRewards: new anchor.BN(i + 1),
+      }));
+    }
+
+    // Holders cannot claim anything before the vault itself is migrated
+    try {
+      await migrateUserPosition(program, { user: holders[0], vaultPda, legacyAuthority: admin.publicKey });
+      assert.fail("a legacy vault should be migrated before its positions");
+    } catch (err) {
+      expect(err.toString()).to.include("AccountDiscriminatorMismatch");
+    }
+
+    const vaultBefore = await program.account.vault.fetch(vaultPda);
+    const vaultUsdcBefore = (await getAccount(provider.connection, vaultUsdcAta, "confirmed")).amount;
+    const legacyRent = (await connection.getAccountInfo(legacyVault)).lamports;
+    await migrateVault(program, provider, { authority: admin, vaultPda, usdcMint });
+
+    const legacyUsdcAta = getAssociatedTokenAddressSync(usdcMint, legacyVault, true);
+    expect((await getAccount(provider.connection, legacyUsdcAta, "confirmed")).amount).to.equal(BigInt(0));
+    expect((await getAccount(provider.connection, vaultUsdcAta, "confirmed")).amount).to.equal(vaultUsdcBefore + BigInt(usdc(30).toString()));
+
+    // The legacy account now books the shares owed to its holders, in less space
+    const migrated = await program.account.migratedVault.fetch(legacyVault);
+    expect(migrated.vault.equals(vaultPda)).to.be.true;
+    expect(migrated.legacyShares.eq(usdc(30))).to.be.true;
+    expect(migrated.vaultShares.gtn(0)).to.be.true;
+    expect((await connection.getAccountInfo(legacyVault)).lamports).to.be.lessThan(legacyRent);
+
+    const vaultAfter = await program.account.vault.fetch(vaultPda);
+    expect(vaultAfter.totalShares.sub(vaultBefore.totalShares).eq(migrated.vaultShares)).to.be.true;
+    expect(vaultAfter.totalUnderlying.sub(vaultBefore.totalUnderlying).gte(usdc(30))).to.be.true;
+
+    // A legacy vault migrates once
+    try {
+      await migrateVault(program, provider, { authority: admin, vaultPda, usdcMint });
+      assert.fail("a migrated vault should not migrate again");
+    } catch (err) {
+      expect(err.toString()).to.include("InvalidLegacyAccount");
+    }
+
+    // Each holder gets their pro-rata part, the last one whatever rounding left over
+    const owed = [migrated.vaultShares.mul(legacyShares[0]).div(usdc(30))];
+    owed.push(migrated.vaultShares.sub(owed[0]));
+    for (const [i, holder] of holders.entries()) {
+      await migrateUserPosition(program, { user: holder, vaultPda, legacyAuthority: admin.publicKey });
+
+      const shareBalance = await getAccount(provider.connection, getAssociatedTokenAddressSync(shareMint, holder.publicKey), "confirmed");
+      expect(shareBalance.amount).to.equal(BigInt(owed[i].toString()));
+      expect(await connection.getAccountInfo(legacyPositionAddress(program.programId, holder.publicKey))).to.be.null;
+
+      const [position] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("user_position"), vaultPda.toBuffer(), holder.publicKey.toBuffer()],
+        program.programId
+      );
+      expect((await program.account.userPosition.fetch(position)).pendingRewards.toNumber()).to.equal(i + 1);
+    }
+
+    const drained = await program.account.migratedVault.fetch(legacyVault);
+    expect(drained.legacyShares.toNumber()).to.equal(0);
+    expect(drained.vaultShares.toNumber()).to.equal(0);
+    expect((await getMint(provider.connection, shareMint, "confirmed")).supply <= BigInt(vaultAfter.totalShares.toString())).to.be.true;
+  });
+
   // it("Withdrawing USDC from Jup to main_vault_usdc_ata", async () => {
   //   // Get Jup withdraw accounts
   //   const { getWithdrawContext } = await import("@jup-ag/lend/earn");